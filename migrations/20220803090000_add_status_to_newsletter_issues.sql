@@ -0,0 +1,12 @@
+BEGIN;
+    -- Issues created so far were delivered as soon as they were submitted.
+    ALTER TABLE newsletter_issues ADD COLUMN status TEXT NOT NULL DEFAULT 'sent';
+    ALTER TABLE newsletter_issues ALTER COLUMN status DROP DEFAULT;
+    UPDATE newsletter_issues
+        SET status = 'sending'
+        WHERE newsletter_issue_id IN (SELECT newsletter_issue_id FROM issue_delivery_queue);
+    ALTER TABLE newsletter_issues ADD COLUMN created_at timestamptz NOT NULL DEFAULT now();
+    ALTER TABLE newsletter_issues ADD COLUMN updated_at timestamptz NOT NULL DEFAULT now();
+    -- Drafts have not been published yet.
+    ALTER TABLE newsletter_issues ALTER COLUMN published_at DROP NOT NULL;
+COMMIT;
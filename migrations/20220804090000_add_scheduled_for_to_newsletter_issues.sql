@@ -0,0 +1,2 @@
+-- When a `scheduled` issue should be handed over to the delivery worker.
+ALTER TABLE newsletter_issues ADD COLUMN scheduled_for timestamptz NULL;
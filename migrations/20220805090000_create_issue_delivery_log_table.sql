@@ -0,0 +1,14 @@
+CREATE TABLE issue_delivery_log (
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES newsletter_issues (newsletter_issue_id),
+    subscriber_email TEXT NOT NULL,
+    status TEXT NOT NULL,
+    provider_message_id TEXT NULL,
+    last_error TEXT NULL,
+    updated_at timestamptz NOT NULL,
+    PRIMARY KEY(newsletter_issue_id, subscriber_email)
+);
+-- Deliveries already in flight.
+INSERT INTO issue_delivery_log (newsletter_issue_id, subscriber_email, status, updated_at)
+SELECT newsletter_issue_id, subscriber_email, 'queued', now()
+FROM issue_delivery_queue;
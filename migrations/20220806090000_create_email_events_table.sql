@@ -0,0 +1,18 @@
+BEGIN;
+    -- Bounces and spam complaints reported by our email provider.
+    CREATE TABLE email_events (
+        id uuid NOT NULL,
+        PRIMARY KEY (id),
+        -- Providers retry webhooks: their event id lets us ignore duplicates.
+        provider_event_id TEXT NULL UNIQUE,
+        subscriber_id uuid NULL REFERENCES subscriptions (id),
+        email TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        provider_message_id TEXT NULL,
+        description TEXT NULL,
+        payload TEXT NOT NULL,
+        received_at timestamptz NOT NULL
+    );
+    ALTER TABLE subscriptions ADD COLUMN soft_bounce_count INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE subscriptions ADD COLUMN suppressed_at timestamptz NULL;
+COMMIT;
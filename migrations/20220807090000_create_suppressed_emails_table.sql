@@ -0,0 +1,15 @@
+BEGIN;
+    -- Addresses we must never email again, whichever subscription they belong to.
+    -- Emails are stored lower-cased: providers do not preserve the case we sent.
+    CREATE TABLE suppressed_emails (
+        email TEXT NOT NULL,
+        PRIMARY KEY (email),
+        reason TEXT NOT NULL,
+        source TEXT NOT NULL,
+        suppressed_at timestamptz NOT NULL
+    );
+    INSERT INTO suppressed_emails (email, reason, source, suppressed_at)
+    SELECT DISTINCT ON (lower(email)) lower(email), 'Suppressed subscriber', 'email_provider', suppressed_at
+    FROM subscriptions
+    WHERE status = 'suppressed' AND suppressed_at IS NOT NULL;
+COMMIT;
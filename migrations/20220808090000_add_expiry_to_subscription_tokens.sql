@@ -0,0 +1,5 @@
+BEGIN;
+    -- Tokens issued before this migration start their lifetime now.
+    ALTER TABLE subscription_tokens ADD COLUMN created_at timestamptz NOT NULL DEFAULT now();
+    ALTER TABLE subscription_tokens ADD COLUMN consumed_at timestamptz NULL;
+COMMIT;
@@ -0,0 +1,8 @@
+-- Requests to resend a confirmation email, kept around to rate-limit them.
+CREATE TABLE confirmation_resend_attempts (
+    email TEXT NOT NULL,
+    ip_address TEXT NOT NULL,
+    attempted_at timestamptz NOT NULL
+);
+CREATE INDEX confirmation_resend_attempts_attempted_at_idx
+    ON confirmation_resend_attempts (attempted_at);
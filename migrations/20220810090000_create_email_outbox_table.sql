@@ -0,0 +1,16 @@
+-- Transactional emails (e.g. subscription confirmations), written in the same
+-- transaction as the change that triggers them and sent by the background worker.
+CREATE TABLE email_outbox (
+    id uuid NOT NULL,
+    PRIMARY KEY (id),
+    recipient TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    html_body TEXT NOT NULL,
+    text_body TEXT NOT NULL,
+    n_retries SMALLINT NOT NULL DEFAULT 0,
+    execute_after timestamptz NOT NULL DEFAULT now(),
+    last_error TEXT NULL,
+    -- Set once we give up: the email stays around to be inspected.
+    failed_at timestamptz NULL,
+    created_at timestamptz NOT NULL
+);
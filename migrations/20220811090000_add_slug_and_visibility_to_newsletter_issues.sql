@@ -0,0 +1,9 @@
+-- Published issues are listed in the public archive under their slug,
+-- unless an editor keeps them private.
+ALTER TABLE newsletter_issues ADD COLUMN slug TEXT NULL UNIQUE;
+ALTER TABLE newsletter_issues ADD COLUMN is_private BOOLEAN NOT NULL DEFAULT false;
+-- Links to issues published so far pointed to `/issues/{newsletter_issue_id}`:
+-- keep them working.
+UPDATE newsletter_issues
+    SET slug = newsletter_issue_id::text
+    WHERE published_at IS NOT NULL;
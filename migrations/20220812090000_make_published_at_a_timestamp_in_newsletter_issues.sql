@@ -0,0 +1,4 @@
+-- `published_at` has always been written with `now()`, whose text
+-- representation Postgres can read back as a timestamp.
+ALTER TABLE newsletter_issues
+    ALTER COLUMN published_at TYPE timestamptz USING published_at::timestamptz;
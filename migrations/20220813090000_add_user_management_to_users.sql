@@ -0,0 +1,19 @@
+-- Admins can invite colleagues: invited users have no password until they
+-- follow the set-password link we email them.
+ALTER TABLE users ADD COLUMN email TEXT NULL UNIQUE;
+ALTER TABLE users ADD COLUMN is_active BOOLEAN NOT NULL DEFAULT true;
+ALTER TABLE users ADD COLUMN created_at timestamptz NOT NULL DEFAULT now();
+ALTER TABLE users ALTER COLUMN password_hash DROP NOT NULL;
+
+CREATE TABLE set_password_tokens (
+    set_password_token TEXT NOT NULL,
+    PRIMARY KEY (set_password_token),
+    user_id uuid NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+    created_at timestamptz NOT NULL,
+    consumed_at timestamptz NULL
+);
+
+-- Deleting a user forgets the responses saved on their behalf.
+ALTER TABLE idempotency DROP CONSTRAINT idempotency_user_id_fkey;
+ALTER TABLE idempotency ADD CONSTRAINT idempotency_user_id_fkey
+    FOREIGN KEY (user_id) REFERENCES users (user_id) ON DELETE CASCADE;
@@ -0,0 +1,5 @@
+-- Users created so far could do everything: they keep doing so as owners.
+ALTER TABLE users ADD COLUMN role TEXT NOT NULL DEFAULT 'owner'
+    CHECK (role IN ('viewer', 'editor', 'publisher', 'owner'));
+-- Invitations pick a role explicitly.
+ALTER TABLE users ALTER COLUMN role DROP DEFAULT;
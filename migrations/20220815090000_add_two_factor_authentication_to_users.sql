@@ -0,0 +1,13 @@
+-- Optional TOTP two-factor authentication. The last time step a code was
+-- accepted for is kept, so that a code cannot be used twice.
+ALTER TABLE users ADD COLUMN totp_secret TEXT NULL;
+ALTER TABLE users ADD COLUMN totp_last_used_step BIGINT NULL;
+
+-- Single-use codes to log in without the authenticator app. Only their
+-- hash is stored.
+CREATE TABLE totp_recovery_codes (
+    user_id uuid NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+    code_hash TEXT NOT NULL,
+    PRIMARY KEY (user_id, code_hash),
+    used_at timestamptz NULL
+);
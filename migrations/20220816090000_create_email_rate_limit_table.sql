@@ -0,0 +1,10 @@
+-- The token bucket shared by every worker process: the quota of our email
+-- provider is the same however many instances we run. A single row, created
+-- on first use.
+CREATE TABLE email_rate_limit (
+    id BOOLEAN NOT NULL DEFAULT TRUE,
+    PRIMARY KEY (id),
+    CHECK (id),
+    tokens DOUBLE PRECISION NOT NULL,
+    last_refill timestamptz NOT NULL
+);
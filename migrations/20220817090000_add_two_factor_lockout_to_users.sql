@@ -0,0 +1,4 @@
+-- Wrong two-factor codes are counted per user rather than per session:
+-- logging in again must not buy an attacker a fresh set of guesses.
+ALTER TABLE users ADD COLUMN two_factor_failed_attempts INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE users ADD COLUMN two_factor_locked_until timestamptz NULL;
@@ -1,11 +1,14 @@
+use crate::domain::UserRole;
 use crate::session_state::TypedSession;
 use crate::utils;
 use actix_web::body::MessageBody;
 use actix_web::dev::{ServiceRequest, ServiceResponse};
 use actix_web::error::InternalError;
-use actix_web::FromRequest;
 use actix_web::HttpMessage;
+use actix_web::{web, FromRequest};
 use actix_web_lab::middleware::Next;
+use anyhow::Context;
+use sqlx::PgPool;
 use std::fmt::Debug;
 use std::ops::Deref;
 use uuid::Uuid;
@@ -27,6 +30,10 @@ impl Deref for UserId {
     }
 }
 
+/// Sessions outlive changes to the user: someone who was deactivated, deleted
+/// or asked to reset their password is logged out on their next request.
+/// The user's role is loaded along with their id, for handlers to check
+/// permissions against.
 pub async fn reject_anonymous_users(
     mut req: ServiceRequest,
     next: Next<impl MessageBody>,
@@ -35,15 +42,51 @@ pub async fn reject_anonymous_users(
         let (http_request, payload) = req.parts_mut();
         TypedSession::from_request(http_request, payload).await
     }?;
-    match session.get_user_id().map_err(utils::e500)? {
-        Some(user_id) => {
-            req.extensions_mut().insert(UserId(user_id));
-            next.call(req).await
-        }
+    let user_id = match session.get_user_id().map_err(utils::e500)? {
+        Some(user_id) => user_id,
         None => {
             let response = utils::see_other("/login");
             let e = anyhow::anyhow!("The user has not logged in");
-            Err(InternalError::from_response(e, response).into())
+            return Err(InternalError::from_response(e, response).into());
         }
-    }
+    };
+    let pool = req
+        .app_data::<web::Data<PgPool>>()
+        .expect("The connection pool is registered as application data.");
+    let role = match get_active_user_role(user_id, pool)
+        .await
+        .map_err(utils::e500)?
+    {
+        Some(role) => role,
+        None => {
+            session.log_out();
+            let response = utils::see_other("/login");
+            let e = anyhow::anyhow!("The user cannot log in anymore");
+            return Err(InternalError::from_response(e, response).into());
+        }
+    };
+    req.extensions_mut().insert(UserId(user_id));
+    req.extensions_mut().insert(role);
+    next.call(req).await
+}
+
+/// `None` if the user cannot log in anymore.
+#[tracing::instrument(name = "Get the role of an active user", skip(pool))]
+async fn get_active_user_role(
+    user_id: Uuid,
+    pool: &PgPool,
+) -> Result<Option<UserRole>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT role
+        FROM users
+        WHERE user_id = $1 AND is_active AND password_hash IS NOT NULL
+        "#,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to perform a query to retrieve the user's role.")?;
+    row.map(|row| UserRole::try_from(row.role).map_err(anyhow::Error::msg))
+        .transpose()
 }
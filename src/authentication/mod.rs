@@ -1,4 +1,10 @@
 mod middleware;
 mod password;
+mod permissions;
+mod set_password;
+mod two_factor;
 pub use middleware::*;
 pub use password::*;
+pub use permissions::*;
+pub use set_password::*;
+pub use two_factor::*;
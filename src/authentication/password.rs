@@ -69,6 +69,8 @@ fn verify_password_hash(
         .map_err(AuthError::InvalidCredentials)
 }
 
+/// Deactivated users, and invited users who have not set a password yet,
+/// have no credentials to log in with.
 #[tracing::instrument(name = "Get stored credentials", skip(username, pool))]
 pub async fn get_stored_credentials(
     username: &str,
@@ -76,9 +78,9 @@ pub async fn get_stored_credentials(
 ) -> Result<Option<(uuid::Uuid, Secret<String>)>, anyhow::Error> {
     let row: Option<_> = sqlx::query!(
         r#"
-        SELECT user_id, password_hash 
-        FROM users 
-        WHERE username = $1
+        SELECT user_id, password_hash AS "password_hash!"
+        FROM users
+        WHERE username = $1 AND is_active AND password_hash IS NOT NULL
         "#,
         username
     )
@@ -113,7 +115,7 @@ pub async fn change_password(
     Ok(())
 }
 
-fn compute_password_hash(password: Secret<String>) -> Result<Secret<String>, anyhow::Error> {
+pub fn compute_password_hash(password: Secret<String>) -> Result<Secret<String>, anyhow::Error> {
     let salt = SaltString::generate(&mut rand::thread_rng());
     let password_hash = Argon2::new(
         Algorithm::Argon2id,
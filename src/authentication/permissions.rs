@@ -0,0 +1,29 @@
+use crate::domain::{Permission, UserRole};
+use actix_web::error::InternalError;
+use actix_web::http::header::ContentType;
+use actix_web::HttpResponse;
+
+/// Turn away, with a 403 page, users whose role does not grant `permission`.
+pub fn require_permission(role: &UserRole, permission: Permission) -> Result<(), actix_web::Error> {
+    if role.can(permission) {
+        return Ok(());
+    }
+    let response = HttpResponse::Forbidden()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Forbidden</title>
+</head>
+    <body>
+        <p>Your role ({role}) does not allow you to do this.
+        Ask an owner if you need a different role.</p>
+        <p><a href="/admin/dashboard">&lt;-Back</a></p>
+    </body>
+</html>"#,
+        ));
+    let e = anyhow::anyhow!("The {} role does not grant {:?}", role, permission);
+    Err(InternalError::from_response(e, response).into())
+}
@@ -0,0 +1,164 @@
+use crate::authentication::compute_password_hash;
+use crate::domain::SubscriberEmail;
+use crate::email_outbox::{enqueue_email, OutgoingEmail};
+use crate::telemetry::spawn_blocking_with_tracing;
+use anyhow::Context;
+use htmlescape::encode_minimal;
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+use secrecy::{ExposeSecret, Secret};
+use sqlx::{PgExecutor, Postgres, Transaction};
+use uuid::Uuid;
+
+/// How long a user has to follow the link we email them.
+const SET_PASSWORD_TOKEN_TTL_HOURS: i32 = 48;
+
+/// Why a user is asked to choose a password.
+#[derive(Debug, Clone, Copy)]
+pub enum SetPasswordReason {
+    Invitation,
+    PasswordReset,
+}
+
+fn generate_set_password_token() -> String {
+    let mut rng = thread_rng();
+    std::iter::repeat_with(|| rng.sample(Alphanumeric))
+        .map(char::from)
+        .take(25)
+        .collect()
+}
+
+/// Email `recipient` a link to choose their password.
+/// Links sent earlier to the same user stop working.
+#[tracing::instrument(name = "Send a set-password link", skip(transaction, base_url))]
+pub async fn send_set_password_link(
+    transaction: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+    username: &str,
+    recipient: &SubscriberEmail,
+    base_url: &str,
+    reason: SetPasswordReason,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"DELETE FROM set_password_tokens WHERE user_id = $1"#,
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+    let set_password_token = generate_set_password_token();
+    sqlx::query!(
+        r#"
+        INSERT INTO set_password_tokens (set_password_token, user_id, created_at)
+        VALUES ($1, $2, now())
+        "#,
+        set_password_token,
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+    let set_password_link = format!(
+        "{}/users/set_password?token={}",
+        base_url, set_password_token
+    );
+    let (subject, intro) = match reason {
+        SetPasswordReason::Invitation => (
+            "You have been invited to manage the newsletter",
+            "you have been invited to help us run the newsletter.",
+        ),
+        SetPasswordReason::PasswordReset => (
+            "Choose a new password",
+            "an admin has asked you to choose a new password.",
+        ),
+    };
+    let plain_body = format!(
+        "Hi {}, {}\nVisit {} to choose your password. The link expires in {} hours.",
+        username, intro, set_password_link, SET_PASSWORD_TOKEN_TTL_HOURS
+    );
+    let html_body = format!(
+        "Hi {}, {}<br />\
+Click <a href=\"{}\">here</a> to choose your password. The link expires in {} hours.",
+        encode_minimal(username),
+        intro,
+        set_password_link,
+        SET_PASSWORD_TOKEN_TTL_HOURS
+    );
+    enqueue_email(
+        transaction,
+        OutgoingEmail {
+            recipient,
+            subject,
+            html_body: &html_body,
+            text_body: &plain_body,
+        },
+    )
+    .await
+}
+
+pub struct SetPasswordToken {
+    pub user_id: Uuid,
+    pub username: String,
+}
+
+/// The user a set-password token belongs to, if the token can still be used:
+/// it works once, for `SET_PASSWORD_TOKEN_TTL_HOURS`, and only for active users.
+#[tracing::instrument(name = "Get set-password token", skip_all)]
+pub async fn get_set_password_token<'c>(
+    executor: impl PgExecutor<'c>,
+    set_password_token: &str,
+) -> Result<Option<SetPasswordToken>, sqlx::Error> {
+    sqlx::query_as!(
+        SetPasswordToken,
+        r#"
+        SELECT u.user_id, u.username
+        FROM set_password_tokens t
+        JOIN users u ON u.user_id = t.user_id
+        WHERE
+            t.set_password_token = $1 AND
+            t.consumed_at IS NULL AND
+            t.created_at > now() - make_interval(hours => $2) AND
+            u.is_active
+        FOR UPDATE
+        "#,
+        set_password_token,
+        SET_PASSWORD_TOKEN_TTL_HOURS
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+/// Set the password of the user `set_password_token` belongs to, and make
+/// sure the token cannot be used again.
+#[tracing::instrument(name = "Set password with token", skip_all)]
+pub async fn set_password_with_token(
+    transaction: &mut Transaction<'_, Postgres>,
+    set_password_token: &str,
+    user_id: Uuid,
+    password: Secret<String>,
+) -> Result<(), anyhow::Error> {
+    let password_hash = spawn_blocking_with_tracing(move || compute_password_hash(password))
+        .await?
+        .context("Failed to hash password")?;
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET password_hash = $1
+        WHERE user_id = $2
+        "#,
+        password_hash.expose_secret(),
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to store the user's password in the database.")?;
+    sqlx::query!(
+        r#"
+        UPDATE set_password_tokens SET consumed_at = now()
+        WHERE set_password_token = $1
+        "#,
+        set_password_token
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to mark the set-password token as used.")?;
+    Ok(())
+}
@@ -0,0 +1,307 @@
+use crate::domain::TotpSecret;
+use anyhow::Context;
+use chrono::Utc;
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+const RECOVERY_CODES_COUNT: usize = 10;
+/// After that many wrong codes in a row, codes are refused for a while.
+const MAX_FAILED_ATTEMPTS: i32 = 5;
+const LOCKOUT_MINUTES: i64 = 15;
+
+pub enum SecondFactorOutcome {
+    Valid,
+    Invalid,
+    /// Too many wrong codes: none is checked until the lockout expires.
+    LockedOut,
+}
+
+#[tracing::instrument(name = "Get TOTP secret", skip(pool))]
+pub async fn get_totp_secret(
+    user_id: Uuid,
+    pool: &PgPool,
+) -> Result<Option<TotpSecret>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"SELECT totp_secret FROM users WHERE user_id = $1"#,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to perform a query to retrieve the TOTP secret.")?;
+    row.and_then(|row| row.totp_secret)
+        .map(|secret| TotpSecret::parse(&secret).map_err(anyhow::Error::msg))
+        .transpose()
+}
+
+/// Turn on two-factor authentication with a secret the user just proved
+/// they hold, with a code for `time_step`.
+/// Returns the recovery codes, which we only keep a hash of.
+#[tracing::instrument(name = "Enable two-factor authentication", skip(secret, pool))]
+pub async fn enable_two_factor(
+    user_id: Uuid,
+    secret: &TotpSecret,
+    time_step: i64,
+    pool: &PgPool,
+) -> Result<Vec<Secret<String>>, anyhow::Error> {
+    let recovery_codes: Vec<_> = std::iter::repeat_with(generate_recovery_code)
+        .take(RECOVERY_CODES_COUNT)
+        .collect();
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    let encoded_secret = secret.to_base32();
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET
+            totp_secret = $2,
+            totp_last_used_step = $3,
+            two_factor_failed_attempts = 0,
+            two_factor_locked_until = NULL
+        WHERE user_id = $1
+        "#,
+        user_id,
+        encoded_secret.expose_secret(),
+        time_step
+    )
+    .execute(&mut transaction)
+    .await
+    .context("Failed to store the TOTP secret.")?;
+    sqlx::query!(
+        r#"DELETE FROM totp_recovery_codes WHERE user_id = $1"#,
+        user_id
+    )
+    .execute(&mut transaction)
+    .await
+    .context("Failed to delete old recovery codes.")?;
+    for code in &recovery_codes {
+        sqlx::query!(
+            r#"
+            INSERT INTO totp_recovery_codes (user_id, code_hash)
+            VALUES ($1, $2)
+            "#,
+            user_id,
+            hash_recovery_code(code.expose_secret())
+        )
+        .execute(&mut transaction)
+        .await
+        .context("Failed to store a recovery code.")?;
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to enable two-factor authentication.")?;
+    Ok(recovery_codes)
+}
+
+#[tracing::instrument(name = "Disable two-factor authentication", skip(pool))]
+pub async fn disable_two_factor(user_id: Uuid, pool: &PgPool) -> Result<(), anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET
+            totp_secret = NULL,
+            totp_last_used_step = NULL,
+            two_factor_failed_attempts = 0,
+            two_factor_locked_until = NULL
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .execute(&mut transaction)
+    .await
+    .context("Failed to remove the TOTP secret.")?;
+    sqlx::query!(
+        r#"DELETE FROM totp_recovery_codes WHERE user_id = $1"#,
+        user_id
+    )
+    .execute(&mut transaction)
+    .await
+    .context("Failed to delete recovery codes.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to disable two-factor authentication.")?;
+    Ok(())
+}
+
+/// Check a code from the user's authenticator app, or one of their recovery
+/// codes. Either way, a code is only accepted once.
+///
+/// Wrong codes are counted against the user, whatever the session they come
+/// from: after `MAX_FAILED_ATTEMPTS` in a row, codes are refused for
+/// `LOCKOUT_MINUTES`.
+#[tracing::instrument(name = "Verify second factor", skip(code, pool))]
+pub async fn verify_second_factor(
+    user_id: Uuid,
+    code: &Secret<String>,
+    pool: &PgPool,
+) -> Result<SecondFactorOutcome, anyhow::Error> {
+    let code: String = code
+        .expose_secret()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    // Locking the row makes concurrent guesses wait for their turn.
+    let user = sqlx::query!(
+        r#"
+        SELECT totp_secret, two_factor_failed_attempts, two_factor_locked_until
+        FROM users
+        WHERE user_id = $1
+        FOR UPDATE
+        "#,
+        user_id
+    )
+    .fetch_one(&mut transaction)
+    .await
+    .context("Failed to perform a query to retrieve the two-factor authentication state.")?;
+    if matches!(user.two_factor_locked_until, Some(locked_until) if locked_until > Utc::now()) {
+        return Ok(SecondFactorOutcome::LockedOut);
+    }
+    let secret = user
+        .totp_secret
+        .map(|secret| TotpSecret::parse(&secret).map_err(anyhow::Error::msg))
+        .transpose()?;
+    let is_valid = match secret {
+        Some(secret) if code.chars().all(|c| c.is_ascii_digit()) => {
+            verify_totp_code(&mut transaction, user_id, &secret, &code).await?
+        }
+        Some(_) => use_recovery_code(&mut transaction, user_id, &code).await?,
+        None => false,
+    };
+    let (failed_attempts, outcome) = if is_valid {
+        (0, SecondFactorOutcome::Valid)
+    } else if user.two_factor_failed_attempts + 1 >= MAX_FAILED_ATTEMPTS {
+        (
+            user.two_factor_failed_attempts + 1,
+            SecondFactorOutcome::LockedOut,
+        )
+    } else {
+        (
+            user.two_factor_failed_attempts + 1,
+            SecondFactorOutcome::Invalid,
+        )
+    };
+    let locked_until = match outcome {
+        SecondFactorOutcome::LockedOut => {
+            Some(Utc::now() + chrono::Duration::minutes(LOCKOUT_MINUTES))
+        }
+        _ => None,
+    };
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET two_factor_failed_attempts = $2, two_factor_locked_until = $3
+        WHERE user_id = $1
+        "#,
+        user_id,
+        failed_attempts,
+        locked_until
+    )
+    .execute(&mut transaction)
+    .await
+    .context("Failed to record a two-factor authentication attempt.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to verify a second factor.")?;
+    Ok(outcome)
+}
+
+async fn verify_totp_code(
+    transaction: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+    secret: &TotpSecret,
+    code: &str,
+) -> Result<bool, anyhow::Error> {
+    let time_step = match secret.verify(code, Utc::now()) {
+        Some(time_step) => time_step,
+        None => return Ok(false),
+    };
+    // Also rejects codes for earlier time steps than one we already accepted.
+    let n_updated_rows = sqlx::query!(
+        r#"
+        UPDATE users
+        SET totp_last_used_step = $2
+        WHERE user_id = $1 AND (totp_last_used_step IS NULL OR totp_last_used_step < $2)
+        "#,
+        user_id,
+        time_step
+    )
+    .execute(transaction)
+    .await
+    .context("Failed to record the use of a TOTP code.")?
+    .rows_affected();
+    Ok(n_updated_rows == 1)
+}
+
+async fn use_recovery_code(
+    transaction: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+    code: &str,
+) -> Result<bool, anyhow::Error> {
+    let n_updated_rows = sqlx::query!(
+        r#"
+        UPDATE totp_recovery_codes
+        SET used_at = now()
+        WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+        "#,
+        user_id,
+        hash_recovery_code(code)
+    )
+    .execute(transaction)
+    .await
+    .context("Failed to record the use of a recovery code.")?
+    .rows_affected();
+    Ok(n_updated_rows == 1)
+}
+
+#[tracing::instrument(name = "Count unused recovery codes", skip(pool))]
+pub async fn count_unused_recovery_codes(
+    user_id: Uuid,
+    pool: &PgPool,
+) -> Result<i64, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT count(*) AS "count!"
+        FROM totp_recovery_codes
+        WHERE user_id = $1 AND used_at IS NULL
+        "#,
+        user_id
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to perform a query to count unused recovery codes.")?;
+    Ok(row.count)
+}
+
+/// Ten random characters, e.g. `x4k2p-9qzm7`. They are random enough that a
+/// fast hash will do.
+fn generate_recovery_code() -> Secret<String> {
+    let mut rng = thread_rng();
+    let code: String = std::iter::repeat_with(|| rng.sample(Alphanumeric))
+        .map(|c| char::from(c).to_ascii_lowercase())
+        .take(10)
+        .collect();
+    Secret::new(format!("{}-{}", &code[..5], &code[5..]))
+}
+
+/// Codes are hashed without the dash, as users may not type it.
+fn hash_recovery_code(code: &str) -> String {
+    let code: String = code.chars().filter(|c| *c != '-').collect();
+    format!("{:x}", Sha256::digest(code.as_bytes()))
+}
@@ -1,9 +1,12 @@
 use crate::domain::SubscriberEmail;
-use crate::email_client::EmailClient;
+use crate::email_client::{
+    EmailClient, FileTransport, HttpApiTransport, LogOnlyTransport, SmtpTransport,
+};
+use crate::rate_limiter::TokenBucket;
 use secrecy::{ExposeSecret, Secret};
 use serde_aux::field_attributes::deserialize_number_from_string;
 use sqlx::postgres::{PgConnectOptions, PgSslMode};
-use sqlx::ConnectOptions;
+use sqlx::{ConnectOptions, PgPool};
 use std::convert::{TryFrom, TryInto};
 
 #[derive(serde::Deserialize, Clone)]
@@ -12,6 +15,9 @@ pub struct Settings {
     pub application: ApplicationSettings,
     pub email_client: EmailClientSettings,
     pub redis_uri: Secret<String>,
+    pub issue_delivery_worker: IssueDeliveryWorkerSettings,
+    pub email_events: EmailEventsSettings,
+    pub subscriptions: SubscriptionsSettings,
 }
 
 #[derive(serde::Deserialize, Clone)]
@@ -23,6 +29,35 @@ pub struct ApplicationSettings {
     pub hmac_secret: Secret<String>,
 }
 
+/// Bounce and spam complaint notifications sent to us by our email provider.
+#[derive(serde::Deserialize, Clone)]
+pub struct EmailEventsSettings {
+    /// The password the provider must use (HTTP basic auth) when calling our webhook.
+    pub webhook_token: Secret<String>,
+    /// How many soft bounces an address can rack up before we stop emailing it.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub soft_bounce_threshold: i32,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct SubscriptionsSettings {
+    /// How long the link in a confirmation email can be used for.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub confirmation_token_ttl_hours: i64,
+    /// How many times an hour a confirmation email can be resent to the same address.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_resends_per_address_per_hour: i64,
+    /// How many times an hour a client can ask for confirmation emails, whatever the address.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_resends_per_ip_per_hour: i64,
+}
+
+impl SubscriptionsSettings {
+    pub fn confirmation_token_ttl(&self) -> chrono::Duration {
+        chrono::Duration::hours(self.confirmation_token_ttl_hours)
+    }
+}
+
 #[derive(serde::Deserialize, Clone)]
 pub struct DatabaseSettings {
     pub username: String,
@@ -58,23 +93,78 @@ impl DatabaseSettings {
 
 #[derive(serde::Deserialize, Clone)]
 pub struct EmailClientSettings {
-    pub base_url: String,
+    #[serde(default)]
+    pub transport: EmailTransportKind,
+    /// Only needed by the `http` transport.
+    pub base_url: Option<String>,
     pub sender_email: String,
-    pub authorization_token: Secret<String>,
+    /// Only needed by the `http` transport.
+    pub authorization_token: Option<Secret<String>>,
     #[serde(deserialize_with = "deserialize_number_from_string")]
     pub timeout_milliseconds: u64,
+    pub smtp: Option<SmtpSettings>,
+    pub file_directory: Option<String>,
+}
+
+/// How emails leave the application.
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailTransportKind {
+    /// Our email provider's HTTP API, using `base_url` and `authorization_token`.
+    #[default]
+    Http,
+    /// An SMTP relay, configured by `smtp`.
+    Smtp,
+    /// `.eml` files written to `file_directory`.
+    File,
+    /// Nothing is sent, emails are only logged.
+    Log,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct SmtpSettings {
+    pub host: String,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<Secret<String>>,
+    pub starttls: bool,
 }
 
 impl EmailClientSettings {
-    pub fn client(self) -> EmailClient {
-        let sender_email = self.sender().expect("Invalid sender email address.");
+    pub fn client(self) -> Result<EmailClient, String> {
+        let sender_email = self.sender()?;
         let timeout = self.timeout();
-        EmailClient::new(
-            self.base_url,
-            sender_email,
-            self.authorization_token,
-            timeout,
-        )
+        let email_client = match self.transport {
+            EmailTransportKind::Http => {
+                let base_url = self.base_url.ok_or(
+                    "The http email transport requires `email_client.base_url` to be set.",
+                )?;
+                let authorization_token = self.authorization_token.ok_or(
+                    "The http email transport requires `email_client.authorization_token` to be set.",
+                )?;
+                EmailClient::with_transport(
+                    sender_email,
+                    HttpApiTransport::new(base_url, authorization_token, timeout),
+                )
+            }
+            EmailTransportKind::Smtp => {
+                let smtp = self
+                    .smtp
+                    .ok_or("The smtp email transport requires `email_client.smtp` settings.")?;
+                let transport = SmtpTransport::new(&smtp, timeout)
+                    .map_err(|e| format!("Invalid SMTP settings: {}", e))?;
+                EmailClient::with_transport(sender_email, transport)
+            }
+            EmailTransportKind::File => {
+                let directory = self.file_directory.ok_or(
+                    "The file email transport requires `email_client.file_directory` to be set.",
+                )?;
+                EmailClient::with_transport(sender_email, FileTransport::new(directory))
+            }
+            EmailTransportKind::Log => EmailClient::with_transport(sender_email, LogOnlyTransport),
+        };
+        Ok(email_client)
     }
 
     pub fn sender(&self) -> Result<SubscriberEmail, String> {
@@ -86,6 +176,67 @@ impl EmailClientSettings {
     }
 }
 
+#[derive(serde::Deserialize, Clone)]
+pub struct IssueDeliveryWorkerSettings {
+    /// How many times a failed delivery is retried before being moved
+    /// to `failed_deliveries`.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_retries: i16,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub initial_backoff_milliseconds: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_backoff_milliseconds: u64,
+    /// The worker is woken up by `NOTIFY` as soon as deliveries are enqueued,
+    /// and when the earliest retry comes due: polling is only a fallback for
+    /// notifications lost while reconnecting.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub poll_interval_milliseconds: u64,
+    /// How often the worker looks for scheduled issues that are due.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub scheduled_issues_poll_interval_milliseconds: u64,
+    /// How many tasks pull deliveries off the queue at the same time.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub concurrency: usize,
+    /// Shared by all the delivery tasks of all the worker processes, to stay
+    /// within the quota of our email provider.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_emails_per_second: u32,
+    /// How long a delivery in progress is given to complete on shutdown
+    /// before it is rolled back.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub drain_timeout_milliseconds: u64,
+}
+
+impl IssueDeliveryWorkerSettings {
+    /// How long to wait before retrying a delivery that already failed `n_retries` times.
+    /// The delay doubles on every attempt, up to `max_backoff_milliseconds`.
+    pub fn backoff(&self, n_retries: i16) -> std::time::Duration {
+        let factor = 2u64.saturating_pow(n_retries.max(0) as u32);
+        let backoff = self
+            .initial_backoff_milliseconds
+            .saturating_mul(factor)
+            .min(self.max_backoff_milliseconds);
+        std::time::Duration::from_millis(backoff)
+    }
+
+    pub fn poll_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.poll_interval_milliseconds)
+    }
+
+    pub fn scheduled_issues_poll_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.scheduled_issues_poll_interval_milliseconds)
+    }
+
+    pub fn drain_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.drain_timeout_milliseconds)
+    }
+
+    /// Allows bursts of up to one second worth of emails.
+    pub fn rate_limiter(&self, pool: PgPool) -> Result<TokenBucket, String> {
+        TokenBucket::new(pool, self.max_emails_per_second, self.max_emails_per_second)
+    }
+}
+
 pub fn get_configuration() -> Result<Settings, config::ConfigError> {
     let base_path = std::env::current_dir().expect("Failed to determine the current directory");
     let configuration_directory = base_path.join("configuration");
@@ -144,4 +295,162 @@ impl TryFrom<String> for Environment {
             )),
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EmailClientSettings, EmailTransportKind, IssueDeliveryWorkerSettings};
+    use crate::domain::SubscriberEmail;
+    use claim::assert_ok;
+    use std::time::Duration;
+
+    fn settings() -> IssueDeliveryWorkerSettings {
+        IssueDeliveryWorkerSettings {
+            max_retries: 5,
+            initial_backoff_milliseconds: 1000,
+            max_backoff_milliseconds: 10_000,
+            poll_interval_milliseconds: 60_000,
+            scheduled_issues_poll_interval_milliseconds: 10_000,
+            concurrency: 1,
+            max_emails_per_second: 10,
+            drain_timeout_milliseconds: 10_000,
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_on_every_retry() {
+        let settings = settings();
+        assert_eq!(settings.backoff(0), Duration::from_millis(1000));
+        assert_eq!(settings.backoff(1), Duration::from_millis(2000));
+        assert_eq!(settings.backoff(3), Duration::from_millis(8000));
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        let settings = settings();
+        assert_eq!(settings.backoff(4), Duration::from_millis(10_000));
+        assert_eq!(settings.backoff(i16::MAX), Duration::from_millis(10_000));
+    }
+
+    fn email_client_settings(yaml: &str) -> EmailClientSettings {
+        config::Config::builder()
+            .add_source(config::File::from_str(yaml, config::FileFormat::Yaml))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap()
+    }
+
+    #[test]
+    fn the_http_transport_is_used_by_default() {
+        let settings = email_client_settings(
+            r#"
+            base_url: "https://api.postmarkapp.com"
+            sender_email: "sender@example.com"
+            authorization_token: "my-secret-token"
+            timeout_milliseconds: 10000
+            "#,
+        );
+        assert!(matches!(settings.transport, EmailTransportKind::Http));
+        assert!(settings.client().is_ok());
+    }
+
+    #[test]
+    fn the_http_transport_requires_a_base_url_and_an_authorization_token() {
+        let settings = email_client_settings(
+            r#"
+            transport: http
+            sender_email: "sender@example.com"
+            timeout_milliseconds: 10000
+            "#,
+        );
+        assert!(settings.clone().client().is_err());
+
+        let mut with_base_url = settings;
+        with_base_url.base_url = Some("https://api.postmarkapp.com".into());
+        assert!(with_base_url.client().is_err());
+    }
+
+    #[test]
+    fn the_smtp_transport_requires_smtp_settings() {
+        let settings = email_client_settings(
+            r#"
+            transport: smtp
+            sender_email: "sender@example.com"
+            timeout_milliseconds: 10000
+            "#,
+        );
+        assert!(settings.client().is_err());
+
+        let settings = email_client_settings(
+            r#"
+            transport: smtp
+            sender_email: "sender@example.com"
+            timeout_milliseconds: 10000
+            smtp:
+              host: "localhost"
+              port: 1025
+              starttls: false
+            "#,
+        );
+        assert!(settings.client().is_ok());
+    }
+
+    #[test]
+    fn the_file_transport_requires_a_directory() {
+        let settings = email_client_settings(
+            r#"
+            transport: file
+            sender_email: "sender@example.com"
+            timeout_milliseconds: 10000
+            "#,
+        );
+        assert!(settings.client().is_err());
+    }
+
+    #[test]
+    fn the_log_transport_needs_neither_a_base_url_nor_a_token() {
+        let settings = email_client_settings(
+            r#"
+            transport: log
+            sender_email: "sender@example.com"
+            timeout_milliseconds: 10000
+            "#,
+        );
+        assert!(settings.client().is_ok());
+    }
+
+    #[tokio::test]
+    async fn the_file_transport_writes_an_eml_file_per_email() {
+        let directory = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let settings = email_client_settings(&format!(
+            r#"
+            transport: file
+            sender_email: "sender@example.com"
+            timeout_milliseconds: 10000
+            file_directory: "{}"
+            "#,
+            directory.display()
+        ));
+        let email_client = settings.client().unwrap();
+
+        let recipient = SubscriberEmail::parse("recipient@example.com".into()).unwrap();
+        assert_ok!(
+            email_client
+                .send_email(&recipient, "Hello", "<p>Hello!</p>", "Hello!")
+                .await
+        );
+
+        let files: Vec<_> = std::fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].extension().unwrap(), "eml");
+        let eml = std::fs::read_to_string(&files[0]).unwrap();
+        assert!(eml.contains("From: sender@example.com"));
+        assert!(eml.contains("To: recipient@example.com"));
+        assert!(eml.contains("Subject: Hello"));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,75 @@
+/// What happened to a newsletter issue for a given recipient,
+/// as recorded in `issue_delivery_log`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeliveryStatus {
+    /// Waiting in `issue_delivery_queue`, possibly after a failed attempt.
+    Queued,
+    Sent,
+    /// Moved to `failed_deliveries`.
+    Failed,
+    SkippedInvalidEmail,
+    /// The recipient left the list before we got to them.
+    SkippedUnsubscribed,
+    /// The address bounced or complained before we got to it.
+    SkippedSuppressed,
+}
+
+impl DeliveryStatus {
+    pub const ALL: [DeliveryStatus; 6] = [
+        DeliveryStatus::Queued,
+        DeliveryStatus::Sent,
+        DeliveryStatus::Failed,
+        DeliveryStatus::SkippedInvalidEmail,
+        DeliveryStatus::SkippedUnsubscribed,
+        DeliveryStatus::SkippedSuppressed,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Queued => "queued",
+            DeliveryStatus::Sent => "sent",
+            DeliveryStatus::Failed => "failed",
+            DeliveryStatus::SkippedInvalidEmail => "skipped_invalid_email",
+            DeliveryStatus::SkippedUnsubscribed => "skipped_unsubscribed",
+            DeliveryStatus::SkippedSuppressed => "skipped_suppressed",
+        }
+    }
+}
+
+impl TryFrom<String> for DeliveryStatus {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Self::ALL
+            .into_iter()
+            .find(|status| status.as_str() == s)
+            .ok_or_else(|| format!("{} is not a valid delivery status.", s))
+    }
+}
+
+impl std::fmt::Display for DeliveryStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DeliveryStatus;
+    use claim::{assert_err, assert_ok_eq};
+
+    #[test]
+    fn statuses_round_trip_through_their_string_representation() {
+        for status in DeliveryStatus::ALL {
+            assert_ok_eq!(
+                DeliveryStatus::try_from(status.as_str().to_string()),
+                status
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_statuses_are_rejected() {
+        assert_err!(DeliveryStatus::try_from("bounced".to_string()));
+    }
+}
@@ -0,0 +1,104 @@
+/// What a notification from our email provider means for a subscriber.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmailEventKind {
+    /// The address does not exist or will never accept our emails.
+    HardBounce,
+    /// A temporary failure: full mailbox, DNS hiccup, ...
+    SoftBounce,
+    /// The recipient flagged our email as spam.
+    SpamComplaint,
+    /// The recipient's mail server accepted our email.
+    Delivered,
+    /// Anything else (auto-responders, address changes, ...): recorded, not acted upon.
+    Other,
+}
+
+impl EmailEventKind {
+    /// Classify a Postmark-style webhook payload from its `RecordType` and `Type` fields.
+    pub fn classify(record_type: &str, event_type: Option<&str>) -> Self {
+        match (record_type, event_type) {
+            ("SpamComplaint", _) | (_, Some("SpamComplaint")) => Self::SpamComplaint,
+            ("Delivery", _) => Self::Delivered,
+            ("Bounce", Some("HardBounce" | "BadEmailAddress" | "ManuallyDeactivated")) => {
+                Self::HardBounce
+            }
+            ("Bounce", Some("SoftBounce" | "Transient" | "DnsError" | "Blocked")) => {
+                Self::SoftBounce
+            }
+            _ => Self::Other,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmailEventKind::HardBounce => "hard_bounce",
+            EmailEventKind::SoftBounce => "soft_bounce",
+            EmailEventKind::SpamComplaint => "spam_complaint",
+            EmailEventKind::Delivered => "delivered",
+            EmailEventKind::Other => "other",
+        }
+    }
+}
+
+impl std::fmt::Display for EmailEventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EmailEventKind;
+
+    #[test]
+    fn hard_bounces_are_recognised() {
+        for event_type in ["HardBounce", "BadEmailAddress", "ManuallyDeactivated"] {
+            assert_eq!(
+                EmailEventKind::classify("Bounce", Some(event_type)),
+                EmailEventKind::HardBounce
+            );
+        }
+    }
+
+    #[test]
+    fn soft_bounces_are_recognised() {
+        for event_type in ["SoftBounce", "Transient", "DnsError", "Blocked"] {
+            assert_eq!(
+                EmailEventKind::classify("Bounce", Some(event_type)),
+                EmailEventKind::SoftBounce
+            );
+        }
+    }
+
+    #[test]
+    fn spam_complaints_are_recognised_whatever_the_record_type() {
+        assert_eq!(
+            EmailEventKind::classify("SpamComplaint", Some("SpamComplaint")),
+            EmailEventKind::SpamComplaint
+        );
+        assert_eq!(
+            EmailEventKind::classify("Bounce", Some("SpamComplaint")),
+            EmailEventKind::SpamComplaint
+        );
+    }
+
+    #[test]
+    fn deliveries_are_recognised() {
+        assert_eq!(
+            EmailEventKind::classify("Delivery", None),
+            EmailEventKind::Delivered
+        );
+    }
+
+    #[test]
+    fn other_events_are_not_acted_upon() {
+        assert_eq!(
+            EmailEventKind::classify("Bounce", Some("AutoResponder")),
+            EmailEventKind::Other
+        );
+        assert_eq!(
+            EmailEventKind::classify("Open", None),
+            EmailEventKind::Other
+        );
+    }
+}
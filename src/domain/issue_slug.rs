@@ -0,0 +1,86 @@
+/// Slugs are kept short enough to read comfortably in a URL.
+const MAX_LENGTH: usize = 60;
+
+/// The URL-friendly name of a published newsletter issue, e.g.
+/// `our-first-issue` in `/issues/our-first-issue`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IssueSlug(String);
+
+impl IssueSlug {
+    /// Lower-case ASCII letters and digits, with every other run of
+    /// characters collapsed into a single dash.
+    pub fn from_title(title: &str) -> IssueSlug {
+        let mut slug = String::new();
+        for c in title.chars() {
+            if c.is_ascii_alphanumeric() {
+                slug.push(c.to_ascii_lowercase());
+            } else if !slug.is_empty() && !slug.ends_with('-') {
+                slug.push('-');
+            }
+        }
+        slug.truncate(MAX_LENGTH);
+        let slug = slug.trim_end_matches('-');
+        if slug.is_empty() {
+            // Nothing in the title survives, e.g. it only uses a non-Latin script.
+            return Self("issue".into());
+        }
+        Self(slug.into())
+    }
+
+    /// The first of `slug`, `slug-2`, `slug-3`, ... that is not `taken`.
+    pub fn first_available(&self, taken: &[String]) -> IssueSlug {
+        std::iter::once(self.0.clone())
+            .chain((2..).map(|n| format!("{}-{}", self.0, n)))
+            .find(|candidate| !taken.contains(candidate))
+            .map(Self)
+            .unwrap()
+    }
+}
+
+impl AsRef<str> for IssueSlug {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for IssueSlug {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IssueSlug;
+
+    #[test]
+    fn titles_are_lower_cased_and_dashed() {
+        let slug = IssueSlug::from_title("  Our First Issue: Rust & Postgres!  ");
+        assert_eq!(slug.as_ref(), "our-first-issue-rust-postgres");
+    }
+
+    #[test]
+    fn non_ascii_characters_are_dropped() {
+        let slug = IssueSlug::from_title("Caffè e cornetto");
+        assert_eq!(slug.as_ref(), "caff-e-cornetto");
+    }
+
+    #[test]
+    fn titles_without_usable_characters_get_a_generic_slug() {
+        assert_eq!(IssueSlug::from_title("¡¿…?!").as_ref(), "issue");
+    }
+
+    #[test]
+    fn long_titles_are_truncated_without_a_trailing_dash() {
+        let slug = IssueSlug::from_title(&format!("{} tail", "a".repeat(59)));
+        assert_eq!(slug.as_ref(), "a".repeat(59));
+    }
+
+    #[test]
+    fn taken_slugs_get_a_numeric_suffix() {
+        let slug = IssueSlug::from_title("Weekly digest");
+        assert_eq!(slug.first_available(&[]).as_ref(), "weekly-digest");
+        let taken = vec!["weekly-digest".to_string(), "weekly-digest-2".to_string()];
+        assert_eq!(slug.first_available(&taken).as_ref(), "weekly-digest-3");
+    }
+}
@@ -0,0 +1,178 @@
+use htmlescape::encode_attribute;
+
+/// The per-recipient values a newsletter issue can refer to.
+pub struct TemplateContext<'a> {
+    pub subscriber_name: &'a str,
+    pub unsubscribe_url: &'a str,
+    pub archive_url: &'a str,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Variable {
+    SubscriberName,
+    UnsubscribeUrl,
+    IssueArchiveUrl,
+}
+
+impl Variable {
+    const ALL: [Variable; 3] = [
+        Variable::SubscriberName,
+        Variable::UnsubscribeUrl,
+        Variable::IssueArchiveUrl,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Variable::SubscriberName => "subscriber.name",
+            Variable::UnsubscribeUrl => "unsubscribe_url",
+            Variable::IssueArchiveUrl => "issue.archive_url",
+        }
+    }
+
+    fn value<'a>(&self, context: &TemplateContext<'a>) -> &'a str {
+        match self {
+            Variable::SubscriberName => context.subscriber_name,
+            Variable::UnsubscribeUrl => context.unsubscribe_url,
+            Variable::IssueArchiveUrl => context.archive_url,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Segment {
+    Text(String),
+    Variable(Variable),
+}
+
+/// Newsletter content with `{{ variable }}` placeholders, filled in for
+/// each recipient at delivery time.
+///
+/// Parsing fails on unknown variables and unterminated placeholders, so
+/// mistakes surface when the issue is saved rather than in the inbox.
+#[derive(Debug)]
+pub struct IssueTemplate(Vec<Segment>);
+
+impl IssueTemplate {
+    pub fn parse(s: &str) -> Result<IssueTemplate, String> {
+        let mut segments = Vec::new();
+        let mut rest = s;
+        while let Some(start) = rest.find("{{") {
+            if start > 0 {
+                segments.push(Segment::Text(rest[..start].to_string()));
+            }
+            let after_open = &rest[start + 2..];
+            let end = after_open
+                .find("}}")
+                .ok_or_else(|| "A `{{` placeholder is never closed with `}}`.".to_string())?;
+            let name = after_open[..end].trim();
+            let variable = Variable::ALL
+                .into_iter()
+                .find(|v| v.name() == name)
+                .ok_or_else(|| {
+                    format!(
+                        "`{{{{ {} }}}}` is not a known template variable. Available variables: {}.",
+                        name,
+                        Variable::ALL
+                            .iter()
+                            .map(|v| v.name())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )
+                })?;
+            segments.push(Segment::Variable(variable));
+            rest = &after_open[end + 2..];
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Text(rest.to_string()));
+        }
+        Ok(Self(segments))
+    }
+
+    /// Values are HTML-escaped: a subscriber's name must not be able to inject markup.
+    pub fn render_html(&self, context: &TemplateContext) -> String {
+        self.render(|v| encode_attribute(v.value(context)))
+    }
+
+    pub fn render_text(&self, context: &TemplateContext) -> String {
+        self.render(|v| v.value(context).to_string())
+    }
+
+    fn render(&self, value: impl Fn(&Variable) -> String) -> String {
+        let mut rendered = String::new();
+        for segment in &self.0 {
+            match segment {
+                Segment::Text(text) => rendered.push_str(text),
+                Segment::Variable(variable) => rendered.push_str(&value(variable)),
+            }
+        }
+        rendered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IssueTemplate, TemplateContext};
+    use claim::{assert_err, assert_ok};
+
+    fn context() -> TemplateContext<'static> {
+        TemplateContext {
+            subscriber_name: "Ursula <Le Guin>",
+            unsubscribe_url: "https://example.com/unsubscribe?a=1&b=2",
+            archive_url: "https://example.com/issues/1",
+        }
+    }
+
+    #[test]
+    fn content_without_placeholders_is_left_untouched() {
+        let template = assert_ok!(IssueTemplate::parse("<p>Hello & welcome</p>"));
+        assert_eq!(template.render_html(&context()), "<p>Hello & welcome</p>");
+    }
+
+    #[test]
+    fn variables_are_substituted_in_text_content() {
+        let template = assert_ok!(IssueTemplate::parse(
+            "Hi {{subscriber.name}}, read it online at {{ issue.archive_url }}."
+        ));
+        assert_eq!(
+            template.render_text(&context()),
+            "Hi Ursula <Le Guin>, read it online at https://example.com/issues/1."
+        );
+    }
+
+    #[test]
+    fn variables_are_escaped_in_html_content() {
+        let template = assert_ok!(IssueTemplate::parse(
+            r#"<p>Hi {{ subscriber.name }}</p><a href="{{ unsubscribe_url }}">x</a>"#
+        ));
+        assert_eq!(
+            template.render_html(&context()),
+            "<p>Hi Ursula&#x20;&lt;Le&#x20;Guin&gt;</p><a href=\"https&#x3A;&#x2F;&#x2F;\
+            example&#x2E;com&#x2F;unsubscribe&#x3F;a&#x3D;1&amp;b&#x3D;2\">x</a>"
+        );
+    }
+
+    #[test]
+    fn variables_cannot_break_out_of_a_quoted_attribute() {
+        let template = assert_ok!(IssueTemplate::parse(
+            "<b title='{{ subscriber.name }}'>Hi</b>"
+        ));
+        let context = TemplateContext {
+            subscriber_name: "O'Brien' onmouseover='alert(1)",
+            ..context()
+        };
+        let rendered = template.render_html(&context);
+        assert_eq!(rendered.matches('\'').count(), 2);
+        assert!(!rendered.contains("onmouseover="));
+    }
+
+    #[test]
+    fn unknown_variables_are_rejected() {
+        let e = assert_err!(IssueTemplate::parse("Hi {{ subscriber.email }}"));
+        assert!(e.contains("subscriber.email"));
+    }
+
+    #[test]
+    fn unterminated_placeholders_are_rejected() {
+        assert_err!(IssueTemplate::parse("Hi {{ subscriber.name"));
+    }
+}
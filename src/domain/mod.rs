@@ -1,7 +1,25 @@
+mod delivery_status;
+mod email_event_kind;
+mod issue_slug;
+mod issue_template;
 mod new_subscriber;
+mod newsletter_issue_status;
+mod scheduled_send_time;
 mod subscriber_email;
 mod subscriber_name;
+mod totp_secret;
+mod unsubscribe_token;
+mod user_role;
 
+pub use delivery_status::DeliveryStatus;
+pub use email_event_kind::EmailEventKind;
+pub use issue_slug::IssueSlug;
+pub use issue_template::{IssueTemplate, TemplateContext};
 pub use new_subscriber::NewSubscriber;
+pub use newsletter_issue_status::NewsletterIssueStatus;
+pub use scheduled_send_time::ScheduledSendTime;
 pub use subscriber_email::SubscriberEmail;
 pub use subscriber_name::SubscriberName;
+pub use totp_secret::TotpSecret;
+pub use unsubscribe_token::UnsubscribeToken;
+pub use user_role::{Permission, UserRole};
@@ -0,0 +1,87 @@
+/// Where a newsletter issue is in its lifecycle.
+///
+/// An issue starts as a draft, which can be edited at will.
+/// It can be scheduled to go out at a later time, which keeps it editable
+/// until the delivery worker picks it up.
+/// Publishing it enqueues a delivery task per confirmed subscriber (`Sending`);
+/// it becomes `Sent` once the delivery queue for the issue has drained.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NewsletterIssueStatus {
+    Draft,
+    Scheduled,
+    Sending,
+    Sent,
+}
+
+impl NewsletterIssueStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NewsletterIssueStatus::Draft => "draft",
+            NewsletterIssueStatus::Scheduled => "scheduled",
+            NewsletterIssueStatus::Sending => "sending",
+            NewsletterIssueStatus::Sent => "sent",
+        }
+    }
+
+    /// Content can only change until the first email has gone out.
+    pub fn is_editable(&self) -> bool {
+        matches!(
+            self,
+            NewsletterIssueStatus::Draft | NewsletterIssueStatus::Scheduled
+        )
+    }
+}
+
+impl TryFrom<String> for NewsletterIssueStatus {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.as_str() {
+            "draft" => Ok(Self::Draft),
+            "scheduled" => Ok(Self::Scheduled),
+            "sending" => Ok(Self::Sending),
+            "sent" => Ok(Self::Sent),
+            other => Err(format!("{} is not a valid newsletter issue status.", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for NewsletterIssueStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NewsletterIssueStatus;
+    use claim::{assert_err, assert_ok_eq};
+
+    #[test]
+    fn statuses_round_trip_through_their_string_representation() {
+        for status in [
+            NewsletterIssueStatus::Draft,
+            NewsletterIssueStatus::Scheduled,
+            NewsletterIssueStatus::Sending,
+            NewsletterIssueStatus::Sent,
+        ] {
+            assert_ok_eq!(
+                NewsletterIssueStatus::try_from(status.as_str().to_string()),
+                status
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_statuses_are_rejected() {
+        assert_err!(NewsletterIssueStatus::try_from("published".to_string()));
+    }
+
+    #[test]
+    fn only_issues_that_are_not_being_sent_are_editable() {
+        assert!(NewsletterIssueStatus::Draft.is_editable());
+        assert!(NewsletterIssueStatus::Scheduled.is_editable());
+        assert!(!NewsletterIssueStatus::Sending.is_editable());
+        assert!(!NewsletterIssueStatus::Sent.is_editable());
+    }
+}
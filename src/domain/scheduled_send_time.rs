@@ -0,0 +1,72 @@
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// The format used by `<input type="datetime-local">`.
+const LOCAL_FORMAT: &str = "%Y-%m-%dT%H:%M";
+
+/// The moment a newsletter issue should go out, entered by an editor as a
+/// local date/time in a given IANA timezone (e.g. `Europe/Rome`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScheduledSendTime(DateTime<Utc>);
+
+impl ScheduledSendTime {
+    pub fn parse(local: &str, timezone: &str) -> Result<ScheduledSendTime, String> {
+        let timezone: Tz = timezone
+            .trim()
+            .parse()
+            .map_err(|_| format!("{} is not a valid timezone.", timezone))?;
+        let local = NaiveDateTime::parse_from_str(local.trim(), LOCAL_FORMAT)
+            .map_err(|_| format!("{} is not a valid date and time.", local))?;
+        // Clocks going back make some local times ambiguous, clocks going
+        // forward make others non-existent: we refuse to guess in both cases.
+        let scheduled_for = timezone
+            .from_local_datetime(&local)
+            .single()
+            .ok_or_else(|| {
+                format!(
+                    "{} does not exist or is ambiguous in {}.",
+                    local.format(LOCAL_FORMAT),
+                    timezone
+                )
+            })?;
+        Ok(Self(scheduled_for.with_timezone(&Utc)))
+    }
+
+    pub fn as_utc(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ScheduledSendTime;
+    use chrono::{TimeZone, Utc};
+    use claim::{assert_err, assert_ok};
+
+    #[test]
+    fn local_times_are_converted_to_utc() {
+        let send_time = assert_ok!(ScheduledSendTime::parse("2022-08-05T09:30", "Europe/Rome"));
+        assert_eq!(
+            send_time.as_utc(),
+            Utc.with_ymd_and_hms(2022, 8, 5, 7, 30, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn unknown_timezones_are_rejected() {
+        assert_err!(ScheduledSendTime::parse(
+            "2022-08-05T09:30",
+            "Mars/Olympus_Mons"
+        ));
+    }
+
+    #[test]
+    fn malformed_dates_are_rejected() {
+        assert_err!(ScheduledSendTime::parse("05/08/2022 09:30", "UTC"));
+    }
+
+    #[test]
+    fn times_skipped_by_daylight_saving_are_rejected() {
+        assert_err!(ScheduledSendTime::parse("2022-03-27T02:30", "Europe/Rome"));
+    }
+}
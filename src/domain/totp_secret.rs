@@ -0,0 +1,175 @@
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use secrecy::{ExposeSecret, Secret};
+use sha1::Sha1;
+
+/// The parameters every authenticator app supports out of the box.
+const STEP_SECONDS: i64 = 30;
+const DIGITS: u32 = 6;
+/// Codes from the time steps right before and after the current one are
+/// accepted too: clocks drift, and typing a code takes a while.
+const ALLOWED_DRIFT_STEPS: i64 = 1;
+const SECRET_LENGTH: usize = 20;
+const BASE32: base32::Alphabet = base32::Alphabet::RFC4648 { padding: false };
+
+/// The key shared with a user's authenticator app to generate time-based
+/// one-time passwords, as per RFC 6238.
+pub struct TotpSecret(Secret<Vec<u8>>);
+
+impl std::fmt::Debug for TotpSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("TotpSecret([REDACTED])")
+    }
+}
+
+impl TotpSecret {
+    pub fn generate() -> Self {
+        let mut key = vec![0; SECRET_LENGTH];
+        rand::thread_rng().fill(&mut key[..]);
+        Self(Secret::new(key))
+    }
+
+    /// Parse the base32 encoding we store and show to users.
+    pub fn parse(s: &str) -> Result<TotpSecret, String> {
+        match base32::decode(BASE32, s) {
+            Some(key) if !key.is_empty() => Ok(Self(Secret::new(key))),
+            _ => Err("The two-factor authentication secret is not valid base32.".into()),
+        }
+    }
+
+    pub fn to_base32(&self) -> Secret<String> {
+        Secret::new(base32::encode(BASE32, self.0.expose_secret()))
+    }
+
+    /// The URI authenticator apps import the secret from, usually as a QR code.
+    pub fn otpauth_uri(&self, issuer: &str, account_name: &str) -> Secret<String> {
+        Secret::new(format!(
+            "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+            urlencoding::encode(issuer),
+            urlencoding::encode(account_name),
+            self.to_base32().expose_secret(),
+            urlencoding::encode(issuer),
+            DIGITS,
+            STEP_SECONDS
+        ))
+    }
+
+    /// The code an authenticator app shows at `time`.
+    pub fn code_at(&self, time: DateTime<Utc>) -> String {
+        self.code_for_step(time_step(time))
+    }
+
+    /// The time step `code` belongs to, if it is valid at `now`.
+    ///
+    /// Callers must remember the steps they accepted: each code works once.
+    pub fn verify(&self, code: &str, now: DateTime<Utc>) -> Option<i64> {
+        let current_step = time_step(now);
+        (current_step - ALLOWED_DRIFT_STEPS..=current_step + ALLOWED_DRIFT_STEPS)
+            .find(|step| self.code_for_step(*step) == code)
+    }
+
+    /// HOTP, as per RFC 4226, with the time step as the counter.
+    fn code_for_step(&self, step: i64) -> String {
+        let mut mac = Hmac::<Sha1>::new_from_slice(self.0.expose_secret())
+            .expect("HMAC can take a key of any size");
+        mac.update(&step.to_be_bytes());
+        let hash = mac.finalize().into_bytes();
+        let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+        let truncated = u32::from_be_bytes([
+            hash[offset] & 0x7f,
+            hash[offset + 1],
+            hash[offset + 2],
+            hash[offset + 3],
+        ]);
+        format!(
+            "{:0width$}",
+            truncated % 10u32.pow(DIGITS),
+            width = DIGITS as usize
+        )
+    }
+}
+
+fn time_step(time: DateTime<Utc>) -> i64 {
+    time.timestamp().div_euclid(STEP_SECONDS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TotpSecret;
+    use chrono::{TimeZone, Utc};
+    use claim::{assert_err, assert_none, assert_some_eq};
+    use secrecy::ExposeSecret;
+
+    /// The SHA1 key of the RFC 6238 test vectors.
+    fn rfc_secret() -> TotpSecret {
+        let secret = base32::encode(
+            base32::Alphabet::RFC4648 { padding: false },
+            b"12345678901234567890",
+        );
+        TotpSecret::parse(&secret).unwrap()
+    }
+
+    #[test]
+    fn codes_match_the_rfc_6238_test_vectors() {
+        // The RFC lists 8-digit codes: we keep the last 6.
+        let secret = rfc_secret();
+        for (timestamp, code) in [
+            (59, "287082"),
+            (1111111109, "081804"),
+            (1111111111, "050471"),
+            (1234567890, "005924"),
+            (2000000000, "279037"),
+        ] {
+            assert_eq!(
+                secret.code_at(Utc.timestamp_opt(timestamp, 0).unwrap()),
+                code
+            );
+        }
+    }
+
+    #[test]
+    fn codes_from_adjacent_time_steps_are_accepted() {
+        let secret = rfc_secret();
+        let now = Utc.timestamp_opt(1111111111, 0).unwrap();
+        let step = now.timestamp() / 30;
+        assert_some_eq!(secret.verify(&secret.code_at(now), now), step);
+        let previous = secret.code_at(Utc.timestamp_opt(1111111111 - 30, 0).unwrap());
+        assert_some_eq!(secret.verify(&previous, now), step - 1);
+        let next = secret.code_at(Utc.timestamp_opt(1111111111 + 30, 0).unwrap());
+        assert_some_eq!(secret.verify(&next, now), step + 1);
+    }
+
+    #[test]
+    fn stale_codes_are_rejected() {
+        let secret = rfc_secret();
+        let now = Utc.timestamp_opt(1111111111, 0).unwrap();
+        let stale = secret.code_at(Utc.timestamp_opt(1111111111 - 90, 0).unwrap());
+        assert_none!(secret.verify(&stale, now));
+    }
+
+    #[test]
+    fn secrets_round_trip_through_base32() {
+        let secret = TotpSecret::generate();
+        let encoded = secret.to_base32();
+        let parsed = TotpSecret::parse(encoded.expose_secret()).unwrap();
+        assert_eq!(parsed.to_base32().expose_secret(), encoded.expose_secret());
+    }
+
+    #[test]
+    fn invalid_base32_is_rejected() {
+        assert_err!(TotpSecret::parse("not base32!"));
+        assert_err!(TotpSecret::parse(""));
+    }
+
+    #[test]
+    fn the_otpauth_uri_names_the_issuer_and_the_account() {
+        let uri = rfc_secret().otpauth_uri("Newsletter", "ursula le guin");
+        assert_eq!(
+            uri.expose_secret(),
+            "otpauth://totp/Newsletter:ursula%20le%20guin\
+            ?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&issuer=Newsletter\
+            &algorithm=SHA1&digits=6&period=30"
+        );
+    }
+}
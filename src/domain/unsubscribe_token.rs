@@ -0,0 +1,111 @@
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, Secret};
+use sha2::Sha256;
+use uuid::Uuid;
+
+/// A tag proving that an unsubscribe link was issued by us for a specific subscriber.
+///
+/// Tokens are not stored: they are recomputed from the subscriber id and
+/// the application HMAC secret, so every link we ever sent stays valid.
+#[derive(Debug)]
+pub struct UnsubscribeToken(String);
+
+impl UnsubscribeToken {
+    pub fn generate(subscriber_id: Uuid, hmac_secret: &Secret<String>) -> Self {
+        let tag = unsubscribe_mac(subscriber_id, hmac_secret)
+            .finalize()
+            .into_bytes();
+        Self(base64::encode_config(tag, base64::URL_SAFE_NO_PAD))
+    }
+
+    /// Returns an instance of `UnsubscribeToken` if `s` is the tag we would
+    /// have generated for `subscriber_id`.
+    pub fn parse(
+        s: String,
+        subscriber_id: Uuid,
+        hmac_secret: &Secret<String>,
+    ) -> Result<UnsubscribeToken, String> {
+        let tag = base64::decode_config(&s, base64::URL_SAFE_NO_PAD)
+            .map_err(|_| format!("{} is not a valid unsubscribe token.", s))?;
+        unsubscribe_mac(subscriber_id, hmac_secret)
+            .verify_slice(&tag)
+            .map_err(|_| format!("{} is not a valid unsubscribe token.", s))?;
+        Ok(Self(s))
+    }
+}
+
+fn unsubscribe_mac(subscriber_id: Uuid, hmac_secret: &Secret<String>) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(hmac_secret.expose_secret().as_bytes())
+        .expect("HMAC can take a key of any size");
+    // Scope the tag to this purpose, the same secret signs other things too.
+    mac.update(b"unsubscribe:");
+    mac.update(subscriber_id.as_bytes());
+    mac
+}
+
+impl AsRef<str> for UnsubscribeToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for UnsubscribeToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UnsubscribeToken;
+    use claim::{assert_err, assert_ok};
+    use secrecy::Secret;
+    use uuid::Uuid;
+
+    fn secret() -> Secret<String> {
+        Secret::new(Uuid::new_v4().to_string())
+    }
+
+    #[test]
+    fn a_generated_token_is_accepted() {
+        let secret = secret();
+        let subscriber_id = Uuid::new_v4();
+        let token = UnsubscribeToken::generate(subscriber_id, &secret);
+        assert_ok!(UnsubscribeToken::parse(
+            token.as_ref().to_owned(),
+            subscriber_id,
+            &secret
+        ));
+    }
+
+    #[test]
+    fn a_token_for_another_subscriber_is_rejected() {
+        let secret = secret();
+        let token = UnsubscribeToken::generate(Uuid::new_v4(), &secret);
+        assert_err!(UnsubscribeToken::parse(
+            token.as_ref().to_owned(),
+            Uuid::new_v4(),
+            &secret
+        ));
+    }
+
+    #[test]
+    fn a_token_signed_with_another_secret_is_rejected() {
+        let subscriber_id = Uuid::new_v4();
+        let token = UnsubscribeToken::generate(subscriber_id, &secret());
+        assert_err!(UnsubscribeToken::parse(
+            token.as_ref().to_owned(),
+            subscriber_id,
+            &secret()
+        ));
+    }
+
+    #[test]
+    fn garbage_is_rejected() {
+        assert_err!(UnsubscribeToken::parse(
+            "not-a-token!".to_string(),
+            Uuid::new_v4(),
+            &secret()
+        ));
+    }
+}
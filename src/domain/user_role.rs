@@ -0,0 +1,117 @@
+/// What a user is allowed to do in the admin area.
+///
+/// Roles are ordered: each one can do everything the previous ones can.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum UserRole {
+    /// Can look at issues, deliveries and the suppression list.
+    Viewer,
+    /// Can also write issues and send test copies.
+    Editor,
+    /// Can also send issues to subscribers and manage who receives them.
+    Publisher,
+    /// Can also manage users.
+    Owner,
+}
+
+/// An action in the admin area that not every role may perform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    ViewIssues,
+    EditIssues,
+    PublishIssues,
+    ViewSuppressions,
+    ManageSuppressions,
+    ManageUsers,
+}
+
+impl Permission {
+    fn required_role(&self) -> UserRole {
+        match self {
+            Permission::ViewIssues | Permission::ViewSuppressions => UserRole::Viewer,
+            Permission::EditIssues => UserRole::Editor,
+            Permission::PublishIssues | Permission::ManageSuppressions => UserRole::Publisher,
+            Permission::ManageUsers => UserRole::Owner,
+        }
+    }
+}
+
+impl UserRole {
+    pub const ALL: [UserRole; 4] = [
+        UserRole::Viewer,
+        UserRole::Editor,
+        UserRole::Publisher,
+        UserRole::Owner,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UserRole::Viewer => "viewer",
+            UserRole::Editor => "editor",
+            UserRole::Publisher => "publisher",
+            UserRole::Owner => "owner",
+        }
+    }
+
+    pub fn can(&self, permission: Permission) -> bool {
+        *self >= permission.required_role()
+    }
+}
+
+impl TryFrom<String> for UserRole {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.as_str() {
+            "viewer" => Ok(Self::Viewer),
+            "editor" => Ok(Self::Editor),
+            "publisher" => Ok(Self::Publisher),
+            "owner" => Ok(Self::Owner),
+            other => Err(format!("{} is not a valid role.", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for UserRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Permission, UserRole};
+    use claim::{assert_err, assert_ok_eq};
+
+    #[test]
+    fn roles_round_trip_through_their_string_representation() {
+        for role in UserRole::ALL {
+            assert_ok_eq!(UserRole::try_from(role.as_str().to_string()), role);
+        }
+    }
+
+    #[test]
+    fn unknown_roles_are_rejected() {
+        assert_err!(UserRole::try_from("admin".to_string()));
+    }
+
+    #[test]
+    fn only_publishers_and_owners_can_publish() {
+        assert!(!UserRole::Viewer.can(Permission::PublishIssues));
+        assert!(!UserRole::Editor.can(Permission::PublishIssues));
+        assert!(UserRole::Publisher.can(Permission::PublishIssues));
+        assert!(UserRole::Owner.can(Permission::PublishIssues));
+    }
+
+    #[test]
+    fn every_role_can_view_issues() {
+        for role in UserRole::ALL {
+            assert!(role.can(Permission::ViewIssues));
+        }
+    }
+
+    #[test]
+    fn only_owners_can_manage_users() {
+        assert!(!UserRole::Publisher.can(Permission::ManageUsers));
+        assert!(UserRole::Owner.can(Permission::ManageUsers));
+    }
+}
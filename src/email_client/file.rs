@@ -0,0 +1,73 @@
+use super::{message_id, Email, EmailTransport, SendEmailError, SentEmail};
+use std::path::PathBuf;
+use uuid::Uuid;
+
+/// Write every email to `<directory>/<uuid>.eml` instead of sending it.
+/// Handy to inspect what we would have sent when running locally.
+pub struct FileTransport {
+    directory: PathBuf,
+}
+
+impl FileTransport {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailTransport for FileTransport {
+    async fn send(&self, email: &Email<'_>) -> Result<SentEmail, SendEmailError> {
+        let message = email.to_mime_message()?;
+        tokio::fs::create_dir_all(&self.directory)
+            .await
+            .map_err(|e| SendEmailError::Transient(e.into()))?;
+        let path = self.directory.join(format!("{}.eml", Uuid::new_v4()));
+        tokio::fs::write(&path, message.formatted())
+            .await
+            .map_err(|e| SendEmailError::Transient(e.into()))?;
+        tracing::info!(path = %path.display(), "Email written to disk.");
+        Ok(SentEmail {
+            message_id: message_id(&message),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FileTransport;
+    use crate::domain::SubscriberEmail;
+    use crate::email_client::{EmailClient, EmailHeader};
+    use claim::assert_ok;
+
+    #[tokio::test]
+    async fn emails_are_written_to_the_directory() {
+        let directory = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let email_client = EmailClient::with_transport(
+            SubscriberEmail::parse("sender@example.com".into()).unwrap(),
+            FileTransport::new(&directory),
+        );
+
+        let outcome = email_client
+            .send_email_with_headers(
+                &SubscriberEmail::parse("recipient@example.com".into()).unwrap(),
+                "Hello",
+                "<p>Hello!</p>",
+                "Hello!",
+                &[EmailHeader::new(
+                    "List-Unsubscribe-Post",
+                    "List-Unsubscribe=One-Click",
+                )],
+            )
+            .await;
+
+        assert_ok!(outcome);
+        let mut entries = std::fs::read_dir(&directory).unwrap();
+        let eml = std::fs::read_to_string(entries.next().unwrap().unwrap().path()).unwrap();
+        assert!(eml.contains("To: recipient@example.com"));
+        assert!(eml.contains("Subject: Hello"));
+        assert!(eml.contains("List-Unsubscribe-Post: List-Unsubscribe=One-Click"));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,408 @@
+use super::{Email, EmailHeader, EmailTransport, SendEmailError, SentEmail};
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Client, StatusCode};
+use secrecy::{ExposeSecret, Secret};
+use std::time::Duration;
+
+/// Deliver emails through a Postmark-style HTTP JSON API.
+pub struct HttpApiTransport {
+    base_url: String,
+    http_client: Client,
+    authorization_token: Secret<String>,
+}
+
+impl HttpApiTransport {
+    pub fn new(base_url: String, authorization_token: Secret<String>, timeout: Duration) -> Self {
+        let http_client = Client::builder().timeout(timeout).build().unwrap();
+        Self {
+            http_client,
+            base_url,
+            authorization_token,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailTransport for HttpApiTransport {
+    async fn send(&self, email: &Email<'_>) -> Result<SentEmail, SendEmailError> {
+        let url = self.base_url.to_string();
+        let request_body = SendEmailRequest {
+            from: email.from.as_ref(),
+            to: email.to.as_ref(),
+            subject: email.subject,
+            html_body: email.html_body,
+            text_body: email.text_body,
+            headers: email.headers,
+        };
+        let response = self
+            .http_client
+            .post(&url)
+            .header("X-Auth-Token", self.authorization_token.expose_secret())
+            .json(&request_body)
+            .send()
+            .await
+            .map_err(from_request_error)?;
+        let status = response.status();
+        if status.is_success() {
+            // The email has been accepted: a body we cannot make sense of
+            // must not turn it into a failure.
+            let body: Option<SendEmailResponse> = response.json().await.ok();
+            return Ok(SentEmail {
+                message_id: body.map(|b| b.message_id),
+            });
+        }
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            let retry_after = response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse().ok())
+                .map(Duration::from_secs);
+            return Err(SendEmailError::RateLimited { retry_after });
+        }
+        if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+            return Err(SendEmailError::Unauthorized {
+                status_code: status.as_u16(),
+            });
+        }
+        if status.is_server_error() || status == StatusCode::REQUEST_TIMEOUT {
+            return Err(SendEmailError::Transient(anyhow::anyhow!(
+                "The email provider answered with {}",
+                status
+            )));
+        }
+        let error_body: Option<ProviderErrorBody> = response.json().await.ok();
+        Err(SendEmailError::Rejected {
+            status_code: status.as_u16(),
+            error_code: error_body.as_ref().map(|b| b.error_code),
+            message: error_body.map(|b| b.message).unwrap_or_default(),
+        })
+    }
+}
+
+fn from_request_error(e: reqwest::Error) -> SendEmailError {
+    if e.is_timeout() {
+        SendEmailError::Timeout(e.into())
+    } else {
+        SendEmailError::Transient(e.into())
+    }
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct SendEmailRequest<'a> {
+    from: &'a str,
+    to: &'a str,
+    subject: &'a str,
+    html_body: &'a str,
+    text_body: &'a str,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    headers: &'a [EmailHeader],
+}
+
+#[derive(serde::Deserialize)]
+struct SendEmailResponse {
+    #[serde(rename = "MessageID")]
+    message_id: String,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ProviderErrorBody {
+    error_code: i64,
+    message: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::SubscriberEmail;
+    use crate::email_client::{EmailClient, EmailHeader, SendEmailError};
+    use claim::assert_err;
+    use claim::assert_ok;
+    use fake::faker::internet::en::SafeEmail;
+    use fake::faker::lorem::en::{Paragraph, Sentence};
+    use fake::{Fake, Faker};
+    use secrecy::Secret;
+    use wiremock::matchers::any;
+    use wiremock::matchers::{header, header_exists, method};
+    use wiremock::Request;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    struct SendEmailBodyMatcher;
+
+    impl wiremock::Match for SendEmailBodyMatcher {
+        fn matches(&self, request: &Request) -> bool {
+            let result: Result<serde_json::Value, _> = serde_json::from_slice(&request.body);
+            if let Ok(body) = result {
+                dbg!(&body);
+                body.get("From").is_some()
+                    && body.get("To").is_some()
+                    && body.get("Subject").is_some()
+                    && body.get("HtmlBody").is_some()
+                    && body.get("TextBody").is_some()
+            } else {
+                false
+            }
+        }
+    }
+
+    struct SendEmailHeadersMatcher;
+
+    impl wiremock::Match for SendEmailHeadersMatcher {
+        fn matches(&self, request: &Request) -> bool {
+            let result: Result<serde_json::Value, _> = serde_json::from_slice(&request.body);
+            if let Ok(body) = result {
+                body["Headers"][0]["Name"] == "List-Unsubscribe"
+                    && body["Headers"][0]["Value"] == "<https://example.com/unsubscribe>"
+            } else {
+                false
+            }
+        }
+    }
+
+    fn subject() -> String {
+        Sentence(1..2).fake()
+    }
+
+    fn content() -> String {
+        Paragraph(1..10).fake()
+    }
+
+    fn email() -> SubscriberEmail {
+        SubscriberEmail::parse(SafeEmail().fake()).unwrap()
+    }
+
+    fn email_client(base_url: String) -> EmailClient {
+        EmailClient::new(
+            base_url,
+            email(),
+            Secret::new(Faker.fake()),
+            std::time::Duration::from_millis(200),
+        )
+    }
+
+    #[tokio::test]
+    async fn send_email_sends_the_expected_request() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(header_exists("X-Auth-Token"))
+            .and(header("Content-Type", "application/json"))
+            .and(method("POST"))
+            .and(SendEmailBodyMatcher)
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let _ = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+    }
+
+    #[tokio::test]
+    async fn send_email_with_headers_forwards_the_headers() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(method("POST"))
+            .and(SendEmailBodyMatcher)
+            .and(SendEmailHeadersMatcher)
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let headers = [EmailHeader::new(
+            "List-Unsubscribe",
+            "<https://example.com/unsubscribe>",
+        )];
+        let outcome = email_client
+            .send_email_with_headers(&email(), &subject(), &content(), &content(), &headers)
+            .await;
+
+        assert_ok!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_succeeds_if_the_server_returns_200() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        assert_ok!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_returns_the_message_id_assigned_by_the_provider() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "To": "recipient@example.com",
+                "MessageID": "b7bc2f4a-e38e-4336-af7d-e6c392c2f817",
+                "ErrorCode": 0,
+                "Message": "OK"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let sent_email = assert_ok!(
+            email_client
+                .send_email(&email(), &subject(), &content(), &content())
+                .await
+        );
+
+        assert_eq!(
+            sent_email.message_id.as_deref(),
+            Some("b7bc2f4a-e38e-4336-af7d-e6c392c2f817")
+        );
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_the_server_returns_500() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        assert_err!(outcome);
+    }
+
+    #[tokio::test]
+    async fn a_500_is_a_transient_failure() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(503))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        let e = outcome.unwrap_err();
+        assert!(matches!(e, SendEmailError::Transient(_)));
+        assert!(e.is_transient());
+    }
+
+    #[tokio::test]
+    async fn a_429_is_a_rate_limit_with_the_requested_delay() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "120"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        let e = outcome.unwrap_err();
+        assert!(e.is_transient());
+        assert_eq!(e.retry_after(), Some(std::time::Duration::from_secs(120)));
+    }
+
+    #[tokio::test]
+    async fn a_401_is_a_transient_failure() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(401))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        let e = outcome.unwrap_err();
+        assert!(matches!(
+            e,
+            SendEmailError::Unauthorized { status_code: 401 }
+        ));
+        assert!(e.is_transient());
+        assert!(!e.is_recipient_rejected());
+    }
+
+    #[tokio::test]
+    async fn an_inactive_recipient_is_a_permanent_rejection() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        let body = serde_json::json!({
+            "ErrorCode": 406,
+            "Message": "You tried to send to a recipient that has been marked as inactive."
+        });
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(422).set_body_json(body))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        let e = outcome.unwrap_err();
+        assert!(!e.is_transient());
+        assert!(e.is_recipient_rejected());
+        assert!(matches!(
+            e,
+            SendEmailError::Rejected {
+                status_code: 422,
+                error_code: Some(406),
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn send_email_times_out_if_the_server_takes_too_long() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        let response = ResponseTemplate::new(200)
+            // 3 minutes!
+            .set_delay(std::time::Duration::from_secs(180));
+        Mock::given(any())
+            .respond_with(response)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+        // Assert
+        assert_err!(&outcome);
+        assert!(matches!(outcome, Err(SendEmailError::Timeout(_))));
+    }
+}
@@ -0,0 +1,17 @@
+use super::{Email, EmailTransport, SendEmailError, SentEmail};
+
+/// Do not send anything, just log the email.
+pub struct LogOnlyTransport;
+
+#[async_trait::async_trait]
+impl EmailTransport for LogOnlyTransport {
+    async fn send(&self, email: &Email<'_>) -> Result<SentEmail, SendEmailError> {
+        tracing::info!(
+            recipient = %email.to,
+            subject = %email.subject,
+            text_body = %email.text_body,
+            "Email not sent: the log-only email transport is in use."
+        );
+        Ok(SentEmail::default())
+    }
+}
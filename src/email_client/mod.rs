@@ -0,0 +1,211 @@
+mod file;
+mod http;
+mod log_only;
+mod smtp;
+
+pub use file::FileTransport;
+pub use http::HttpApiTransport;
+pub use log_only::LogOnlyTransport;
+pub use smtp::SmtpTransport;
+
+use crate::domain::SubscriberEmail;
+use lettre::message::header::{HeaderName, HeaderValue};
+use lettre::message::{Mailbox, MultiPart};
+use lettre::Message;
+use std::time::Duration;
+
+/// A way of getting an email to its recipient: an HTTP API, an SMTP relay, ...
+#[async_trait::async_trait]
+pub trait EmailTransport: Send + Sync {
+    async fn send(&self, email: &Email<'_>) -> Result<SentEmail, SendEmailError>;
+}
+
+/// What a transport tells us about an email it has accepted.
+#[derive(Debug, Default)]
+pub struct SentEmail {
+    /// The identifier the provider assigned to the message, if it gave us one.
+    pub message_id: Option<String>,
+}
+
+pub struct Email<'a> {
+    pub from: &'a SubscriberEmail,
+    pub to: &'a SubscriberEmail,
+    pub subject: &'a str,
+    pub html_body: &'a str,
+    pub text_body: &'a str,
+    pub headers: &'a [EmailHeader],
+}
+
+impl Email<'_> {
+    /// Render the email as a MIME message, for transports speaking raw RFC 5322.
+    /// A `Message-ID` header is generated for us.
+    fn to_mime_message(&self) -> Result<Message, SendEmailError> {
+        let mut builder = Message::builder()
+            .from(parse_mailbox(self.from)?)
+            .to(parse_mailbox(self.to)?)
+            .subject(self.subject);
+        for header in self.headers {
+            let name = HeaderName::new_from_ascii(header.name.clone())
+                .map_err(|e| SendEmailError::Invalid(e.into()))?;
+            builder = builder.raw_header(HeaderValue::new(name, header.value.clone()));
+        }
+        builder
+            .multipart(MultiPart::alternative_plain_html(
+                self.text_body.to_owned(),
+                self.html_body.to_owned(),
+            ))
+            .map_err(|e| SendEmailError::Invalid(e.into()))
+    }
+}
+
+fn message_id(message: &Message) -> Option<String> {
+    message.headers().get_raw("Message-ID").map(str::to_owned)
+}
+
+fn parse_mailbox(email: &SubscriberEmail) -> Result<Mailbox, SendEmailError> {
+    email
+        .as_ref()
+        .parse()
+        .map_err(|e: lettre::address::AddressError| SendEmailError::Invalid(e.into()))
+}
+
+pub struct EmailClient {
+    sender: SubscriberEmail,
+    transport: Box<dyn EmailTransport>,
+}
+
+impl EmailClient {
+    /// An email client delivering through our HTTP email API.
+    pub fn new(
+        base_url: String,
+        sender: SubscriberEmail,
+        authorization_token: secrecy::Secret<String>,
+        timeout: Duration,
+    ) -> Self {
+        Self::with_transport(
+            sender,
+            HttpApiTransport::new(base_url, authorization_token, timeout),
+        )
+    }
+
+    pub fn with_transport(
+        sender: SubscriberEmail,
+        transport: impl EmailTransport + 'static,
+    ) -> Self {
+        Self {
+            sender,
+            transport: Box::new(transport),
+        }
+    }
+
+    pub async fn send_email(
+        &self,
+        recipient: &SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+    ) -> Result<SentEmail, SendEmailError> {
+        self.send_email_with_headers(recipient, subject, html_content, text_content, &[])
+            .await
+    }
+
+    /// Same as `send_email`, attaching extra headers (e.g. `List-Unsubscribe`)
+    /// to the outgoing message.
+    pub async fn send_email_with_headers(
+        &self,
+        recipient: &SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+        headers: &[EmailHeader],
+    ) -> Result<SentEmail, SendEmailError> {
+        let email = Email {
+            from: &self.sender,
+            to: recipient,
+            subject,
+            html_body: html_content,
+            text_body: text_content,
+            headers,
+        };
+        self.transport.send(&email).await
+    }
+}
+
+/// Postmark's code for a recipient that hard-bounced or complained before.
+const INACTIVE_RECIPIENT_ERROR_CODE: i64 = 406;
+
+/// SMTP reply codes for a mailbox that does not exist or cannot be used.
+const MAILBOX_UNAVAILABLE_REPLY_CODES: [u16; 3] = [550, 551, 553];
+
+/// SMTP reply codes for credentials the server does not accept.
+const AUTHENTICATION_FAILED_REPLY_CODES: [u16; 2] = [530, 535];
+
+#[derive(thiserror::Error, Debug)]
+pub enum SendEmailError {
+    #[error("The email provider did not answer in time.")]
+    Timeout(#[source] anyhow::Error),
+    #[error("The email provider is rate-limiting us.")]
+    RateLimited { retry_after: Option<Duration> },
+    #[error("The email provider could not be reached or failed to process the request.")]
+    Transient(#[source] anyhow::Error),
+    /// Our credentials are wrong or revoked: it says nothing about the recipient,
+    /// and deliveries can go through once the configuration is fixed.
+    #[error("The email provider refused our credentials (status code {status_code}).")]
+    Unauthorized { status_code: u16 },
+    /// `status_code` is an HTTP status code or an SMTP reply code, depending on the transport.
+    #[error("The email provider rejected the email (status code {status_code}, error code {error_code:?}): {message}")]
+    Rejected {
+        status_code: u16,
+        error_code: Option<i64>,
+        message: String,
+    },
+    #[error("The email could not be built.")]
+    Invalid(#[source] anyhow::Error),
+}
+
+impl SendEmailError {
+    /// Whether sending the same email again later has a chance of succeeding.
+    pub fn is_transient(&self) -> bool {
+        !matches!(self, Self::Rejected { .. } | Self::Invalid(_))
+    }
+
+    /// How long the provider asked us to wait before trying again, if it told us.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::RateLimited { retry_after } => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// Whether the provider will never deliver to this recipient.
+    pub fn is_recipient_rejected(&self) -> bool {
+        match self {
+            Self::Rejected {
+                error_code: Some(INACTIVE_RECIPIENT_ERROR_CODE),
+                ..
+            } => true,
+            Self::Rejected {
+                status_code,
+                error_code: None,
+                ..
+            } => MAILBOX_UNAVAILABLE_REPLY_CODES.contains(status_code),
+            _ => false,
+        }
+    }
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct EmailHeader {
+    name: String,
+    value: String,
+}
+
+impl EmailHeader {
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+        }
+    }
+}
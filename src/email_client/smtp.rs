@@ -0,0 +1,63 @@
+use super::{
+    message_id, Email, EmailTransport, SendEmailError, SentEmail, AUTHENTICATION_FAILED_REPLY_CODES,
+};
+use crate::configuration::SmtpSettings;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+use secrecy::ExposeSecret;
+use std::time::Duration;
+
+/// Deliver emails to an SMTP relay, upgrading the connection with STARTTLS
+/// unless told otherwise.
+pub struct SmtpTransport {
+    mailer: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpTransport {
+    pub fn new(settings: &SmtpSettings, timeout: Duration) -> Result<Self, anyhow::Error> {
+        let builder = if settings.starttls {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&settings.host)?
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&settings.host)
+        };
+        let mut builder = builder.port(settings.port).timeout(Some(timeout));
+        if let (Some(username), Some(password)) = (&settings.username, &settings.password) {
+            builder = builder.credentials(Credentials::new(
+                username.clone(),
+                password.expose_secret().clone(),
+            ));
+        }
+        Ok(Self {
+            mailer: builder.build(),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailTransport for SmtpTransport {
+    async fn send(&self, email: &Email<'_>) -> Result<SentEmail, SendEmailError> {
+        let message = email.to_mime_message()?;
+        let message_id = message_id(&message);
+        self.mailer.send(message).await.map_err(from_smtp_error)?;
+        Ok(SentEmail { message_id })
+    }
+}
+
+fn from_smtp_error(e: lettre::transport::smtp::Error) -> SendEmailError {
+    let status_code = e.status().map(u16::from);
+    if let Some(status_code) =
+        status_code.filter(|code| AUTHENTICATION_FAILED_REPLY_CODES.contains(code))
+    {
+        SendEmailError::Unauthorized { status_code }
+    } else if e.is_permanent() {
+        SendEmailError::Rejected {
+            status_code: status_code.unwrap_or_default(),
+            error_code: None,
+            message: e.to_string(),
+        }
+    } else if e.is_timeout() {
+        SendEmailError::Timeout(e.into())
+    } else {
+        SendEmailError::Transient(e.into())
+    }
+}
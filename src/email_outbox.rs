@@ -0,0 +1,227 @@
+use crate::configuration::IssueDeliveryWorkerSettings;
+use crate::domain::SubscriberEmail;
+use crate::email_client::{EmailClient, SendEmailError};
+use crate::issue_delivery_worker::{ExecutionOutcome, NEW_TASKS_CHANNEL};
+use crate::rate_limiter::TokenBucket;
+use crate::suppression::{is_suppressed, suppress_subscriber, SuppressionSource};
+use chrono::Utc;
+use sqlx::{PgPool, Postgres, Transaction};
+use tracing::{field::display, Span};
+use uuid::Uuid;
+
+type PgTransaction = Transaction<'static, Postgres>;
+
+/// A transactional email waiting to be sent.
+pub struct OutgoingEmail<'a> {
+    pub recipient: &'a SubscriberEmail,
+    pub subject: &'a str,
+    pub html_body: &'a str,
+    pub text_body: &'a str,
+}
+
+/// Queue an email for the background worker: it goes out only if `transaction`
+/// commits, and the request enqueuing it never waits on the email provider.
+#[tracing::instrument(skip_all, fields(recipient = %email.recipient))]
+pub async fn enqueue_email(
+    transaction: &mut Transaction<'_, Postgres>,
+    email: OutgoingEmail<'_>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO email_outbox (id, recipient, subject, html_body, text_body, created_at)
+        VALUES ($1, $2, $3, $4, $5, now())
+        "#,
+        Uuid::new_v4(),
+        email.recipient.as_ref(),
+        email.subject,
+        email.html_body,
+        email.text_body
+    )
+    .execute(&mut *transaction)
+    .await?;
+    // Wakes up the delivery workers once the transaction commits.
+    sqlx::query("SELECT pg_notify($1, 'email_outbox')")
+        .bind(NEW_TASKS_CHANNEL)
+        .execute(&mut *transaction)
+        .await?;
+    Ok(())
+}
+
+struct OutboxEmail {
+    id: Uuid,
+    recipient: String,
+    subject: String,
+    html_body: String,
+    text_body: String,
+    n_retries: i16,
+}
+
+/// Send the oldest email in the outbox that is due, if any.
+///
+/// The rate limiter is waited on before dequeuing: nobody should sleep
+/// while holding a connection from the pool.
+#[tracing::instrument(
+    skip_all,
+    fields(email_id=tracing::field::Empty, recipient=tracing::field::Empty),
+    err
+)]
+pub async fn try_send_outbox_email(
+    pool: &PgPool,
+    email_client: &EmailClient,
+    worker_settings: &IssueDeliveryWorkerSettings,
+    rate_limiter: &TokenBucket,
+) -> Result<ExecutionOutcome, anyhow::Error> {
+    rate_limiter.acquire().await?;
+    let (mut transaction, email) = match dequeue_email(pool).await? {
+        Some(dequeued) => dequeued,
+        None => {
+            rate_limiter.release().await?;
+            return Ok(ExecutionOutcome::EmptyQueue);
+        }
+    };
+    Span::current()
+        .record("email_id", &display(email.id))
+        .record("recipient", &display(&email.recipient));
+    if is_suppressed(&mut transaction, &email.recipient).await? {
+        tracing::info!("Dropping an email to an address on the suppression list.");
+        return delete_email(transaction, email.id).await;
+    }
+    let recipient = match SubscriberEmail::parse(email.recipient.clone()) {
+        Ok(recipient) => recipient,
+        Err(e) => {
+            tracing::error!(error.message = %e, "Dropping an email to an invalid address.");
+            return mark_email_as_failed(transaction, email.id, &e).await;
+        }
+    };
+    let outcome = email_client
+        .send_email(
+            &recipient,
+            &email.subject,
+            &email.html_body,
+            &email.text_body,
+        )
+        .await;
+    match outcome {
+        Ok(_) => delete_email(transaction, email.id).await,
+        Err(e) => {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to send an email from the outbox."
+            );
+            handle_send_failure(transaction, &email, &e, worker_settings).await
+        }
+    }
+}
+
+/// Mirrors the newsletter deliveries: only failures that might go away on their
+/// own are retried, everything else is kept around with `failed_at` set.
+async fn handle_send_failure(
+    mut transaction: PgTransaction,
+    email: &OutboxEmail,
+    e: &SendEmailError,
+    worker_settings: &IssueDeliveryWorkerSettings,
+) -> Result<ExecutionOutcome, anyhow::Error> {
+    if e.is_transient() && email.n_retries < worker_settings.max_retries {
+        let backoff = worker_settings
+            .backoff(email.n_retries)
+            .max(e.retry_after().unwrap_or_default());
+        return retry_email_later(transaction, email.id, backoff, &e.to_string()).await;
+    }
+    if e.is_recipient_rejected() {
+        tracing::warn!("The email provider refuses to deliver to this address. Suppressing it.");
+        suppress_subscriber(
+            &mut transaction,
+            &email.recipient,
+            SuppressionSource::EmailProvider,
+            &e.to_string(),
+        )
+        .await?;
+    } else if e.is_transient() {
+        tracing::error!("Giving up on the email after {} retries.", email.n_retries);
+    }
+    mark_email_as_failed(transaction, email.id, &e.to_string()).await
+}
+
+#[tracing::instrument(skip_all)]
+async fn dequeue_email(
+    pool: &PgPool,
+) -> Result<Option<(PgTransaction, OutboxEmail)>, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let email = sqlx::query_as!(
+        OutboxEmail,
+        r#"
+        SELECT id, recipient, subject, html_body, text_body, n_retries
+        FROM email_outbox
+        WHERE failed_at IS NULL AND execute_after <= now()
+        ORDER BY created_at
+        FOR UPDATE
+        SKIP LOCKED
+        LIMIT 1
+        "#,
+    )
+    .fetch_optional(&mut transaction)
+    .await?;
+    Ok(email.map(|email| (transaction, email)))
+}
+
+#[tracing::instrument(skip_all)]
+async fn delete_email(
+    mut transaction: PgTransaction,
+    email_id: Uuid,
+) -> Result<ExecutionOutcome, anyhow::Error> {
+    sqlx::query!(r#"DELETE FROM email_outbox WHERE id = $1"#, email_id)
+        .execute(&mut transaction)
+        .await?;
+    transaction.commit().await?;
+    Ok(ExecutionOutcome::TaskCompleted)
+}
+
+#[tracing::instrument(skip_all)]
+async fn retry_email_later(
+    mut transaction: PgTransaction,
+    email_id: Uuid,
+    backoff: std::time::Duration,
+    last_error: &str,
+) -> Result<ExecutionOutcome, anyhow::Error> {
+    let execute_after = Utc::now() + chrono::Duration::from_std(backoff)?;
+    sqlx::query!(
+        r#"
+        UPDATE email_outbox
+        SET
+            n_retries = n_retries + 1,
+            execute_after = $2,
+            last_error = $3
+        WHERE id = $1
+        "#,
+        email_id,
+        execute_after,
+        last_error
+    )
+    .execute(&mut transaction)
+    .await?;
+    transaction.commit().await?;
+    Ok(ExecutionOutcome::TaskCompleted)
+}
+
+/// Failed emails stay in the outbox, where they can be inspected by hand.
+#[tracing::instrument(skip_all)]
+async fn mark_email_as_failed(
+    mut transaction: PgTransaction,
+    email_id: Uuid,
+    last_error: &str,
+) -> Result<ExecutionOutcome, anyhow::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE email_outbox
+        SET failed_at = now(), last_error = $2
+        WHERE id = $1
+        "#,
+        email_id,
+        last_error
+    )
+    .execute(&mut transaction)
+    .await?;
+    transaction.commit().await?;
+    Ok(ExecutionOutcome::TaskCompleted)
+}
@@ -1,81 +1,172 @@
-use crate::domain::SubscriberEmail;
-use crate::email_client::EmailClient;
-use crate::{configuration::Settings, startup};
+use crate::domain::{DeliveryStatus, IssueSlug, IssueTemplate, SubscriberEmail, TemplateContext};
+use crate::email_client::{EmailClient, EmailHeader, SendEmailError, SentEmail};
+use crate::email_outbox::try_send_outbox_email;
+use crate::rate_limiter::TokenBucket;
+use crate::routes::unsubscribe_link;
+use crate::shutdown::Shutdown;
+use crate::startup::{ApplicationBaseUrl, HmacSecret};
+use crate::suppression::{is_suppressed, suppress_subscriber, SuppressionSource};
+use crate::{
+    configuration::{IssueDeliveryWorkerSettings, Settings},
+    startup,
+};
 use actix_web_lab::web::Spa;
-use sqlx::{PgPool, Postgres, Transaction};
-use std::time::Duration;
+use chrono::{DateTime, Utc};
+use sqlx::postgres::PgListener;
+use sqlx::{PgExecutor, PgPool, Postgres, Transaction};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
 use tracing::{field::display, Span};
 use uuid::Uuid;
 
-enum ExecutionOutcome {
+pub enum ExecutionOutcome {
     TaskCompleted,
     EmptyQueue,
 }
 
+/// The channel workers `LISTEN` on to hear about newly enqueued deliveries
+/// and outbox emails.
+pub(crate) const NEW_TASKS_CHANNEL: &str = "issue_delivery_queue";
+
+struct Task {
+    newsletter_issue_id: Uuid,
+    subscriber_email: String,
+    n_retries: i16,
+}
+
+/// Deliver the next issue in the queue, if any.
+///
+/// Everything runs on the transaction holding the task, and the rate limiter
+/// is waited on before dequeuing: a task never holds more than one connection
+/// from the pool, nor sleeps while holding one.
 #[tracing::instrument(
     skip_all,
     fields(
         newsletter_issue_id=tracing::field::Empty,
-        subscriber_email=tracing::field::Empty
+        subscriber_email=tracing::field::Empty,
+        n_retries=tracing::field::Empty
     ),
     err
 )]
-async fn try_execute_task(
+pub async fn try_execute_task(
     pool: &PgPool,
     email_client: &EmailClient,
+    base_url: &ApplicationBaseUrl,
+    hmac_secret: &HmacSecret,
+    worker_settings: &IssueDeliveryWorkerSettings,
+    rate_limiter: &TokenBucket,
 ) -> Result<ExecutionOutcome, anyhow::Error> {
+    rate_limiter.acquire().await?;
     let task = dequeue_task(pool).await?;
     if task.is_none() {
+        rate_limiter.release().await?;
         return Ok(ExecutionOutcome::EmptyQueue);
     }
-    let (transaction, issue_id, email) = task.unwrap();
+    let (mut transaction, task) = task.unwrap();
     Span::current()
-        .record("newsletter_issue_id", &display(issue_id))
-        .record("subscriber_email", &display(&email));
-    match SubscriberEmail::parse(email.clone()) {
-        Ok(email) => {
-            let issue = get_issue(pool, issue_id).await?;
-            if let Err(e) = email_client
-                .send_email(
-                    &email,
-                    &issue.title,
-                    &issue.html_content,
-                    &issue.text_content,
-                )
-                .await
-            {
+        .record("newsletter_issue_id", &display(task.newsletter_issue_id))
+        .record("subscriber_email", &display(&task.subscriber_email))
+        .record("n_retries", &display(task.n_retries));
+    let (status, provider_message_id, last_error) =
+        match SubscriberEmail::parse(task.subscriber_email.clone()) {
+            Ok(email) if is_suppressed(&mut transaction, email.as_ref()).await? => {
+                tracing::info!("Skipping an address on the suppression list.");
+                (DeliveryStatus::SkippedSuppressed, None, None)
+            }
+            Ok(email) => match get_subscriber(&mut transaction, email.as_ref()).await? {
+                Some(subscriber) => {
+                    let issue = get_issue(&mut transaction, task.newsletter_issue_id).await?;
+                    let unsubscribe_url = unsubscribe_link(&base_url.0, subscriber.id, hmac_secret);
+                    let archive_url = issue_archive_url(&base_url.0, &issue.slug);
+                    let context = TemplateContext {
+                        subscriber_name: &subscriber.name,
+                        unsubscribe_url: &unsubscribe_url,
+                        archive_url: &archive_url,
+                    };
+                    match send_issue(email_client, &email, &issue, &context).await {
+                        Ok(sent_email) => (DeliveryStatus::Sent, sent_email.message_id, None),
+                        Err(e) => {
+                            tracing::error!(
+                                error.cause_chain = ?e,
+                                error.message = %e,
+                                "Failed to deliver issue to a confirmed subscriber."
+                            );
+                            handle_delivery_failure(transaction, &task, &e, worker_settings)
+                                .await?;
+                            return Ok(ExecutionOutcome::TaskCompleted);
+                        }
+                    }
+                }
+                None => {
+                    tracing::warn!("Skipping a subscriber that is no longer in our records.");
+                    (DeliveryStatus::SkippedUnsubscribed, None, None)
+                }
+            },
+            Err(e) => {
                 tracing::error!(
                     error.cause_chain = ?e,
                     error.message = %e,
-                    "Failed to deliver issue to a confirmed subscriber. \
-                    Skipping."
+                    "Skipping a confirmed subscriber. \
+                    Their stored contact details are invalid"
                 );
+                (DeliveryStatus::SkippedInvalidEmail, None, Some(e))
             }
-        }
-        Err(e) => {
-            tracing::error!(
-                error.cause_chain = ?e,
-                error.message = %e,
-                "Skipping a confirmed subscriber. \
-                Their stored contact details are invalid"
-            );
-        }
-    }
-    delete_task(transaction, issue_id, &email).await?;
+        };
+    update_delivery_log(
+        &mut transaction,
+        &task,
+        status,
+        provider_message_id.as_deref(),
+        last_error.as_deref(),
+    )
+    .await?;
+    delete_task(transaction, &task).await?;
     Ok(ExecutionOutcome::TaskCompleted)
 }
 
 type PgTransaction = Transaction<'static, Postgres>;
 
+/// Only failures that might go away on their own are retried.
+/// Everything else ends up in `failed_deliveries` straight away.
+async fn handle_delivery_failure(
+    mut transaction: PgTransaction,
+    task: &Task,
+    e: &SendEmailError,
+    worker_settings: &IssueDeliveryWorkerSettings,
+) -> Result<(), anyhow::Error> {
+    if e.is_transient() && task.n_retries < worker_settings.max_retries {
+        let backoff = worker_settings
+            .backoff(task.n_retries)
+            .max(e.retry_after().unwrap_or_default());
+        return retry_task_later(transaction, task, backoff, &e.to_string()).await;
+    }
+    if e.is_recipient_rejected() {
+        tracing::warn!("The email provider refuses to deliver to this address. Suppressing it.");
+        suppress_subscriber(
+            &mut transaction,
+            &task.subscriber_email,
+            SuppressionSource::EmailProvider,
+            &e.to_string(),
+        )
+        .await?;
+    } else if e.is_transient() {
+        tracing::error!(
+            "Giving up on the delivery after {} retries.",
+            task.n_retries
+        );
+    }
+    move_task_to_failed_deliveries(transaction, task, &e.to_string()).await
+}
+
 #[tracing::instrument(skip_all)]
-async fn dequeue_task(
-    pool: &PgPool,
-) -> Result<Option<(PgTransaction, Uuid, String)>, anyhow::Error> {
+async fn dequeue_task(pool: &PgPool) -> Result<Option<(PgTransaction, Task)>, anyhow::Error> {
     let mut transaction = pool.begin().await?;
     let r = sqlx::query!(
         r#"
-        SELECT newsletter_issue_id, subscriber_email
+        SELECT newsletter_issue_id, subscriber_email, n_retries
         FROM issue_delivery_queue
+        WHERE execute_after <= now()
         FOR UPDATE
         SKIP LOCKED
         LIMIT 1
@@ -86,8 +177,11 @@ async fn dequeue_task(
     if let Some(r) = r {
         Ok(Some((
             transaction,
-            r.newsletter_issue_id,
-            r.subscriber_email,
+            Task {
+                newsletter_issue_id: r.newsletter_issue_id,
+                subscriber_email: r.subscriber_email,
+                n_retries: r.n_retries,
+            },
         )))
     } else {
         Ok(None)
@@ -95,11 +189,7 @@ async fn dequeue_task(
 }
 
 #[tracing::instrument(skip_all)]
-async fn delete_task(
-    mut transaction: PgTransaction,
-    issue_id: Uuid,
-    email: &str,
-) -> Result<(), anyhow::Error> {
+async fn delete_task(mut transaction: PgTransaction, task: &Task) -> Result<(), anyhow::Error> {
     sqlx::query!(
         r#"
         DELETE FROM issue_delivery_queue
@@ -107,64 +197,639 @@ async fn delete_task(
             newsletter_issue_id = $1 AND
             subscriber_email = $2
         "#,
-        issue_id,
-        email
+        task.newsletter_issue_id,
+        task.subscriber_email
     )
     .execute(&mut transaction)
     .await?;
+    mark_issue_as_sent_if_delivered(&mut transaction, task.newsletter_issue_id).await?;
     transaction.commit().await?;
     Ok(())
 }
 
-struct NewsletterIssue {
-    title: String,
-    text_content: String,
-    html_content: String,
+/// Flag the issue as `sent` once the last of its delivery tasks is gone.
+#[tracing::instrument(skip_all)]
+async fn mark_issue_as_sent_if_delivered(
+    transaction: &mut PgTransaction,
+    issue_id: Uuid,
+) -> Result<(), anyhow::Error> {
+    // Serialise workers completing tasks for the same issue: the check below
+    // must see the deletions committed by the others.
+    sqlx::query!(
+        r#"
+        SELECT newsletter_issue_id
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        FOR UPDATE
+        "#,
+        issue_id
+    )
+    .fetch_one(&mut *transaction)
+    .await?;
+    sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET status = 'sent', updated_at = now()
+        WHERE
+            newsletter_issue_id = $1 AND
+            status = 'sending' AND
+            NOT EXISTS (
+                SELECT 1 FROM issue_delivery_queue WHERE newsletter_issue_id = $1
+            )
+        "#,
+        issue_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+    Ok(())
 }
 
 #[tracing::instrument(skip_all)]
-async fn get_issue(pool: &PgPool, issue_id: Uuid) -> Result<NewsletterIssue, anyhow::Error> {
+async fn retry_task_later(
+    mut transaction: PgTransaction,
+    task: &Task,
+    backoff: Duration,
+    last_error: &str,
+) -> Result<(), anyhow::Error> {
+    let execute_after = Utc::now() + chrono::Duration::from_std(backoff)?;
+    sqlx::query!(
+        r#"
+        UPDATE issue_delivery_queue
+        SET
+            n_retries = n_retries + 1,
+            execute_after = $3
+        WHERE
+            newsletter_issue_id = $1 AND
+            subscriber_email = $2
+        "#,
+        task.newsletter_issue_id,
+        task.subscriber_email,
+        execute_after
+    )
+    .execute(&mut transaction)
+    .await?;
+    update_delivery_log(
+        &mut transaction,
+        task,
+        DeliveryStatus::Queued,
+        None,
+        Some(last_error),
+    )
+    .await?;
+    transaction.commit().await?;
+    Ok(())
+}
+
+/// Park a task we could not deliver in `failed_deliveries`, where it can be
+/// inspected and requeued by hand.
+#[tracing::instrument(skip_all)]
+async fn move_task_to_failed_deliveries(
+    mut transaction: PgTransaction,
+    task: &Task,
+    last_error: &str,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO failed_deliveries (
+            newsletter_issue_id,
+            subscriber_email,
+            n_retries,
+            last_error,
+            failed_at
+        )
+        VALUES ($1, $2, $3, $4, now())
+        ON CONFLICT (newsletter_issue_id, subscriber_email) DO UPDATE
+        SET
+            n_retries = EXCLUDED.n_retries,
+            last_error = EXCLUDED.last_error,
+            failed_at = EXCLUDED.failed_at
+        "#,
+        task.newsletter_issue_id,
+        task.subscriber_email,
+        task.n_retries,
+        last_error
+    )
+    .execute(&mut transaction)
+    .await?;
+    update_delivery_log(
+        &mut transaction,
+        task,
+        DeliveryStatus::Failed,
+        None,
+        Some(last_error),
+    )
+    .await?;
+    delete_task(transaction, task).await
+}
+
+#[tracing::instrument(skip_all)]
+async fn update_delivery_log(
+    transaction: &mut PgTransaction,
+    task: &Task,
+    status: DeliveryStatus,
+    provider_message_id: Option<&str>,
+    last_error: Option<&str>,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE issue_delivery_log
+        SET
+            status = $3,
+            provider_message_id = $4,
+            last_error = $5,
+            updated_at = now()
+        WHERE
+            newsletter_issue_id = $1 AND
+            subscriber_email = $2
+        "#,
+        task.newsletter_issue_id,
+        task.subscriber_email,
+        status.as_str(),
+        provider_message_id,
+        last_error
+    )
+    .execute(transaction)
+    .await?;
+    Ok(())
+}
+
+/// Enqueue a delivery task for every confirmed subscriber, tracking each of
+/// them in `issue_delivery_log`.
+/// An issue with nobody to deliver to is marked as `sent` straight away.
+#[tracing::instrument(skip_all)]
+pub async fn enqueue_delivery_tasks(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    let n_enqueued = sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_queue (
+            newsletter_issue_id,
+            subscriber_email
+        )
+        SELECT $1, email
+        FROM subscriptions
+        WHERE status = 'confirmed'
+        "#,
+        newsletter_issue_id,
+    )
+    .execute(&mut *transaction)
+    .await?
+    .rows_affected();
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_log (
+            newsletter_issue_id,
+            subscriber_email,
+            status,
+            updated_at
+        )
+        SELECT newsletter_issue_id, subscriber_email, 'queued', now()
+        FROM issue_delivery_queue
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id,
+    )
+    .execute(&mut *transaction)
+    .await?;
+    // Delivered to listening workers when the transaction commits.
+    // `pg_notify` returns `void`, which the `query!` macro cannot describe.
+    sqlx::query("SELECT pg_notify($1, $2)")
+        .bind(NEW_TASKS_CHANNEL)
+        .bind(newsletter_issue_id.to_string())
+        .execute(&mut *transaction)
+        .await?;
+    if n_enqueued == 0 {
+        sqlx::query!(
+            r#"
+            UPDATE newsletter_issues
+            SET status = 'sent', updated_at = now()
+            WHERE newsletter_issue_id = $1
+            "#,
+            newsletter_issue_id,
+        )
+        .execute(&mut *transaction)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Give a newly published issue the slug it is listed under in the archive,
+/// derived from its title.
+#[tracing::instrument(skip(transaction))]
+pub async fn assign_slug(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    let issue = sqlx::query!(
+        r#"SELECT title FROM newsletter_issues WHERE newsletter_issue_id = $1"#,
+        newsletter_issue_id
+    )
+    .fetch_one(&mut *transaction)
+    .await?;
+    let slug = IssueSlug::from_title(&issue.title);
+    let taken: Vec<String> = sqlx::query!(
+        r#"
+        SELECT slug AS "slug!"
+        FROM newsletter_issues
+        WHERE slug = $1 OR slug LIKE $1 || '-%'
+        "#,
+        slug.as_ref()
+    )
+    .fetch_all(&mut *transaction)
+    .await?
+    .into_iter()
+    .map(|r| r.slug)
+    .collect();
+    let slug = slug.first_available(&taken);
+    sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET slug = $2
+        WHERE newsletter_issue_id = $1 AND slug IS NULL
+        "#,
+        newsletter_issue_id,
+        slug.as_ref()
+    )
+    .execute(&mut *transaction)
+    .await?;
+    Ok(())
+}
+
+/// Start sending every `scheduled` issue whose time has come.
+/// Returns how many issues were moved to `sending`.
+#[tracing::instrument(skip_all, err)]
+pub async fn enqueue_due_scheduled_issues(pool: &PgPool) -> Result<u64, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    // `SKIP LOCKED` keeps concurrent workers from enqueuing the same issue twice;
+    // the row lock also makes an editor cancelling the schedule wait for us.
+    let due_issues = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET status = 'sending', published_at = now(), updated_at = now()
+        WHERE newsletter_issue_id IN (
+            SELECT newsletter_issue_id
+            FROM newsletter_issues
+            WHERE status = 'scheduled' AND scheduled_for <= now()
+            FOR UPDATE
+            SKIP LOCKED
+        )
+        RETURNING newsletter_issue_id
+        "#,
+    )
+    .fetch_all(&mut transaction)
+    .await?;
+    for issue in &due_issues {
+        tracing::info!(
+            newsletter_issue_id = %issue.newsletter_issue_id,
+            "Sending a scheduled newsletter issue."
+        );
+        assign_slug(&mut transaction, issue.newsletter_issue_id).await?;
+        enqueue_delivery_tasks(&mut transaction, issue.newsletter_issue_id).await?;
+    }
+    transaction.commit().await?;
+    Ok(due_issues.len() as u64)
+}
+
+pub struct NewsletterIssue {
+    pub title: String,
+    pub slug: String,
+    pub text_content: String,
+    pub html_content: String,
+    /// `None` for drafts, which only go out as test copies.
+    pub published_at: Option<DateTime<Utc>>,
+}
+
+/// Send an issue to a single recipient, the way every subscriber receives it.
+pub async fn send_issue(
+    email_client: &EmailClient,
+    recipient: &SubscriberEmail,
+    issue: &NewsletterIssue,
+    context: &TemplateContext<'_>,
+) -> Result<SentEmail, SendEmailError> {
+    email_client
+        .send_email_with_headers(
+            recipient,
+            &issue.title,
+            &issue.html_content_for(context),
+            &issue.text_content_for(context),
+            &list_unsubscribe_headers(context.unsubscribe_url),
+        )
+        .await
+}
+
+/// Where readers can find the issue online.
+pub fn issue_archive_url(base_url: &str, slug: &str) -> String {
+    format!("{}/issues/{}", base_url, slug)
+}
+
+impl NewsletterIssue {
+    fn html_content_for(&self, context: &TemplateContext) -> String {
+        let html_content = match IssueTemplate::parse(&self.html_content) {
+            Ok(template) => template.render_html(context),
+            Err(e) => untemplated_content(&self.html_content, e),
+        };
+        format!(
+            "{}<hr />\
+<p>Don't want to receive these emails anymore? \
+<a href=\"{}\">Unsubscribe</a>.</p>",
+            html_content,
+            htmlescape::encode_attribute(context.unsubscribe_url)
+        )
+    }
+
+    fn text_content_for(&self, context: &TemplateContext) -> String {
+        let text_content = match IssueTemplate::parse(&self.text_content) {
+            Ok(template) => template.render_text(context),
+            Err(e) => untemplated_content(&self.text_content, e),
+        };
+        format!(
+            "{}\n\n--\nDon't want to receive these emails anymore? \
+Visit {} to unsubscribe.",
+            text_content, context.unsubscribe_url
+        )
+    }
+}
+
+/// Templates are validated when an issue is saved, but issues written before
+/// templating was introduced may contain stray braces: send those verbatim.
+fn untemplated_content(content: &str, e: String) -> String {
+    tracing::warn!(
+        error.message = %e,
+        "The newsletter issue content is not a valid template. Sending it as is."
+    );
+    content.to_string()
+}
+
+/// RFC 8058 headers, letting mail clients offer a native one-click unsubscribe button.
+fn list_unsubscribe_headers(unsubscribe_link: &str) -> [EmailHeader; 2] {
+    [
+        EmailHeader::new("List-Unsubscribe", format!("<{}>", unsubscribe_link)),
+        EmailHeader::new("List-Unsubscribe-Post", "List-Unsubscribe=One-Click"),
+    ]
+}
+
+pub struct Subscriber {
+    pub id: Uuid,
+    pub name: String,
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn get_subscriber(
+    executor: impl PgExecutor<'_>,
+    email: &str,
+) -> Result<Option<Subscriber>, anyhow::Error> {
+    let subscriber = sqlx::query_as!(
+        Subscriber,
+        r#"
+        SELECT id, name
+        FROM subscriptions
+        WHERE email = $1
+        "#,
+        email
+    )
+    .fetch_optional(executor)
+    .await?;
+    Ok(subscriber)
+}
+
+#[tracing::instrument(skip_all)]
+async fn get_issue(
+    executor: impl PgExecutor<'_>,
+    issue_id: Uuid,
+) -> Result<NewsletterIssue, anyhow::Error> {
+    // Issues get their slug when they are published, before any delivery is enqueued.
     let issue = sqlx::query_as!(
         NewsletterIssue,
         r#"
-        SELECT title, text_content, html_content
+        SELECT title, slug AS "slug!", text_content, html_content, published_at
         FROM newsletter_issues
         WHERE 
             newsletter_issue_id = $1
         "#,
         issue_id
     )
-    .fetch_one(pool)
+    .fetch_one(executor)
     .await?;
     Ok(issue)
 }
 
-async fn worker_loop(pool: PgPool, email_client: EmailClient) -> Result<(), anyhow::Error> {
-    loop {
-        match try_execute_task(&pool, &email_client).await {
+/// Everything a delivery task needs, shared by all the tasks of a worker.
+struct Worker {
+    pool: PgPool,
+    email_client: EmailClient,
+    base_url: ApplicationBaseUrl,
+    hmac_secret: HmacSecret,
+    settings: IssueDeliveryWorkerSettings,
+    rate_limiter: TokenBucket,
+    /// Woken up by `forward_new_task_notifications` whenever deliveries or
+    /// outbox emails are enqueued.
+    new_tasks: Notify,
+}
+
+async fn worker_loop(worker: Arc<Worker>, shutdown: Shutdown) -> Result<(), anyhow::Error> {
+    let mut next_schedule_check = Instant::now();
+    while !shutdown.is_triggered() {
+        if Instant::now() >= next_schedule_check {
+            // Errors are already logged by the instrumentation, and the check
+            // will simply run again later.
+            let _ = enqueue_due_scheduled_issues(&worker.pool).await;
+            next_schedule_check = Instant::now() + worker.settings.scheduled_issues_poll_interval();
+        }
+        // Registered before looking at the queue: tasks enqueued while we do
+        // must still wake us up.
+        let new_tasks = worker.new_tasks.notified();
+        tokio::pin!(new_tasks);
+        new_tasks.as_mut().enable();
+        let outcome = tokio::select! {
+            outcome = try_execute_next(&worker) => outcome,
+            // Dropping the task rolls its transaction back: the delivery
+            // goes back to the queue for the next worker to pick up.
+            _ = drain_deadline(&shutdown, worker.settings.drain_timeout()) => {
+                tracing::warn!("Gave up on the delivery in progress to shut down in time.");
+                break;
+            }
+        };
+        match outcome {
             Ok(ExecutionOutcome::EmptyQueue) => {
-                tokio::time::sleep(Duration::from_secs(10)).await;
+                let timeout = idle_timeout(&worker, next_schedule_check).await;
+                tokio::select! {
+                    _ = new_tasks => {},
+                    _ = tokio::time::sleep(timeout) => {},
+                    _ = shutdown.triggered() => {},
+                }
             }
             Err(_) => {
-                tokio::time::sleep(Duration::from_secs(1)).await;
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_secs(1)) => {},
+                    _ = shutdown.triggered() => {},
+                }
             }
             Ok(ExecutionOutcome::TaskCompleted) => {}
         }
     }
+    Ok(())
 }
 
-pub async fn run_worker_until_stopped(configuration: Settings) -> Result<(), anyhow::Error> {
-    let connection_pool = startup::get_connection_pool(&configuration.database);
-    let sender_email = configuration
+/// Emails in the outbox go first: someone is waiting for them to arrive.
+async fn try_execute_next(worker: &Worker) -> Result<ExecutionOutcome, anyhow::Error> {
+    let outcome = try_send_outbox_email(
+        &worker.pool,
+        &worker.email_client,
+        &worker.settings,
+        &worker.rate_limiter,
+    )
+    .await?;
+    if let ExecutionOutcome::TaskCompleted = outcome {
+        return Ok(outcome);
+    }
+    try_execute_task(
+        &worker.pool,
+        &worker.email_client,
+        &worker.base_url,
+        &worker.hmac_secret,
+        &worker.settings,
+        &worker.rate_limiter,
+    )
+    .await
+}
+
+/// Resolves `drain_timeout` after the shutdown has been triggered.
+async fn drain_deadline(shutdown: &Shutdown, drain_timeout: Duration) {
+    shutdown.triggered().await;
+    tokio::time::sleep(drain_timeout).await;
+}
+
+/// How long an idle task can sleep before looking at the queues again: until
+/// the earliest retry or outbox email comes due, or until the next look at
+/// scheduled issues. Never more than the poll interval, our safety net for
+/// notifications lost while reconnecting.
+async fn idle_timeout(worker: &Worker, next_schedule_check: Instant) -> Duration {
+    let timeout = worker
+        .settings
+        .poll_interval()
+        .min(next_schedule_check.saturating_duration_since(Instant::now()));
+    match time_until_next_task_is_due(&worker.pool).await {
+        Ok(Some(until_due)) => timeout.min(until_due),
+        // Errors are already logged by the instrumentation.
+        Ok(None) | Err(_) => timeout,
+    }
+}
+
+/// Tasks already due are being worked on by somebody else (we just found
+/// nothing to dequeue): only those waiting for their `execute_after` matter.
+#[tracing::instrument(skip_all, err)]
+async fn time_until_next_task_is_due(pool: &PgPool) -> Result<Option<Duration>, sqlx::Error> {
+    let next_due = sqlx::query!(
+        r#"
+        SELECT EXTRACT(EPOCH FROM LEAST(
+            (SELECT MIN(execute_after) FROM issue_delivery_queue WHERE execute_after > now()),
+            (
+                SELECT MIN(execute_after) FROM email_outbox
+                WHERE failed_at IS NULL AND execute_after > now()
+            )
+        ) - now())::float8 AS "seconds"
+        "#
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(next_due
+        .seconds
+        .map(|seconds| Duration::from_secs_f64(seconds.max(0.0))))
+}
+
+/// A single connection per process `LISTEN`s for new tasks and wakes up the
+/// idle delivery tasks.
+///
+/// The listener is reconnected when the connection drops: in the meantime
+/// tasks fall back to polling, and they are all woken up once we are back
+/// in case they missed a notification.
+async fn forward_new_task_notifications(worker: Arc<Worker>, shutdown: Shutdown) {
+    let mut listener: Option<PgListener> = None;
+    while !shutdown.is_triggered() {
+        let l = match listener.as_mut() {
+            Some(l) => l,
+            None => match listen_for_new_tasks(&worker.pool).await {
+                Ok(l) => {
+                    worker.new_tasks.notify_waiters();
+                    listener.insert(l)
+                }
+                Err(_) => {
+                    tokio::select! {
+                        _ = tokio::time::sleep(Duration::from_secs(1)) => {},
+                        _ = shutdown.triggered() => {},
+                    }
+                    continue;
+                }
+            },
+        };
+        tokio::select! {
+            notification = l.recv() => match notification {
+                Ok(_) => worker.new_tasks.notify_waiters(),
+                Err(e) => {
+                    tracing::warn!(
+                        error.cause_chain = ?e,
+                        error.message = %e,
+                        "Lost the connection listening for new delivery tasks."
+                    );
+                    listener = None;
+                }
+            },
+            _ = shutdown.triggered() => {},
+        }
+    }
+}
+
+#[tracing::instrument(skip_all, err)]
+async fn listen_for_new_tasks(pool: &PgPool) -> Result<PgListener, sqlx::Error> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen(NEW_TASKS_CHANNEL).await?;
+    Ok(listener)
+}
+
+/// Run `concurrency` delivery tasks side by side: `SKIP LOCKED` makes sure
+/// they never pick up the same delivery.
+///
+/// Once `shutdown` is triggered each task finishes the delivery it is working
+/// on, or rolls it back if that takes longer than the drain timeout.
+pub async fn run_worker_until_stopped(
+    configuration: Settings,
+    shutdown: Shutdown,
+) -> Result<(), anyhow::Error> {
+    let settings = configuration.issue_delivery_worker;
+    let email_client = configuration
         .email_client
-        .sender()
-        .expect("Invalid sender email address");
-    let timeout = configuration.email_client.timeout();
-    let email_client = EmailClient::new(
-        configuration.email_client.base_url,
-        sender_email,
-        configuration.email_client.authorization_token,
-        timeout,
-    );
-    worker_loop(connection_pool, email_client).await
+        .client()
+        .map_err(anyhow::Error::msg)?;
+    // Each delivery task holds a connection while it delivers, and the
+    // listener for new tasks holds one more.
+    let max_connections = u32::try_from(settings.concurrency.max(1) + 1)
+        .map_err(|_| anyhow::anyhow!("The delivery worker concurrency is too high."))?;
+    let pool = startup::connection_pool_options()
+        .max_connections(max_connections)
+        .connect_lazy_with(configuration.database.with_db());
+    let rate_limiter = settings
+        .rate_limiter(pool.clone())
+        .map_err(anyhow::Error::msg)?;
+    let worker = Arc::new(Worker {
+        pool,
+        email_client,
+        base_url: ApplicationBaseUrl(configuration.application.base_url),
+        hmac_secret: HmacSecret(configuration.application.hmac_secret),
+        rate_limiter,
+        settings,
+        new_tasks: Notify::new(),
+    });
+    let listener = tokio::spawn(forward_new_task_notifications(
+        worker.clone(),
+        shutdown.clone(),
+    ));
+    let tasks: Vec<_> = (0..worker.settings.concurrency.max(1))
+        .map(|_| tokio::spawn(worker_loop(worker.clone(), shutdown.clone())))
+        .collect();
+    for task in tasks {
+        task.await??;
+    }
+    listener.await?;
+    Ok(())
 }
@@ -2,10 +2,14 @@ pub mod authentication;
 pub mod configuration;
 pub mod domain;
 pub mod email_client;
+pub mod email_outbox;
 pub mod idempotency;
 pub mod issue_delivery_worker;
+pub mod rate_limiter;
 pub mod routes;
 pub mod session_state;
+pub mod shutdown;
 pub mod startup;
+pub mod suppression;
 pub mod telemetry;
 pub mod utils;
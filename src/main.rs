@@ -1,7 +1,8 @@
 use std::fmt::{Debug, Display};
-use tokio::task::JoinError;
+use tokio::task::{JoinError, JoinHandle};
 use ZeroToProd::configuration::get_configuration;
 use ZeroToProd::issue_delivery_worker;
+use ZeroToProd::shutdown::{wait_for_signal, Shutdown};
 use ZeroToProd::startup::Application;
 use ZeroToProd::telemetry::{get_subscriber, init_subscriber};
 
@@ -12,18 +13,37 @@ async fn main() -> anyhow::Result<()> {
     init_subscriber(subscriber);
     let configuration = get_configuration().expect("Failed to read configuration.");
     let application = Application::build(configuration.clone()).await?;
-    let application_task = tokio::spawn(application.run_until_stopped());
+    let shutdown = Shutdown::new();
+    let application_task = tokio::spawn(application.run_until_stopped(shutdown.clone()));
     let worker_task = tokio::spawn(issue_delivery_worker::run_worker_until_stopped(
         configuration,
+        shutdown.clone(),
     ));
+    tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move {
+            wait_for_signal().await;
+            tracing::info!("Received a termination signal, shutting down.");
+            shutdown.trigger();
+        }
+    });
 
-    tokio::select! {
-        o = application_task => report_exit("API", o),
-        o = worker_task => report_exit("Background worker", o)
-    };
+    // Whichever task exits first, for whatever reason, takes the other one down with it.
+    let (application_outcome, worker_outcome) = tokio::join!(
+        stop_all_on_exit(application_task, &shutdown),
+        stop_all_on_exit(worker_task, &shutdown)
+    );
+    report_exit("API", application_outcome);
+    report_exit("Background worker", worker_outcome);
     Ok(())
 }
 
+async fn stop_all_on_exit<T>(task: JoinHandle<T>, shutdown: &Shutdown) -> Result<T, JoinError> {
+    let outcome = task.await;
+    shutdown.trigger();
+    outcome
+}
+
 fn report_exit(task_name: &str, outcome: Result<Result<(), impl Debug + Display>, JoinError>) {
     match outcome {
         Ok(Ok(())) => {
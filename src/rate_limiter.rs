@@ -0,0 +1,71 @@
+use sqlx::PgPool;
+use std::time::Duration;
+
+/// A token bucket, refilled at a steady `rate` per second and holding up to
+/// `capacity` tokens.
+///
+/// The balance lives in Postgres, so that all the worker processes draw from
+/// the same bucket. Waiters reserve their token up front, driving the balance
+/// negative, and then sleep until it would have been refilled: they are served
+/// in order and nobody holds a lock while sleeping.
+pub struct TokenBucket {
+    pool: PgPool,
+    rate: f64,
+    capacity: f64,
+}
+
+impl TokenBucket {
+    /// `rate` is in tokens per second. The bucket starts full.
+    pub fn new(pool: PgPool, rate: u32, capacity: u32) -> Result<Self, String> {
+        if rate == 0 {
+            return Err("The rate of a token bucket must be positive.".into());
+        }
+        Ok(Self {
+            pool,
+            rate: f64::from(rate),
+            capacity: f64::from(capacity.max(1)),
+        })
+    }
+
+    /// Wait until a token is available and take it.
+    pub async fn acquire(&self) -> Result<(), sqlx::Error> {
+        // The upsert locks the row: concurrent callers take their token one
+        // after the other.
+        let tokens = sqlx::query!(
+            r#"
+            INSERT INTO email_rate_limit (id, tokens, last_refill)
+            VALUES (TRUE, $2::float8 - 1, clock_timestamp())
+            ON CONFLICT (id) DO UPDATE SET
+                tokens = LEAST(
+                    $2::float8,
+                    email_rate_limit.tokens + $1::float8 * GREATEST(
+                        EXTRACT(EPOCH FROM EXCLUDED.last_refill - email_rate_limit.last_refill),
+                        0
+                    )::float8
+                ) - 1,
+                last_refill = GREATEST(EXCLUDED.last_refill, email_rate_limit.last_refill)
+            RETURNING tokens
+            "#,
+            self.rate,
+            self.capacity,
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .tokens;
+        if tokens < 0.0 {
+            tokio::time::sleep(Duration::from_secs_f64(-tokens / self.rate)).await;
+        }
+        Ok(())
+    }
+
+    /// Give back a token that was acquired but ended up unused.
+    pub async fn release(&self) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "UPDATE email_rate_limit SET tokens = LEAST(tokens + 1, $1::float8)",
+            self.capacity
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
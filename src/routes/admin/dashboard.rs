@@ -1,3 +1,4 @@
+use crate::domain::{Permission, UserRole};
 use crate::session_state::TypedSession;
 use crate::utils;
 use actix_web::http::header::{ContentType, LOCATION};
@@ -9,6 +10,7 @@ use uuid::Uuid;
 pub async fn admin_dashboard(
     session: TypedSession,
     pool: web::Data<PgPool>,
+    role: web::ReqData<UserRole>,
 ) -> Result<HttpResponse, actix_web::Error> {
     let username = if let Some(user_id) = session.get_user_id().map_err(utils::e500)? {
         get_username(user_id, &pool).await.map_err(utils::e500)?
@@ -17,6 +19,11 @@ pub async fn admin_dashboard(
             .insert_header((LOCATION, "/login"))
             .finish());
     };
+    let users_link = if role.can(Permission::ManageUsers) {
+        r#"<li><a href="/admin/users">Users</a></li>"#
+    } else {
+        ""
+    };
     Ok(HttpResponse::Ok()
         .content_type(ContentType::html())
         .body(format!(
@@ -28,10 +35,14 @@ pub async fn admin_dashboard(
         </head>
         <body>
             <p>Welcome {username}!</p>
+            <p>Your role: {role}</p>
             <p>Available actions:</p>
             <ol>
                 <li><a href="/admin/password">Change password</a></li>
-                <li><a href="/admin/newsletters">Send a newsletter</a></li>
+                <li><a href="/admin/two_factor">Two-factor authentication</a></li>
+                <li><a href="/admin/newsletters">Newsletter issues</a></li>
+                <li><a href="/admin/suppressions">Suppression list</a></li>
+                {users_link}
                 <li>
                     <form name="logoutForm" action="/admin/logout" method="post">
                         <input type="submit" value="Logout">
@@ -39,7 +50,8 @@ pub async fn admin_dashboard(
                 </li>
             </ol>
         </body>
-        </html>"#
+        </html>"#,
+            role = role.into_inner(),
         )))
 }
 
@@ -2,8 +2,14 @@ mod dashboard;
 mod logout;
 mod newsletter;
 mod password;
+mod suppressions;
+mod two_factor;
+mod users;
 
 pub use dashboard::*;
 pub use logout::*;
 pub use newsletter::*;
 pub use password::*;
+pub use suppressions::*;
+pub use two_factor::*;
+pub use users::*;
@@ -0,0 +1,181 @@
+use super::issue::get_newsletter_issue;
+use crate::authentication::require_permission;
+use crate::domain::{DeliveryStatus, Permission, UserRole};
+use crate::utils;
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use htmlescape::encode_minimal;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::fmt::Write;
+use uuid::Uuid;
+
+/// The window used to estimate the current delivery rate.
+const THROUGHPUT_WINDOW_MINUTES: i64 = 5;
+
+/// Totals, progress and failure reasons for the delivery of an issue.
+pub async fn newsletter_issue_deliveries(
+    newsletter_issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    role: web::ReqData<UserRole>,
+) -> Result<HttpResponse, actix_web::Error> {
+    require_permission(&role, Permission::ViewIssues)?;
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+    let issue = match get_newsletter_issue(&pool, newsletter_issue_id)
+        .await
+        .map_err(utils::e500)?
+    {
+        Some(issue) => issue,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+    let totals = get_delivery_totals(&pool, newsletter_issue_id)
+        .await
+        .map_err(utils::e500)?;
+    let total: i64 = totals.values().sum();
+    let queued = totals.get(&DeliveryStatus::Queued).copied().unwrap_or(0);
+    let progress = if total == 0 {
+        100
+    } else {
+        (total - queued) * 100 / total
+    };
+    let recently_processed = get_recently_processed(&pool, newsletter_issue_id)
+        .await
+        .map_err(utils::e500)?;
+    let eta = if queued == 0 {
+        "-".to_string()
+    } else if recently_processed == 0 {
+        "unknown".to_string()
+    } else {
+        let minutes = queued * THROUGHPUT_WINDOW_MINUTES / recently_processed;
+        format!("about {} minute(s)", minutes.max(1))
+    };
+
+    let mut totals_html = String::new();
+    for status in DeliveryStatus::ALL {
+        writeln!(
+            totals_html,
+            "<tr><td>{}</td><td>{}</td></tr>",
+            status,
+            totals.get(&status).copied().unwrap_or(0)
+        )
+        .unwrap();
+    }
+    let mut failures_html = String::new();
+    for (reason, count) in get_failure_reasons(&pool, newsletter_issue_id)
+        .await
+        .map_err(utils::e500)?
+    {
+        writeln!(
+            failures_html,
+            "<tr><td>{}</td><td>{}</td></tr>",
+            encode_minimal(&reason),
+            count
+        )
+        .unwrap();
+    }
+    let title = encode_minimal(&issue.title);
+    let status = issue.status;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equev="content-type" content="text/html"; charset="utf-8">
+    <title>Deliveries: {title}</title>
+</head>
+    <body>
+        <h1>{title}</h1>
+        <p>Status: {status}</p>
+        <p>Progress: {progress}% of {total} recipients processed.</p>
+        <p>Estimated time left: {eta}</p>
+        <table>
+            <tr><th>Delivery status</th><th>Recipients</th></tr>
+            {totals_html}
+        </table>
+        <h2>Failure reasons</h2>
+        <table>
+            <tr><th>Reason</th><th>Recipients</th></tr>
+            {failures_html}
+        </table>
+            <br>
+            <p><a href="/admin/newsletters">&lt;-Back</a></p>
+    </body>
+</html>"#,
+        )))
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_delivery_totals(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+) -> Result<HashMap<DeliveryStatus, i64>, anyhow::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT status, COUNT(*) as "n!"
+        FROM issue_delivery_log
+        WHERE newsletter_issue_id = $1
+        GROUP BY status
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to count the deliveries of a newsletter issue.")?;
+    rows.into_iter()
+        .map(|r| {
+            let status = r.status.try_into().map_err(anyhow::Error::msg)?;
+            Ok((status, r.n))
+        })
+        .collect()
+}
+
+/// How many recipients were processed over the last `THROUGHPUT_WINDOW_MINUTES`.
+#[tracing::instrument(skip(pool))]
+async fn get_recently_processed(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+) -> Result<i64, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT COUNT(*) as "n!"
+        FROM issue_delivery_log
+        WHERE
+            newsletter_issue_id = $1 AND
+            status <> 'queued' AND
+            updated_at > now() - make_interval(mins => $2)
+        "#,
+        newsletter_issue_id,
+        THROUGHPUT_WINDOW_MINUTES as i32
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to measure the delivery throughput of a newsletter issue.")?;
+    Ok(row.n)
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_failure_reasons(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+) -> Result<Vec<(String, i64)>, anyhow::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT last_error as "last_error!", COUNT(*) as "n!"
+        FROM issue_delivery_log
+        WHERE
+            newsletter_issue_id = $1 AND
+            status IN ('failed', 'skipped_invalid_email') AND
+            last_error IS NOT NULL
+        GROUP BY last_error
+        ORDER BY COUNT(*) DESC
+        LIMIT 20
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the delivery failures of a newsletter issue.")?;
+    Ok(rows.into_iter().map(|r| (r.last_error, r.n)).collect())
+}
@@ -0,0 +1,189 @@
+use super::issue::{get_newsletter_issue, validate_content};
+use crate::authentication::require_permission;
+use crate::domain::{Permission, UserRole};
+use crate::utils;
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use htmlescape::{encode_attribute, encode_minimal};
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+pub async fn edit_newsletter_issue_form(
+    newsletter_issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+    role: web::ReqData<UserRole>,
+) -> Result<HttpResponse, actix_web::Error> {
+    require_permission(&role, Permission::EditIssues)?;
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+    let issue = match get_newsletter_issue(&pool, newsletter_issue_id)
+        .await
+        .map_err(utils::e500)?
+    {
+        Some(issue) => issue,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+    if !issue.status.is_editable() {
+        FlashMessage::error("This issue is already being sent and can no longer be edited.").send();
+        return Ok(utils::see_other("/admin/newsletters"));
+    }
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", encode_minimal(m.content())).unwrap();
+    }
+    let title = encode_attribute(&issue.title);
+    let text_content = encode_minimal(&issue.text_content);
+    let html_content = encode_minimal(&issue.html_content);
+    let schedule_html = match issue.scheduled_for {
+        Some(scheduled_for) => format!(
+            r#"<p>Scheduled to go out on {} UTC.</p>
+        <form action="/admin/newsletters/{newsletter_issue_id}/unschedule" method="post">
+            <button type="submit">Cancel schedule</button>
+        </form>"#,
+            scheduled_for.format("%Y-%m-%d %H:%M")
+        ),
+        None => String::new(),
+    };
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equev="content-type" content="text/html"; charset="utf-8">
+    <title>Edit newsletter issue</title>
+</head>
+    <body>
+        {msg_html}
+        <p>Personalise the content with <code>{{{{ subscriber.name }}}}</code>,
+        <code>{{{{ unsubscribe_url }}}}</code> and <code>{{{{ issue.archive_url }}}}</code>.</p>
+        <form action="/admin/newsletters/{newsletter_issue_id}/edit" method="post">
+            <label>Title
+                <input
+                    type="text"
+                    placeholder="Enter title"
+                    name="title"
+                    value="{title}"
+                >
+            </label>
+            <br>
+            <label>Text content
+                <textarea
+                    placeholder="Enter content"
+                    name="text_content"
+                >{text_content}</textarea>
+            </label>
+            <br>
+            <label>Html content
+                <textarea
+                    placeholder="Enter content"
+                    name="html_content"
+                >{html_content}</textarea>
+            </label>
+            <br>
+            <button type="submit">Save draft</button>
+        </form>
+        <p><a href="/admin/newsletters/{newsletter_issue_id}/preview">Preview</a></p>
+        <form action="/admin/newsletters/{newsletter_issue_id}/send_test" method="post">
+            <label>Send a test copy to
+                <input
+                    type="text"
+                    placeholder="you@example.com, reviewer@example.com"
+                    name="recipients"
+                >
+            </label>
+            <button type="submit">Send test</button>
+        </form>
+        <form action="/admin/newsletters/{newsletter_issue_id}/publish" method="post">
+            <button type="submit">Publish now</button>
+        </form>
+        {schedule_html}
+        <form action="/admin/newsletters/{newsletter_issue_id}/schedule" method="post">
+            <label>Send at
+                <input type="datetime-local" name="send_at">
+            </label>
+            <label>Timezone
+                <input
+                    type="text"
+                    placeholder="e.g. Europe/Rome"
+                    name="timezone"
+                    value="UTC"
+                >
+            </label>
+            <button type="submit">Schedule</button>
+        </form>
+            <br>
+            <p><a href="/admin/newsletters">&lt;-Back</a></p>
+    </body>
+</html>"#,
+        )))
+}
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    title: String,
+    text_content: String,
+    html_content: String,
+}
+
+#[tracing::instrument(name = "Edit a newsletter issue draft", skip(form, pool))]
+pub async fn edit_newsletter_issue(
+    newsletter_issue_id: web::Path<Uuid>,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    role: web::ReqData<UserRole>,
+) -> Result<HttpResponse, actix_web::Error> {
+    require_permission(&role, Permission::EditIssues)?;
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+    if let Err(e) = validate_content(&form.text_content, &form.html_content) {
+        FlashMessage::error(e).send();
+        return Ok(utils::see_other(&format!(
+            "/admin/newsletters/{}/edit",
+            newsletter_issue_id
+        )));
+    }
+    let is_updated = update_draft(&pool, newsletter_issue_id, &form.0)
+        .await
+        .context("Failed to update the newsletter issue draft")
+        .map_err(utils::e500)?;
+    if !is_updated {
+        FlashMessage::error("This issue is already being sent and can no longer be edited.").send();
+        return Ok(utils::see_other("/admin/newsletters"));
+    }
+    FlashMessage::info("The newsletter issue draft has been saved.").send();
+    Ok(utils::see_other(&format!(
+        "/admin/newsletters/{}/edit",
+        newsletter_issue_id
+    )))
+}
+
+/// Returns `false` if the issue does not exist or is already being sent.
+#[tracing::instrument(skip_all)]
+async fn update_draft(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+    form: &FormData,
+) -> Result<bool, sqlx::Error> {
+    let n_updated_rows = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET
+            title = $2,
+            text_content = $3,
+            html_content = $4,
+            updated_at = now()
+        WHERE newsletter_issue_id = $1 AND status IN ('draft', 'scheduled')
+        "#,
+        newsletter_issue_id,
+        form.title,
+        form.text_content,
+        form.html_content
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(n_updated_rows > 0)
+}
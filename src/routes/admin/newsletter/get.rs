@@ -1,20 +1,25 @@
+use crate::authentication::require_permission;
+use crate::domain::{Permission, UserRole};
 use crate::session_state::TypedSession;
 use crate::utils;
-use actix_web::HttpResponse;
 use actix_web::{self, http::header::ContentType};
+use actix_web::{web, HttpResponse};
 use actix_web_flash_messages::IncomingFlashMessages;
+use htmlescape::encode_minimal;
 use std::fmt::Write;
 
-pub async fn send_newsletters_form(
+pub async fn new_newsletter_issue_form(
     session: TypedSession,
     flash_messages: IncomingFlashMessages,
+    role: web::ReqData<UserRole>,
 ) -> Result<HttpResponse, actix_web::Error> {
+    require_permission(&role, Permission::EditIssues)?;
     if session.get_user_id().map_err(utils::e500)?.is_none() {
         return Ok(utils::see_other("/login"));
     };
     let mut msg_html = String::new();
     for m in flash_messages.iter() {
-        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+        writeln!(msg_html, "<p><i>{}</i></p>", encode_minimal(m.content())).unwrap();
     }
     let idempotency_key = uuid::Uuid::new_v4();
     Ok(HttpResponse::Ok()
@@ -24,40 +29,40 @@ pub async fn send_newsletters_form(
 <html lang="en">
 <head>
     <meta http-equev="content-type" content="text/html"; charset="utf-8">
-    <title>Send a newsletter</title>
+    <title>New newsletter issue</title>
 </head>
     <body>
         {msg_html}
+        <p>Personalise the content with <code>{{{{ subscriber.name }}}}</code>,
+        <code>{{{{ unsubscribe_url }}}}</code> and <code>{{{{ issue.archive_url }}}}</code>.</p>
         <form action="/admin/newsletters" method="post">
             <label>Title
                 <input
-                    type="test"
+                    type="text"
                     placeholder="Enter title"
                     name="title"
                 >
             </label>
             <br>
             <label>Text content
-                <input
-                    type="text"
+                <textarea
                     placeholder="Enter content"
                     name="text_content"
-                >
+                ></textarea>
             </label>
             <br>
             <label>Html content
-                <input
-                    type="text"
+                <textarea
                     placeholder="Enter content"
                     name="html_content"
-                >
+                ></textarea>
             </label>
             <br>
             <input hidden type="text" name="idempotency_key" value="{idempotency_key}">
-            <button type="submit">Send</button>
+            <button type="submit">Save draft</button>
         </form>
             <br>
-            <p><a href="/admin/dashboard">&lt;-Back</a></p>
+            <p><a href="/admin/newsletters">&lt;-Back</a></p>
     </body>
 </html>"#,
         )))
@@ -0,0 +1,52 @@
+use crate::domain::{IssueTemplate, NewsletterIssueStatus};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub struct NewsletterIssue {
+    pub title: String,
+    pub slug: Option<String>,
+    pub text_content: String,
+    pub html_content: String,
+    pub status: NewsletterIssueStatus,
+    pub scheduled_for: Option<DateTime<Utc>>,
+    pub published_at: Option<DateTime<Utc>>,
+}
+
+#[tracing::instrument(name = "Get newsletter issue", skip(pool))]
+pub async fn get_newsletter_issue(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+) -> Result<Option<NewsletterIssue>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT title, slug, text_content, html_content, status, scheduled_for, published_at
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to perform a query to retrieve a newsletter issue.")?;
+    row.map(|r| {
+        Ok(NewsletterIssue {
+            title: r.title,
+            slug: r.slug,
+            text_content: r.text_content,
+            html_content: r.html_content,
+            status: r.status.try_into().map_err(anyhow::Error::msg)?,
+            scheduled_for: r.scheduled_for,
+            published_at: r.published_at,
+        })
+    })
+    .transpose()
+}
+
+/// Check that both versions of the content only use known template variables.
+pub fn validate_content(text_content: &str, html_content: &str) -> Result<(), String> {
+    IssueTemplate::parse(text_content).map_err(|e| format!("Text content: {}", e))?;
+    IssueTemplate::parse(html_content).map_err(|e| format!("Html content: {}", e))?;
+    Ok(())
+}
@@ -0,0 +1,132 @@
+use crate::authentication::require_permission;
+use crate::domain::{NewsletterIssueStatus, Permission, UserRole};
+use crate::utils;
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use htmlescape::encode_minimal;
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+pub async fn list_newsletter_issues(
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+    role: web::ReqData<UserRole>,
+) -> Result<HttpResponse, actix_web::Error> {
+    require_permission(&role, Permission::ViewIssues)?;
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", encode_minimal(m.content())).unwrap();
+    }
+    let issues = get_newsletter_issues(&pool).await.map_err(utils::e500)?;
+    let mut rows_html = String::new();
+    for issue in issues {
+        let newsletter_issue_id = issue.newsletter_issue_id;
+        let actions = if issue.status.is_editable() {
+            format!(
+                r#"<a href="/admin/newsletters/{newsletter_issue_id}/edit">Edit</a>
+                <a href="/admin/newsletters/{newsletter_issue_id}/preview">Preview</a>
+                <form action="/admin/newsletters/{newsletter_issue_id}/publish" method="post">
+                    <button type="submit">Publish now</button>
+                </form>"#
+            )
+        } else {
+            format!(
+                r#"<a href="/admin/newsletters/{newsletter_issue_id}/preview">Preview</a>
+                <a href="/admin/newsletters/{newsletter_issue_id}/deliveries">Deliveries</a>"#
+            )
+        };
+        let (visibility, toggle_label, make_private) = if issue.is_private {
+            ("Private", "Make public", false)
+        } else {
+            ("Public", "Make private", true)
+        };
+        let visibility_html = format!(
+            r#"{visibility}
+                <form action="/admin/newsletters/{newsletter_issue_id}/visibility" method="post">
+                    <input type="hidden" name="is_private" value="{make_private}">
+                    <button type="submit">{toggle_label}</button>
+                </form>"#
+        );
+        writeln!(
+            rows_html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            encode_minimal(&issue.title),
+            match (issue.scheduled_for, issue.published_at) {
+                (Some(scheduled_for), _) => format!(
+                    "{} for {} UTC",
+                    issue.status,
+                    scheduled_for.format("%Y-%m-%d %H:%M")
+                ),
+                (None, Some(published_at)) => format!(
+                    "{}, published on {} UTC",
+                    issue.status,
+                    published_at.format("%Y-%m-%d %H:%M")
+                ),
+                (None, None) => issue.status.to_string(),
+            },
+            visibility_html,
+            actions
+        )
+        .unwrap();
+    }
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equev="content-type" content="text/html"; charset="utf-8">
+    <title>Newsletter issues</title>
+</head>
+    <body>
+        {msg_html}
+        <p><a href="/admin/newsletters/new">Write a new issue</a></p>
+        <table>
+            <tr><th>Title</th><th>Status</th><th>Archive</th><th>Actions</th></tr>
+            {rows_html}
+        </table>
+            <br>
+            <p><a href="/admin/dashboard">&lt;-Back</a></p>
+    </body>
+</html>"#,
+        )))
+}
+
+struct IssueSummary {
+    newsletter_issue_id: Uuid,
+    title: String,
+    status: NewsletterIssueStatus,
+    scheduled_for: Option<DateTime<Utc>>,
+    published_at: Option<DateTime<Utc>>,
+    is_private: bool,
+}
+
+#[tracing::instrument(name = "Get newsletter issues", skip(pool))]
+async fn get_newsletter_issues(pool: &PgPool) -> Result<Vec<IssueSummary>, anyhow::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT newsletter_issue_id, title, status, scheduled_for, published_at, is_private
+        FROM newsletter_issues
+        ORDER BY created_at DESC
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to perform a query to retrieve newsletter issues.")?;
+    rows.into_iter()
+        .map(|r| {
+            Ok(IssueSummary {
+                newsletter_issue_id: r.newsletter_issue_id,
+                title: r.title,
+                status: r.status.try_into().map_err(anyhow::Error::msg)?,
+                scheduled_for: r.scheduled_for,
+                published_at: r.published_at,
+                is_private: r.is_private,
+            })
+        })
+        .collect()
+}
@@ -1,5 +1,22 @@
+mod deliveries;
+mod edit;
 mod get;
+mod issue;
+mod list;
 mod post;
+mod preview;
+mod publish;
+mod schedule;
+mod send_test;
+mod visibility;
 
+pub use deliveries::*;
+pub use edit::*;
 pub use get::*;
+pub use list::*;
 pub use post::*;
+pub use preview::*;
+pub use publish::*;
+pub use schedule::*;
+pub use send_test::*;
+pub use visibility::*;
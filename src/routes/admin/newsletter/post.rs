@@ -1,4 +1,6 @@
-use crate::authentication::UserId;
+use super::issue::validate_content;
+use crate::authentication::{require_permission, UserId};
+use crate::domain::{Permission, UserRole};
 use crate::idempotency::{self, IdempotencyKey, NextAction};
 use crate::utils;
 use actix_web::{web, HttpResponse};
@@ -16,15 +18,17 @@ pub struct FormData {
 }
 
 #[tracing::instrument(
-name = "Publish a newsletter issue",
+name = "Create a newsletter issue draft",
 skip_all,
 fields(user_id=%&*user_id)
 )]
-pub async fn publish_newsletter(
+pub async fn create_newsletter_issue(
     form: web::Form<FormData>,
     pool: web::Data<PgPool>,
     user_id: web::ReqData<UserId>,
+    role: web::ReqData<UserRole>,
 ) -> Result<HttpResponse, actix_web::Error> {
+    require_permission(&role, Permission::EditIssues)?;
     let user_id = user_id.into_inner();
     tracing::Span::current().record("user_id", &tracing::field::display(*user_id));
 
@@ -35,6 +39,10 @@ pub async fn publish_newsletter(
         idempotency_key,
     } = form.0;
     let idempotency_key: IdempotencyKey = idempotency_key.try_into().map_err(utils::e400)?;
+    if let Err(e) = validate_content(&text_content, &html_content) {
+        FlashMessage::error(e).send();
+        return Ok(utils::see_other("/admin/newsletters/new"));
+    }
     let mut transaction = match idempotency::try_processing(&pool, &idempotency_key, *user_id)
         .await
         .map_err(utils::e500)?
@@ -45,14 +53,10 @@ pub async fn publish_newsletter(
             return Ok(saved_response);
         }
     };
-    let issue_id = insert_newsletter_issue(&mut transaction, &title, &text_content, &html_content)
+    insert_newsletter_issue(&mut transaction, &title, &text_content, &html_content)
         .await
         .context("Failed to store newsletter issue details")
         .map_err(utils::e500)?;
-    enqueue_delivery_tasks(&mut transaction, issue_id)
-        .await
-        .context("Failed to enqueue delivery tasks")
-        .map_err(utils::e500)?;
     success_message().send();
     let response = utils::see_other("/admin/newsletters");
     let response = idempotency::save_response(transaction, &idempotency_key, *user_id, response)
@@ -62,10 +66,7 @@ pub async fn publish_newsletter(
 }
 
 fn success_message() -> FlashMessage {
-    FlashMessage::info(
-        "The newsletter issue has been accepted - \
-        emails will go out shortly.",
-    )
+    FlashMessage::info("The newsletter issue draft has been saved.")
 }
 
 #[tracing::instrument(skip_all)]
@@ -83,9 +84,11 @@ async fn insert_newsletter_issue(
             title,
             text_content,
             html_content,
-            published_at
+            status,
+            created_at,
+            updated_at
         )
-        VALUES ($1, $2, $3, $4, now())
+        VALUES ($1, $2, $3, $4, 'draft', now(), now())
         "#,
         newsletter_issue_id,
         title,
@@ -96,25 +99,3 @@ async fn insert_newsletter_issue(
     .await?;
     Ok(newsletter_issue_id)
 }
-
-#[tracing::instrument(skip_all)]
-async fn enqueue_delivery_tasks(
-    transaction: &mut Transaction<'_, Postgres>,
-    newsletter_issue_id: Uuid,
-) -> Result<(), sqlx::Error> {
-    sqlx::query!(
-        r#"
-        INSERT INTO issue_delivery_queue (
-            newsletter_issue_id,
-            subscriber_email
-        )
-        SELECT $1, email
-        FROM subscriptions
-        WHERE status = 'confirmed'
-        "#,
-        newsletter_issue_id,
-    )
-    .execute(transaction)
-    .await?;
-    Ok(())
-}
@@ -0,0 +1,49 @@
+use super::issue::get_newsletter_issue;
+use crate::authentication::require_permission;
+use crate::domain::{Permission, UserRole};
+use crate::utils;
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use htmlescape::encode_minimal;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub async fn preview_newsletter_issue(
+    newsletter_issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    role: web::ReqData<UserRole>,
+) -> Result<HttpResponse, actix_web::Error> {
+    require_permission(&role, Permission::ViewIssues)?;
+    let issue = match get_newsletter_issue(&pool, newsletter_issue_id.into_inner())
+        .await
+        .map_err(utils::e500)?
+    {
+        Some(issue) => issue,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+    let title = encode_minimal(&issue.title);
+    let text_content = encode_minimal(&issue.text_content);
+    let html_content = issue.html_content;
+    let status = issue.status;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equev="content-type" content="text/html"; charset="utf-8">
+    <title>Preview: {title}</title>
+</head>
+    <body>
+        <p>Status: {status}</p>
+        <h1>{title}</h1>
+        <h2>HTML version</h2>
+        <div>{html_content}</div>
+        <h2>Text version</h2>
+        <pre>{text_content}</pre>
+            <br>
+            <p><a href="/admin/newsletters">&lt;-Back</a></p>
+    </body>
+</html>"#,
+        )))
+}
@@ -0,0 +1,81 @@
+use crate::authentication::{require_permission, UserId};
+use crate::domain::{Permission, UserRole};
+use crate::issue_delivery_worker::{assign_slug, enqueue_delivery_tasks};
+use crate::utils;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+#[tracing::instrument(
+    name = "Publish a newsletter issue",
+    skip(pool, user_id),
+    fields(user_id=%&*user_id)
+)]
+pub async fn publish_newsletter(
+    newsletter_issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    role: web::ReqData<UserRole>,
+) -> Result<HttpResponse, actix_web::Error> {
+    require_permission(&role, Permission::PublishIssues)?;
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")
+        .map_err(utils::e500)?;
+    // Moving the issue to `sending` is what guards against double sends:
+    // only one request (or the worker, for scheduled issues) can win the transition.
+    let is_published = mark_issue_as_sending(&mut transaction, newsletter_issue_id)
+        .await
+        .context("Failed to update the newsletter issue status")
+        .map_err(utils::e500)?;
+    if !is_published {
+        FlashMessage::error("Only draft or scheduled issues can be published.").send();
+        return Ok(utils::see_other("/admin/newsletters"));
+    }
+    assign_slug(&mut transaction, newsletter_issue_id)
+        .await
+        .context("Failed to assign a slug to the newsletter issue")
+        .map_err(utils::e500)?;
+    enqueue_delivery_tasks(&mut transaction, newsletter_issue_id)
+        .await
+        .context("Failed to enqueue delivery tasks")
+        .map_err(utils::e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to publish a newsletter issue.")
+        .map_err(utils::e500)?;
+    FlashMessage::info(
+        "The newsletter issue has been published - \
+        emails will go out shortly.",
+    )
+    .send();
+    Ok(utils::see_other("/admin/newsletters"))
+}
+
+#[tracing::instrument(skip_all)]
+async fn mark_issue_as_sending(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let n_updated_rows = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET
+            status = 'sending',
+            published_at = now(),
+            scheduled_for = NULL,
+            updated_at = now()
+        WHERE newsletter_issue_id = $1 AND status IN ('draft', 'scheduled')
+        "#,
+        newsletter_issue_id,
+    )
+    .execute(transaction)
+    .await?
+    .rows_affected();
+    Ok(n_updated_rows > 0)
+}
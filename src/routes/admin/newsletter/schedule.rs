@@ -0,0 +1,113 @@
+use crate::authentication::require_permission;
+use crate::domain::{Permission, ScheduledSendTime, UserRole};
+use crate::utils;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use chrono::Utc;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    send_at: String,
+    timezone: String,
+}
+
+#[tracing::instrument(name = "Schedule a newsletter issue", skip(form, pool))]
+pub async fn schedule_newsletter(
+    newsletter_issue_id: web::Path<Uuid>,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    role: web::ReqData<UserRole>,
+) -> Result<HttpResponse, actix_web::Error> {
+    require_permission(&role, Permission::PublishIssues)?;
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+    let edit_page = format!("/admin/newsletters/{}/edit", newsletter_issue_id);
+    let send_time = match ScheduledSendTime::parse(&form.send_at, &form.timezone) {
+        Ok(send_time) => send_time,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(utils::see_other(&edit_page));
+        }
+    };
+    if send_time.as_utc() <= Utc::now() {
+        FlashMessage::error("The send time must be in the future.").send();
+        return Ok(utils::see_other(&edit_page));
+    }
+    let is_scheduled = mark_issue_as_scheduled(&pool, newsletter_issue_id, send_time)
+        .await
+        .context("Failed to schedule the newsletter issue")
+        .map_err(utils::e500)?;
+    if !is_scheduled {
+        FlashMessage::error("This issue is already being sent and can no longer be scheduled.")
+            .send();
+        return Ok(utils::see_other("/admin/newsletters"));
+    }
+    FlashMessage::info(format!(
+        "The newsletter issue will go out on {} UTC.",
+        send_time.as_utc().format("%Y-%m-%d %H:%M")
+    ))
+    .send();
+    Ok(utils::see_other("/admin/newsletters"))
+}
+
+#[tracing::instrument(name = "Cancel a scheduled newsletter issue", skip(pool))]
+pub async fn unschedule_newsletter(
+    newsletter_issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    role: web::ReqData<UserRole>,
+) -> Result<HttpResponse, actix_web::Error> {
+    require_permission(&role, Permission::PublishIssues)?;
+    let is_unscheduled = mark_issue_as_draft(&pool, newsletter_issue_id.into_inner())
+        .await
+        .context("Failed to cancel the newsletter issue schedule")
+        .map_err(utils::e500)?;
+    if is_unscheduled {
+        FlashMessage::info("The schedule has been cancelled - the issue is a draft again.").send();
+    } else {
+        FlashMessage::error("Only scheduled issues can be cancelled.").send();
+    }
+    Ok(utils::see_other("/admin/newsletters"))
+}
+
+/// Rescheduling an issue that is already scheduled just moves its send time.
+#[tracing::instrument(skip_all)]
+async fn mark_issue_as_scheduled(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+    send_time: ScheduledSendTime,
+) -> Result<bool, sqlx::Error> {
+    let n_updated_rows = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET status = 'scheduled', scheduled_for = $2, updated_at = now()
+        WHERE newsletter_issue_id = $1 AND status IN ('draft', 'scheduled')
+        "#,
+        newsletter_issue_id,
+        send_time.as_utc()
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(n_updated_rows > 0)
+}
+
+#[tracing::instrument(skip_all)]
+async fn mark_issue_as_draft(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let n_updated_rows = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET status = 'draft', scheduled_for = NULL, updated_at = now()
+        WHERE newsletter_issue_id = $1 AND status = 'scheduled'
+        "#,
+        newsletter_issue_id
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(n_updated_rows > 0)
+}
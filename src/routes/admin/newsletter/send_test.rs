@@ -0,0 +1,142 @@
+use super::issue::get_newsletter_issue;
+use crate::authentication::require_permission;
+use crate::domain::{IssueSlug, Permission, SubscriberEmail, TemplateContext, UserRole};
+use crate::email_client::EmailClient;
+use crate::issue_delivery_worker::{
+    self, get_subscriber, issue_archive_url, send_issue, Subscriber,
+};
+use crate::routes::unsubscribe_link;
+use crate::startup::{ApplicationBaseUrl, HmacSecret};
+use crate::suppression::is_suppressed;
+use crate::utils;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Test copies are meant for a handful of reviewers, not for a mailing list.
+const MAX_TEST_RECIPIENTS: usize = 10;
+
+#[derive(serde::Deserialize)]
+pub struct SendTestFormData {
+    recipients: String,
+}
+
+/// Send a single copy of an issue to each of the given addresses.
+///
+/// The email is rendered exactly as subscribers will see it, but it goes
+/// straight to the email client: nothing is enqueued and the issue status
+/// is left untouched.
+#[tracing::instrument(
+    name = "Send a test copy of a newsletter issue",
+    skip(form, pool, email_client, base_url, hmac_secret)
+)]
+pub async fn send_test_newsletter(
+    newsletter_issue_id: web::Path<Uuid>,
+    form: web::Form<SendTestFormData>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    hmac_secret: web::Data<HmacSecret>,
+    role: web::ReqData<UserRole>,
+) -> Result<HttpResponse, actix_web::Error> {
+    require_permission(&role, Permission::EditIssues)?;
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+    let edit_page = format!("/admin/newsletters/{}/edit", newsletter_issue_id);
+    let recipients = match parse_recipients(&form.recipients) {
+        Ok(recipients) => recipients,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(utils::see_other(&edit_page));
+        }
+    };
+    for recipient in &recipients {
+        if is_suppressed(pool.get_ref(), recipient.as_ref())
+            .await
+            .context("Failed to check the suppression list.")
+            .map_err(utils::e500)?
+        {
+            FlashMessage::error(format!(
+                "{} is on the suppression list: no test copy has been sent.",
+                recipient
+            ))
+            .send();
+            return Ok(utils::see_other(&edit_page));
+        }
+    }
+    let issue = match get_newsletter_issue(&pool, newsletter_issue_id)
+        .await
+        .map_err(utils::e500)?
+    {
+        Some(issue) => issue_delivery_worker::NewsletterIssue {
+            // Drafts link to where the issue will most likely be published.
+            slug: issue
+                .slug
+                .unwrap_or_else(|| IssueSlug::from_title(&issue.title).to_string()),
+            title: issue.title,
+            text_content: issue.text_content,
+            html_content: issue.html_content,
+            published_at: issue.published_at,
+        },
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+    let archive_url = issue_archive_url(&base_url.0, &issue.slug);
+    for recipient in &recipients {
+        // Subscribers get their own name and working unsubscribe link; anybody
+        // else gets their address as a name and a link matching no subscriber.
+        let subscriber = get_subscriber(pool.get_ref(), recipient.as_ref())
+            .await
+            .context("Failed to look up the test recipient.")
+            .map_err(utils::e500)?
+            .unwrap_or_else(|| Subscriber {
+                id: Uuid::nil(),
+                name: recipient.as_ref().to_string(),
+            });
+        let unsubscribe_url = unsubscribe_link(&base_url.0, subscriber.id, &hmac_secret);
+        let context = TemplateContext {
+            subscriber_name: &subscriber.name,
+            unsubscribe_url: &unsubscribe_url,
+            archive_url: &archive_url,
+        };
+        if let Err(e) = send_issue(&email_client, recipient, &issue, &context).await {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to send a test copy of a newsletter issue."
+            );
+            FlashMessage::error(format!(
+                "Failed to send a test copy to {}: {}",
+                recipient, e
+            ))
+            .send();
+            return Ok(utils::see_other(&edit_page));
+        }
+    }
+    let recipients = recipients
+        .iter()
+        .map(|r| r.as_ref())
+        .collect::<Vec<_>>()
+        .join(", ");
+    FlashMessage::info(format!("A test copy has been sent to {}.", recipients)).send();
+    Ok(utils::see_other(&edit_page))
+}
+
+/// Addresses can be separated by commas, whitespace or new lines.
+fn parse_recipients(recipients: &str) -> Result<Vec<SubscriberEmail>, String> {
+    let recipients = recipients
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|r| !r.is_empty())
+        .map(|r| SubscriberEmail::parse(r.to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+    if recipients.is_empty() {
+        return Err("Enter at least one address to send a test copy to.".into());
+    }
+    if recipients.len() > MAX_TEST_RECIPIENTS {
+        return Err(format!(
+            "A test copy can be sent to at most {} addresses.",
+            MAX_TEST_RECIPIENTS
+        ));
+    }
+    Ok(recipients)
+}
@@ -0,0 +1,48 @@
+use crate::authentication::require_permission;
+use crate::domain::{Permission, UserRole};
+use crate::utils;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(serde::Deserialize)]
+pub struct VisibilityFormData {
+    is_private: bool,
+}
+
+/// Private issues are still delivered to subscribers, but are left out of the
+/// public archive.
+#[tracing::instrument(name = "Change the visibility of a newsletter issue", skip(form, pool))]
+pub async fn set_newsletter_visibility(
+    newsletter_issue_id: web::Path<Uuid>,
+    form: web::Form<VisibilityFormData>,
+    pool: web::Data<PgPool>,
+    role: web::ReqData<UserRole>,
+) -> Result<HttpResponse, actix_web::Error> {
+    require_permission(&role, Permission::PublishIssues)?;
+    let n_updated_rows = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET is_private = $2, updated_at = now()
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id.into_inner(),
+        form.is_private
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to update the newsletter issue visibility")
+    .map_err(utils::e500)?
+    .rows_affected();
+    if n_updated_rows == 0 {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    if form.is_private {
+        FlashMessage::info("The newsletter issue is now hidden from the public archive.").send();
+    } else {
+        FlashMessage::info("The newsletter issue is now listed in the public archive.").send();
+    }
+    Ok(utils::see_other("/admin/newsletters"))
+}
@@ -3,6 +3,7 @@ use crate::utils;
 use actix_web::http::header::ContentType;
 use actix_web::HttpResponse;
 use actix_web_flash_messages::IncomingFlashMessages;
+use htmlescape::encode_minimal;
 use std::fmt::Write;
 
 pub async fn change_password_form(
@@ -14,7 +15,7 @@ pub async fn change_password_form(
     };
     let mut msg_html = String::new();
     for m in flash_messages.iter() {
-        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+        writeln!(msg_html, "<p><i>{}</i></p>", encode_minimal(m.content())).unwrap();
     }
     Ok(HttpResponse::Ok()
         .content_type(ContentType::html())
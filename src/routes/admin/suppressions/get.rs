@@ -0,0 +1,96 @@
+use crate::authentication::require_permission;
+use crate::domain::{Permission, UserRole};
+use crate::suppression::search_suppressed_emails;
+use crate::utils;
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use htmlescape::{encode_attribute, encode_minimal};
+use sqlx::PgPool;
+use std::fmt::Write;
+
+/// The list can grow large: admins are expected to search it.
+const MAX_LISTED_ENTRIES: i64 = 100;
+
+#[derive(serde::Deserialize)]
+pub struct SearchParameters {
+    search: Option<String>,
+}
+
+pub async fn suppression_list(
+    parameters: web::Query<SearchParameters>,
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+    role: web::ReqData<UserRole>,
+) -> Result<HttpResponse, actix_web::Error> {
+    require_permission(&role, Permission::ViewSuppressions)?;
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        // Error messages echo back what was typed in the form.
+        writeln!(msg_html, "<p><i>{}</i></p>", encode_minimal(m.content())).unwrap();
+    }
+    let search = parameters.search.as_deref().unwrap_or_default().trim();
+    let entries = search_suppressed_emails(&pool, search, MAX_LISTED_ENTRIES)
+        .await
+        .map_err(utils::e500)?;
+    let mut rows_html = String::new();
+    for entry in &entries {
+        writeln!(
+            rows_html,
+            r#"<tr><td>{}</td><td>{}</td><td>{}</td><td>{} UTC</td><td>
+                <form action="/admin/suppressions/remove" method="post">
+                    <input type="hidden" name="email" value="{}">
+                    <button type="submit">Remove</button>
+                </form>
+            </td></tr>"#,
+            encode_minimal(&entry.email),
+            encode_minimal(&entry.reason),
+            entry.source,
+            entry.suppressed_at.format("%Y-%m-%d %H:%M"),
+            encode_attribute(&entry.email),
+        )
+        .unwrap();
+    }
+    if entries.is_empty() {
+        rows_html.push_str(r#"<tr><td colspan="5">No suppressed addresses.</td></tr>"#);
+    }
+    let search = encode_attribute(search);
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equev="content-type" content="text/html"; charset="utf-8">
+    <title>Suppression list</title>
+</head>
+    <body>
+        {msg_html}
+        <p>We never send any email to the addresses below.</p>
+        <form action="/admin/suppressions" method="get">
+            <label>Search
+                <input type="text" name="search" value="{search}">
+            </label>
+            <button type="submit">Search</button>
+        </form>
+        <table>
+            <tr><th>Email</th><th>Reason</th><th>Source</th><th>Since</th><th>Actions</th></tr>
+            {rows_html}
+        </table>
+        <form action="/admin/suppressions" method="post">
+            <label>Email
+                <input type="text" placeholder="Enter the address to suppress" name="email">
+            </label>
+            <br>
+            <label>Reason
+                <input type="text" placeholder="Why should they not hear from us?" name="reason">
+            </label>
+            <br>
+            <button type="submit">Suppress</button>
+        </form>
+            <br>
+            <p><a href="/admin/dashboard">&lt;-Back</a></p>
+    </body>
+</html>"#,
+        )))
+}
@@ -0,0 +1,97 @@
+use crate::authentication::require_permission;
+use crate::domain::{Permission, SubscriberEmail, UserRole};
+use crate::suppression::{remove_suppressed_email, suppress_subscriber, SuppressionSource};
+use crate::utils;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+
+#[derive(serde::Deserialize)]
+pub struct SuppressFormData {
+    email: String,
+    reason: String,
+}
+
+#[tracing::instrument(name = "Suppress an email address by hand", skip(form, pool))]
+pub async fn suppress_email(
+    form: web::Form<SuppressFormData>,
+    pool: web::Data<PgPool>,
+    role: web::ReqData<UserRole>,
+) -> Result<HttpResponse, actix_web::Error> {
+    require_permission(&role, Permission::ManageSuppressions)?;
+    let email = match SubscriberEmail::parse(form.0.email) {
+        Ok(email) => email,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(utils::see_other("/admin/suppressions"));
+        }
+    };
+    let reason = match form.0.reason.trim() {
+        "" => "Suppressed by an admin",
+        reason => reason,
+    };
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")
+        .map_err(utils::e500)?;
+    let is_new = suppress_subscriber(
+        &mut transaction,
+        email.as_ref(),
+        SuppressionSource::Admin,
+        reason,
+    )
+    .await
+    .context("Failed to suppress the email address.")
+    .map_err(utils::e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to suppress an email address.")
+        .map_err(utils::e500)?;
+    if is_new {
+        FlashMessage::info(format!("{} has been added to the suppression list.", email)).send();
+    } else {
+        FlashMessage::info(format!("{} is already on the suppression list.", email)).send();
+    }
+    Ok(utils::see_other("/admin/suppressions"))
+}
+
+#[derive(serde::Deserialize)]
+pub struct UnsuppressFormData {
+    email: String,
+}
+
+#[tracing::instrument(
+    name = "Remove an email address from the suppression list",
+    skip(form, pool)
+)]
+pub async fn unsuppress_email(
+    form: web::Form<UnsuppressFormData>,
+    pool: web::Data<PgPool>,
+    role: web::ReqData<UserRole>,
+) -> Result<HttpResponse, actix_web::Error> {
+    require_permission(&role, Permission::ManageSuppressions)?;
+    let email = match SubscriberEmail::parse(form.0.email) {
+        Ok(email) => email,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(utils::see_other("/admin/suppressions"));
+        }
+    };
+    let was_suppressed = remove_suppressed_email(&pool, email.as_ref())
+        .await
+        .context("Failed to remove the email address from the suppression list.")
+        .map_err(utils::e500)?;
+    if was_suppressed {
+        FlashMessage::info(format!(
+            "{} has been removed from the suppression list.",
+            email
+        ))
+        .send();
+    } else {
+        FlashMessage::error(format!("{} is not on the suppression list.", email)).send();
+    }
+    Ok(utils::see_other("/admin/suppressions"))
+}
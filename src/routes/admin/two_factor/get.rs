@@ -0,0 +1,95 @@
+use crate::authentication::{self, UserId};
+use crate::domain::TotpSecret;
+use crate::routes::admin::dashboard;
+use crate::session_state::TypedSession;
+use crate::utils;
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use htmlescape::encode_minimal;
+use secrecy::ExposeSecret;
+use sqlx::PgPool;
+use std::fmt::Write;
+
+/// The name authenticator apps list the account under.
+pub const TOTP_ISSUER: &str = "Newsletter";
+
+pub async fn two_factor_settings(
+    pool: web::Data<PgPool>,
+    session: TypedSession,
+    user_id: web::ReqData<UserId>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", encode_minimal(m.content())).unwrap();
+    }
+    let enabled = authentication::get_totp_secret(*user_id, &pool)
+        .await
+        .map_err(utils::e500)?
+        .is_some();
+    let body_html = if enabled {
+        let n_recovery_codes = authentication::count_unused_recovery_codes(*user_id, &pool)
+            .await
+            .map_err(utils::e500)?;
+        format!(
+            r#"<p>Two-factor authentication is enabled.</p>
+            <p>You have {n_recovery_codes} unused recovery codes left.</p>
+            <form action="/admin/two_factor/disable" method="post">
+                <label>Code
+                    <input type="text" placeholder="Enter code" name="code">
+                </label>
+                <br>
+                <button type="submit">Disable two-factor authentication</button>
+            </form>"#
+        )
+    } else {
+        // Keep showing the same secret until the user confirms it, in case
+        // they reload the page after adding it to their app.
+        let secret = match session.get_pending_totp_secret().map_err(utils::e500)? {
+            Some(secret) => TotpSecret::parse(&secret).map_err(utils::e500)?,
+            None => {
+                let secret = TotpSecret::generate();
+                session
+                    .insert_pending_totp_secret(secret.to_base32().expose_secret())
+                    .map_err(utils::e500)?;
+                secret
+            }
+        };
+        let username = dashboard::get_username(*user_id, &pool)
+            .await
+            .map_err(utils::e500)?;
+        format!(
+            r#"<p>Two-factor authentication is disabled.</p>
+            <p>To enable it, add this account to your authenticator app:</p>
+            <p><code>{}</code></p>
+            <p>Or enter this key by hand: <code>{}</code></p>
+            <form action="/admin/two_factor" method="post">
+                <label>Then enter the code it shows
+                    <input type="text" placeholder="Enter code" name="code">
+                </label>
+                <br>
+                <button type="submit">Enable two-factor authentication</button>
+            </form>"#,
+            encode_minimal(secret.otpauth_uri(TOTP_ISSUER, &username).expose_secret()),
+            secret.to_base32().expose_secret(),
+        )
+    };
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Two-factor authentication</title>
+</head>
+    <body>
+        {msg_html}
+        {body_html}
+        <p><a href="/admin/dashboard">&lt;-Back</a></p>
+    </body>
+</html>"#,
+        )))
+}
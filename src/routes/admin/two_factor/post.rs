@@ -0,0 +1,93 @@
+use crate::authentication::{self, SecondFactorOutcome, UserId};
+use crate::domain::TotpSecret;
+use crate::session_state::TypedSession;
+use crate::utils;
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use chrono::Utc;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+use std::fmt::Write;
+
+#[derive(serde::Deserialize)]
+pub struct TwoFactorCodeFormData {
+    code: Secret<String>,
+}
+
+pub async fn enable_two_factor(
+    form: web::Form<TwoFactorCodeFormData>,
+    pool: web::Data<PgPool>,
+    session: TypedSession,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    let secret = match session.get_pending_totp_secret().map_err(utils::e500)? {
+        Some(secret) => TotpSecret::parse(&secret).map_err(utils::e500)?,
+        None => return Ok(utils::see_other("/admin/two_factor")),
+    };
+    let code = form.0.code.expose_secret().trim().to_string();
+    let time_step = match secret.verify(&code, Utc::now()) {
+        Some(time_step) => time_step,
+        None => {
+            FlashMessage::error("The code is invalid: check the clock of your device.").send();
+            return Ok(utils::see_other("/admin/two_factor"));
+        }
+    };
+    let recovery_codes = authentication::enable_two_factor(*user_id, &secret, time_step, &pool)
+        .await
+        .map_err(utils::e500)?;
+    session.remove_pending_totp_secret();
+    // We only store hashes: this is the one time users get to see the codes.
+    let mut codes_html = String::new();
+    for code in &recovery_codes {
+        writeln!(codes_html, "<li><code>{}</code></li>", code.expose_secret()).unwrap();
+    }
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Two-factor authentication</title>
+</head>
+    <body>
+        <p>Two-factor authentication is enabled.</p>
+        <p>If you lose your device, log in with one of these recovery codes instead of a code from your app.
+        Each works once. Keep them somewhere safe: they will not be shown again.</p>
+        <ul>
+        {codes_html}
+        </ul>
+        <p><a href="/admin/dashboard">&lt;-Back</a></p>
+    </body>
+</html>"#,
+        )))
+}
+
+pub async fn disable_two_factor(
+    form: web::Form<TwoFactorCodeFormData>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    match authentication::verify_second_factor(*user_id, &form.0.code, &pool)
+        .await
+        .map_err(utils::e500)?
+    {
+        SecondFactorOutcome::Valid => {}
+        SecondFactorOutcome::Invalid => {
+            FlashMessage::error("The code is invalid.").send();
+            return Ok(utils::see_other("/admin/two_factor"));
+        }
+        SecondFactorOutcome::LockedOut => {
+            FlashMessage::error("Too many invalid codes: please try again later.").send();
+            return Ok(utils::see_other("/admin/two_factor"));
+        }
+    }
+    authentication::disable_two_factor(*user_id, &pool)
+        .await
+        .map_err(utils::e500)?;
+    FlashMessage::info("Two-factor authentication has been disabled.").send();
+    Ok(utils::see_other("/admin/two_factor"))
+}
@@ -0,0 +1,160 @@
+use crate::authentication::{require_permission, UserId};
+use crate::domain::{Permission, UserRole};
+use crate::utils;
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use htmlescape::encode_minimal;
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+pub async fn list_users(
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    flash_messages: IncomingFlashMessages,
+    role: web::ReqData<UserRole>,
+) -> Result<HttpResponse, actix_web::Error> {
+    require_permission(&role, Permission::ManageUsers)?;
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", encode_minimal(m.content())).unwrap();
+    }
+    let users = get_users(&pool).await.map_err(utils::e500)?;
+    let role_options = |selected: &str| {
+        UserRole::ALL
+            .iter()
+            .map(|role| {
+                let selected = if role.as_str() == selected {
+                    " selected"
+                } else {
+                    ""
+                };
+                format!(r#"<option value="{role}"{selected}>{role}</option>"#)
+            })
+            .collect::<String>()
+    };
+    let mut rows_html = String::new();
+    for user in &users {
+        let status = if !user.is_active {
+            "Deactivated"
+        } else if !user.has_password {
+            "Waiting for a password"
+        } else {
+            "Active"
+        };
+        // Admins cannot lock themselves out.
+        let (role_html, actions) = if user.user_id == **user_id {
+            (user.role.clone(), "This is you".to_string())
+        } else {
+            let (toggle_action, toggle_label) = if user.is_active {
+                ("deactivate", "Deactivate")
+            } else {
+                ("activate", "Activate")
+            };
+            let role_html = format!(
+                r#"<form action="/admin/users/{}/role" method="post">
+                    <select name="role">{}</select>
+                    <button type="submit">Change role</button>
+                </form>"#,
+                user.user_id,
+                role_options(&user.role)
+            );
+            let actions = format!(
+                r#"<form action="/admin/users/{id}/{toggle_action}" method="post">
+                    <button type="submit">{toggle_label}</button>
+                </form>
+                <form action="/admin/users/{id}/reset_password" method="post">
+                    <button type="submit">Reset password</button>
+                </form>
+                <form action="/admin/users/{id}/delete" method="post">
+                    <button type="submit">Delete</button>
+                </form>"#,
+                id = user.user_id,
+            );
+            (role_html, actions)
+        };
+        writeln!(
+            rows_html,
+            r#"<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{} UTC</td><td>{}</td></tr>"#,
+            encode_minimal(&user.username),
+            encode_minimal(user.email.as_deref().unwrap_or("-")),
+            role_html,
+            status,
+            user.created_at.format("%Y-%m-%d %H:%M"),
+            actions
+        )
+        .unwrap();
+    }
+    let invite_role_options = role_options(UserRole::Viewer.as_str());
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equev="content-type" content="text/html"; charset="utf-8">
+    <title>Users</title>
+</head>
+    <body>
+        {msg_html}
+        <table>
+            <tr><th>Username</th><th>Email</th><th>Role</th><th>Status</th><th>Created</th><th>Actions</th></tr>
+            {rows_html}
+        </table>
+        <p>Invite a colleague: we will email them a link to choose their password.</p>
+        <form action="/admin/users" method="post">
+            <label>Username
+                <input type="text" placeholder="Enter their username" name="username">
+            </label>
+            <br>
+            <label>Email
+                <input type="text" placeholder="Enter their email address" name="email">
+            </label>
+            <br>
+            <label>Role
+                <select name="role">{invite_role_options}</select>
+            </label>
+            <br>
+            <button type="submit">Invite</button>
+        </form>
+            <br>
+            <p><a href="/admin/dashboard">&lt;-Back</a></p>
+    </body>
+</html>"#,
+        )))
+}
+
+struct User {
+    user_id: Uuid,
+    username: String,
+    email: Option<String>,
+    role: String,
+    is_active: bool,
+    has_password: bool,
+    created_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(name = "Get users", skip(pool))]
+async fn get_users(pool: &PgPool) -> Result<Vec<User>, anyhow::Error> {
+    sqlx::query_as!(
+        User,
+        r#"
+        SELECT
+            user_id,
+            username,
+            email,
+            role,
+            is_active,
+            password_hash IS NOT NULL AS "has_password!",
+            created_at
+        FROM users
+        ORDER BY username
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to perform a query to retrieve the users.")
+}
@@ -0,0 +1,318 @@
+use crate::authentication::{
+    require_permission, send_set_password_link, SetPasswordReason, UserId,
+};
+use crate::domain::{Permission, SubscriberEmail, UserRole};
+use crate::startup::ApplicationBaseUrl;
+use crate::utils;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+use unicode_segmentation::UnicodeSegmentation;
+use uuid::Uuid;
+
+const MAX_USERNAME_LENGTH: usize = 64;
+
+#[derive(serde::Deserialize)]
+pub struct InviteFormData {
+    username: String,
+    email: String,
+    role: String,
+}
+
+/// Create a user without a password, and email them a link to choose one.
+#[tracing::instrument(name = "Invite a user", skip(form, pool, base_url))]
+pub async fn invite_user(
+    form: web::Form<InviteFormData>,
+    pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    role: web::ReqData<UserRole>,
+) -> Result<HttpResponse, actix_web::Error> {
+    require_permission(&role, Permission::ManageUsers)?;
+    let username = form.0.username.trim();
+    if username.is_empty() || username.graphemes(true).count() > MAX_USERNAME_LENGTH {
+        FlashMessage::error(format!(
+            "The username must contain between 1 and {} characters.",
+            MAX_USERNAME_LENGTH
+        ))
+        .send();
+        return Ok(utils::see_other("/admin/users"));
+    }
+    let email = match SubscriberEmail::parse(form.0.email) {
+        Ok(email) => email,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(utils::see_other("/admin/users"));
+        }
+    };
+    let new_user_role = UserRole::try_from(form.0.role).map_err(utils::e400)?;
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")
+        .map_err(utils::e500)?;
+    let user = sqlx::query!(
+        r#"
+        INSERT INTO users (user_id, username, email, role)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT DO NOTHING
+        RETURNING user_id
+        "#,
+        Uuid::new_v4(),
+        username,
+        email.as_ref(),
+        new_user_role.as_str()
+    )
+    .fetch_optional(&mut transaction)
+    .await
+    .context("Failed to insert the invited user.")
+    .map_err(utils::e500)?;
+    let user = match user {
+        Some(user) => user,
+        None => {
+            FlashMessage::error("A user with this username or email already exists.").send();
+            return Ok(utils::see_other("/admin/users"));
+        }
+    };
+    send_set_password_link(
+        &mut transaction,
+        user.user_id,
+        username,
+        &email,
+        &base_url.0,
+        SetPasswordReason::Invitation,
+    )
+    .await
+    .context("Failed to send the invitation email.")
+    .map_err(utils::e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to invite a user.")
+        .map_err(utils::e500)?;
+    FlashMessage::info(format!(
+        "{} has been invited: they will receive an email at {}.",
+        username, email
+    ))
+    .send();
+    Ok(utils::see_other("/admin/users"))
+}
+
+#[tracing::instrument(name = "Deactivate a user", skip(pool, current_user_id))]
+pub async fn deactivate_user(
+    user_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    current_user_id: web::ReqData<UserId>,
+    role: web::ReqData<UserRole>,
+) -> Result<HttpResponse, actix_web::Error> {
+    require_permission(&role, Permission::ManageUsers)?;
+    set_user_activation(&pool, *user_id, *current_user_id.into_inner(), false).await
+}
+
+#[tracing::instrument(name = "Activate a user", skip(pool, current_user_id))]
+pub async fn activate_user(
+    user_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    current_user_id: web::ReqData<UserId>,
+    role: web::ReqData<UserRole>,
+) -> Result<HttpResponse, actix_web::Error> {
+    require_permission(&role, Permission::ManageUsers)?;
+    set_user_activation(&pool, *user_id, *current_user_id.into_inner(), true).await
+}
+
+/// Deactivated users keep their account, but cannot log in until they are
+/// activated again.
+async fn set_user_activation(
+    pool: &PgPool,
+    user_id: Uuid,
+    current_user_id: Uuid,
+    is_active: bool,
+) -> Result<HttpResponse, actix_web::Error> {
+    if user_id == current_user_id {
+        FlashMessage::error("You cannot deactivate yourself.").send();
+        return Ok(utils::see_other("/admin/users"));
+    }
+    let user = sqlx::query!(
+        r#"
+        UPDATE users
+        SET is_active = $2
+        WHERE user_id = $1
+        RETURNING username
+        "#,
+        user_id,
+        is_active
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to update the user's status.")
+    .map_err(utils::e500)?;
+    let user = match user {
+        Some(user) => user,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+    if is_active {
+        FlashMessage::info(format!("{} can log in again.", user.username)).send();
+    } else {
+        FlashMessage::info(format!("{} has been deactivated.", user.username)).send();
+    }
+    Ok(utils::see_other("/admin/users"))
+}
+
+#[derive(serde::Deserialize)]
+pub struct RoleFormData {
+    role: String,
+}
+
+#[tracing::instrument(name = "Change the role of a user", skip(form, pool, current_user_id))]
+pub async fn change_user_role(
+    user_id: web::Path<Uuid>,
+    form: web::Form<RoleFormData>,
+    pool: web::Data<PgPool>,
+    current_user_id: web::ReqData<UserId>,
+    role: web::ReqData<UserRole>,
+) -> Result<HttpResponse, actix_web::Error> {
+    require_permission(&role, Permission::ManageUsers)?;
+    // Otherwise the last owner could leave nobody able to manage users.
+    if *user_id == **current_user_id {
+        FlashMessage::error("You cannot change your own role.").send();
+        return Ok(utils::see_other("/admin/users"));
+    }
+    let new_role = UserRole::try_from(form.0.role).map_err(utils::e400)?;
+    let user = sqlx::query!(
+        r#"
+        UPDATE users
+        SET role = $2
+        WHERE user_id = $1
+        RETURNING username
+        "#,
+        *user_id,
+        new_role.as_str()
+    )
+    .fetch_optional(pool.get_ref())
+    .await
+    .context("Failed to update the user's role.")
+    .map_err(utils::e500)?;
+    let user = match user {
+        Some(user) => user,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+    FlashMessage::info(format!(
+        "The role of {} is now {}.",
+        user.username, new_role
+    ))
+    .send();
+    Ok(utils::see_other("/admin/users"))
+}
+
+#[tracing::instrument(name = "Delete a user", skip(pool, current_user_id))]
+pub async fn delete_user(
+    user_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    current_user_id: web::ReqData<UserId>,
+    role: web::ReqData<UserRole>,
+) -> Result<HttpResponse, actix_web::Error> {
+    require_permission(&role, Permission::ManageUsers)?;
+    if *user_id == **current_user_id {
+        FlashMessage::error("You cannot delete yourself.").send();
+        return Ok(utils::see_other("/admin/users"));
+    }
+    let user = sqlx::query!(
+        r#"DELETE FROM users WHERE user_id = $1 RETURNING username"#,
+        *user_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await
+    .context("Failed to delete the user.")
+    .map_err(utils::e500)?;
+    let user = match user {
+        Some(user) => user,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+    FlashMessage::info(format!("{} has been deleted.", user.username)).send();
+    Ok(utils::see_other("/admin/users"))
+}
+
+/// Forget the user's password, logging them out, and email them a link to
+/// choose a new one.
+#[tracing::instrument(
+    name = "Reset a user's password",
+    skip(pool, base_url, current_user_id)
+)]
+pub async fn reset_user_password(
+    user_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    current_user_id: web::ReqData<UserId>,
+    role: web::ReqData<UserRole>,
+) -> Result<HttpResponse, actix_web::Error> {
+    require_permission(&role, Permission::ManageUsers)?;
+    if *user_id == **current_user_id {
+        FlashMessage::error("Change your own password from the password page instead.").send();
+        return Ok(utils::see_other("/admin/users"));
+    }
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")
+        .map_err(utils::e500)?;
+    let user = sqlx::query!(
+        r#"
+        SELECT username, email
+        FROM users
+        WHERE user_id = $1
+        FOR UPDATE
+        "#,
+        *user_id
+    )
+    .fetch_optional(&mut transaction)
+    .await
+    .context("Failed to retrieve the user.")
+    .map_err(utils::e500)?;
+    let user = match user {
+        Some(user) => user,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+    // Users created before invitations existed, like the first admin, may
+    // have no email address on file.
+    let email = match user.email.map(SubscriberEmail::parse) {
+        Some(Ok(email)) => email,
+        _ => {
+            FlashMessage::error(format!(
+                "{} has no valid email address to send a reset link to.",
+                user.username
+            ))
+            .send();
+            return Ok(utils::see_other("/admin/users"));
+        }
+    };
+    sqlx::query!(
+        r#"UPDATE users SET password_hash = NULL WHERE user_id = $1"#,
+        *user_id
+    )
+    .execute(&mut transaction)
+    .await
+    .context("Failed to clear the user's password.")
+    .map_err(utils::e500)?;
+    send_set_password_link(
+        &mut transaction,
+        *user_id,
+        &user.username,
+        &email,
+        &base_url.0,
+        SetPasswordReason::PasswordReset,
+    )
+    .await
+    .context("Failed to send the password reset email.")
+    .map_err(utils::e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to reset a password.")
+        .map_err(utils::e500)?;
+    FlashMessage::info(format!(
+        "{} has been logged out: they will receive an email at {} to choose a new password.",
+        user.username, email
+    ))
+    .send();
+    Ok(utils::see_other("/admin/users"))
+}
@@ -0,0 +1,222 @@
+use crate::configuration::EmailEventsSettings;
+use crate::domain::EmailEventKind;
+use crate::suppression::{suppress_subscriber, SuppressionSource};
+use crate::utils;
+use actix_web::http::header::{HeaderMap, HeaderValue, AUTHORIZATION, WWW_AUTHENTICATE};
+use actix_web::{web, HttpRequest, HttpResponse};
+use anyhow::Context;
+use secrecy::ExposeSecret;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+/// The fields we care about in a Postmark-style bounce, spam complaint or
+/// delivery webhook.
+#[derive(serde::Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+pub struct EmailEventPayload {
+    record_type: String,
+    #[serde(rename = "ID")]
+    id: Option<i64>,
+    #[serde(rename = "Type")]
+    event_type: Option<String>,
+    /// Delivery webhooks call it `Recipient`.
+    #[serde(alias = "Recipient")]
+    email: String,
+    #[serde(rename = "MessageID")]
+    message_id: Option<String>,
+    description: Option<String>,
+}
+
+/// Webhook called by our email provider when an email bounces, is reported
+/// as spam or is delivered.
+///
+/// Hard bounces and complaints suppress the address straight away; soft
+/// bounces only do once they reach `soft_bounce_threshold` in a row: a
+/// successful delivery starts the count over.
+#[tracing::instrument(
+    name = "Receive an email event",
+    skip(request, body, pool, settings),
+    fields(email = tracing::field::Empty, kind = tracing::field::Empty)
+)]
+pub async fn receive_email_event(
+    request: HttpRequest,
+    body: web::Bytes,
+    pool: web::Data<PgPool>,
+    settings: web::Data<EmailEventsSettings>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if !is_authorized(request.headers(), &settings) {
+        let mut response = HttpResponse::Unauthorized().finish();
+        let header_value = HeaderValue::from_str(r#"Basic realm="email_events""#).unwrap();
+        response
+            .headers_mut()
+            .insert(WWW_AUTHENTICATE, header_value);
+        return Ok(response);
+    }
+    let payload: EmailEventPayload = serde_json::from_slice(&body).map_err(utils::e400)?;
+    let kind = EmailEventKind::classify(&payload.record_type, payload.event_type.as_deref());
+    tracing::Span::current()
+        .record("email", &tracing::field::display(&payload.email))
+        .record("kind", &tracing::field::display(kind));
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")
+        .map_err(utils::e500)?;
+    let is_new = store_email_event(&mut transaction, &payload, kind, &body)
+        .await
+        .context("Failed to store the email event.")
+        .map_err(utils::e500)?;
+    if !is_new {
+        tracing::info!("Ignoring an email event we have already processed.");
+        return Ok(HttpResponse::Ok().finish());
+    }
+    let suppress = match kind {
+        EmailEventKind::HardBounce | EmailEventKind::SpamComplaint => true,
+        EmailEventKind::SoftBounce => {
+            let soft_bounce_count = count_soft_bounce(&mut transaction, &payload.email)
+                .await
+                .context("Failed to count a soft bounce.")
+                .map_err(utils::e500)?;
+            soft_bounce_count.unwrap_or(0) >= settings.soft_bounce_threshold
+        }
+        EmailEventKind::Delivered => {
+            reset_soft_bounce_count(&mut transaction, &payload.email)
+                .await
+                .context("Failed to reset the soft bounce count.")
+                .map_err(utils::e500)?;
+            false
+        }
+        EmailEventKind::Other => false,
+    };
+    if suppress {
+        let reason = match &payload.description {
+            Some(description) => format!("{}: {}", kind, description),
+            None => kind.to_string(),
+        };
+        suppress_subscriber(
+            &mut transaction,
+            &payload.email,
+            SuppressionSource::EmailProvider,
+            &reason,
+        )
+        .await
+        .context("Failed to suppress the subscriber.")
+        .map_err(utils::e500)?;
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to store an email event.")
+        .map_err(utils::e500)?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Providers can only be configured with credentials in the webhook URL,
+/// which they send as HTTP basic auth: we only check the password.
+fn is_authorized(headers: &HeaderMap, settings: &EmailEventsSettings) -> bool {
+    let password = headers
+        .get(AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Basic "))
+        .and_then(|credentials| base64::decode(credentials).ok())
+        .and_then(|credentials| String::from_utf8(credentials).ok())
+        .and_then(|credentials| {
+            credentials
+                .split_once(':')
+                .map(|(_, password)| password.to_string())
+        });
+    match password {
+        Some(password) => constant_time_eq(
+            password.as_bytes(),
+            settings.webhook_token.expose_secret().as_bytes(),
+        ),
+        None => false,
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Returns `false` if the provider already told us about this event.
+#[tracing::instrument(skip_all)]
+async fn store_email_event(
+    transaction: &mut Transaction<'_, Postgres>,
+    payload: &EmailEventPayload,
+    kind: EmailEventKind,
+    raw_payload: &[u8],
+) -> Result<bool, sqlx::Error> {
+    let raw_payload = String::from_utf8_lossy(raw_payload);
+    let n_inserted_rows = sqlx::query!(
+        r#"
+        INSERT INTO email_events (
+            id,
+            provider_event_id,
+            subscriber_id,
+            email,
+            kind,
+            provider_message_id,
+            description,
+            payload,
+            received_at
+        )
+        SELECT
+            $1,
+            $2,
+            (
+                SELECT id FROM subscriptions
+                WHERE lower(email) = lower($3)
+                ORDER BY email = $3 DESC
+                LIMIT 1
+            ),
+            $3, $4, $5, $6, $7, now()
+        ON CONFLICT (provider_event_id) DO NOTHING
+        "#,
+        Uuid::new_v4(),
+        payload.id.map(|id| id.to_string()),
+        payload.email,
+        kind.as_str(),
+        payload.message_id,
+        payload.description,
+        raw_payload.as_ref()
+    )
+    .execute(transaction)
+    .await?
+    .rows_affected();
+    Ok(n_inserted_rows > 0)
+}
+
+/// Returns the updated count, or `None` if the address is not one of our subscribers.
+#[tracing::instrument(skip(transaction))]
+async fn count_soft_bounce(
+    transaction: &mut Transaction<'_, Postgres>,
+    email: &str,
+) -> Result<Option<i32>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET soft_bounce_count = soft_bounce_count + 1
+        WHERE lower(email) = lower($1)
+        RETURNING soft_bounce_count
+        "#,
+        email
+    )
+    .fetch_optional(transaction)
+    .await?;
+    Ok(row.map(|r| r.soft_bounce_count))
+}
+
+#[tracing::instrument(skip(transaction))]
+async fn reset_soft_bounce_count(
+    transaction: &mut Transaction<'_, Postgres>,
+    email: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE subscriptions SET soft_bounce_count = 0 WHERE lower(email) = lower($1)",
+        email
+    )
+    .execute(transaction)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,18 @@
+use crate::domain::{IssueTemplate, TemplateContext};
+use crate::issue_delivery_worker::issue_archive_url;
+
+/// The HTML content of an issue as readers of the archive see it: without
+/// the details of any particular subscriber.
+pub fn archived_html_content(html_content: String, base_url: &str, slug: &str) -> String {
+    let archive_url = issue_archive_url(base_url, slug);
+    let context = TemplateContext {
+        subscriber_name: "reader",
+        unsubscribe_url: base_url,
+        archive_url: &archive_url,
+    };
+    // Issues written before templating was introduced may contain stray braces.
+    match IssueTemplate::parse(&html_content) {
+        Ok(template) => template.render_html(&context),
+        Err(_) => html_content,
+    }
+}
@@ -0,0 +1,230 @@
+use super::archive::archived_html_content;
+use crate::issue_delivery_worker::issue_archive_url;
+use crate::startup::ApplicationBaseUrl;
+use crate::utils;
+use actix_web::http::header::{
+    ETag, EntityTag, HttpDate, IfModifiedSince, IfNoneMatch, LastModified,
+};
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, SubsecRound, Utc};
+use htmlescape::encode_minimal;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::fmt::Write;
+use std::time::SystemTime;
+use uuid::Uuid;
+
+/// Feed readers only look for what is new: older issues stay in the archive.
+const MAX_FEED_ENTRIES: i64 = 20;
+
+const FEED_TITLE: &str = "Newsletter";
+
+pub async fn rss_feed(
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let feed = get_feed(&pool).await.map_err(utils::e500)?;
+    let base_url = &base_url.0;
+    let mut items_xml = String::new();
+    for entry in feed.entries {
+        // The GUID is the issue id: it survives changes to the title and slug.
+        writeln!(
+            items_xml,
+            r#"<item>
+    <title>{}</title>
+    <link>{}</link>
+    <guid isPermaLink="false">urn:uuid:{}</guid>
+    <pubDate>{}</pubDate>
+    <description>{}</description>
+</item>"#,
+            encode_minimal(&entry.title),
+            encode_minimal(&issue_archive_url(base_url, &entry.slug)),
+            entry.newsletter_issue_id,
+            entry.published_at.to_rfc2822(),
+            encode_minimal(&archived_html_content(
+                entry.html_content,
+                base_url,
+                &entry.slug
+            ))
+        )
+        .unwrap();
+    }
+    let last_build_date = feed
+        .last_modified
+        .map(|t| format!("<lastBuildDate>{}</lastBuildDate>", t.to_rfc2822()))
+        .unwrap_or_default();
+    let body = format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<rss version="2.0">
+<channel>
+<title>{FEED_TITLE}</title>
+<link>{base_url}/issues</link>
+<description>Every issue of the newsletter.</description>
+{last_build_date}
+{items_xml}</channel>
+</rss>"#,
+        base_url = encode_minimal(base_url),
+    );
+    Ok(conditional_response(
+        &request,
+        "application/rss+xml; charset=utf-8",
+        body,
+        feed.last_modified,
+    ))
+}
+
+pub async fn atom_feed(
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let feed = get_feed(&pool).await.map_err(utils::e500)?;
+    let base_url = &base_url.0;
+    let mut entries_xml = String::new();
+    for entry in feed.entries {
+        writeln!(
+            entries_xml,
+            r#"<entry>
+    <title>{}</title>
+    <link href="{}"/>
+    <id>urn:uuid:{}</id>
+    <published>{}</published>
+    <updated>{}</updated>
+    <content type="html">{}</content>
+</entry>"#,
+            encode_minimal(&entry.title),
+            encode_minimal(&issue_archive_url(base_url, &entry.slug)),
+            entry.newsletter_issue_id,
+            entry.published_at.to_rfc3339(),
+            entry.updated_at.to_rfc3339(),
+            encode_minimal(&archived_html_content(
+                entry.html_content,
+                base_url,
+                &entry.slug
+            ))
+        )
+        .unwrap();
+    }
+    // `updated` is mandatory: an empty feed has not changed since the epoch.
+    let updated = feed
+        .last_modified
+        .unwrap_or_else(|| DateTime::<Utc>::from(SystemTime::UNIX_EPOCH));
+    let body = format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+<title>{FEED_TITLE}</title>
+<id>{base_url}/feed.atom</id>
+<link rel="self" href="{base_url}/feed.atom"/>
+<link href="{base_url}/issues"/>
+<updated>{updated}</updated>
+<author><name>{FEED_TITLE}</name></author>
+{entries_xml}</feed>"#,
+        base_url = encode_minimal(base_url),
+        updated = updated.to_rfc3339(),
+    );
+    Ok(conditional_response(
+        &request,
+        "application/atom+xml; charset=utf-8",
+        body,
+        feed.last_modified,
+    ))
+}
+
+/// Answer with a `304 Not Modified` if the client already has this version of
+/// the feed, so that feed readers polling us do not download it again.
+///
+/// As per RFC 7232, `If-Modified-Since` is ignored when `If-None-Match` is sent.
+fn conditional_response(
+    request: &HttpRequest,
+    content_type: &str,
+    body: String,
+    last_modified: Option<DateTime<Utc>>,
+) -> HttpResponse {
+    let etag = EntityTag::new_strong(base64::encode_config(
+        Sha256::digest(body.as_bytes()),
+        base64::URL_SAFE_NO_PAD,
+    ));
+    // Headers carry dates with a one second resolution.
+    let last_modified = last_modified.map(|t| HttpDate::from(SystemTime::from(t.trunc_subsecs(0))));
+    let is_unchanged = match request.get_header::<IfNoneMatch>() {
+        Some(IfNoneMatch::Any) => true,
+        Some(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(&etag)),
+        None => match (request.get_header::<IfModifiedSince>(), last_modified) {
+            (Some(IfModifiedSince(since)), Some(last_modified)) => {
+                SystemTime::from(last_modified) <= SystemTime::from(since)
+            }
+            _ => false,
+        },
+    };
+    let mut response = if is_unchanged {
+        HttpResponse::NotModified()
+    } else {
+        HttpResponse::Ok()
+    };
+    response.insert_header(ETag(etag));
+    if let Some(last_modified) = last_modified {
+        response.insert_header(LastModified(last_modified));
+    }
+    if is_unchanged {
+        response.finish()
+    } else {
+        response.content_type(content_type).body(body)
+    }
+}
+
+struct FeedEntry {
+    newsletter_issue_id: Uuid,
+    title: String,
+    slug: String,
+    html_content: String,
+    published_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+struct Feed {
+    entries: Vec<FeedEntry>,
+    /// When an issue last entered, left or changed in the feed.
+    last_modified: Option<DateTime<Utc>>,
+}
+
+#[tracing::instrument(name = "Get the newsletter feed", skip(pool))]
+async fn get_feed(pool: &PgPool) -> Result<Feed, anyhow::Error> {
+    let entries = sqlx::query_as!(
+        FeedEntry,
+        r#"
+        SELECT
+            newsletter_issue_id,
+            title,
+            slug AS "slug!",
+            html_content,
+            published_at AS "published_at!",
+            updated_at
+        FROM newsletter_issues
+        WHERE slug IS NOT NULL AND NOT is_private
+        ORDER BY published_at DESC
+        LIMIT $1
+        "#,
+        MAX_FEED_ENTRIES
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to perform a query to retrieve the feed entries.")?;
+    // Private issues count too: hiding one changes the feed.
+    let last_modified = sqlx::query!(
+        r#"
+        SELECT max(updated_at) AS last_modified
+        FROM newsletter_issues
+        WHERE slug IS NOT NULL
+        "#
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to perform a query to retrieve when the feed last changed.")?
+    .last_modified;
+    Ok(Feed {
+        entries,
+        last_modified,
+    })
+}
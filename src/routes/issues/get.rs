@@ -0,0 +1,74 @@
+use super::archive::archived_html_content;
+use crate::startup::ApplicationBaseUrl;
+use crate::utils;
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use htmlescape::encode_minimal;
+use sqlx::PgPool;
+
+/// The archived copy of an issue, as subscribers received it minus the
+/// per-subscriber details.
+pub async fn published_issue(
+    slug: web::Path<String>,
+    pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> Result<HttpResponse, actix_web::Error> {
+    // Drafts, scheduled and private issues are not found, as far as readers can tell.
+    let issue = match get_published_issue(&pool, &slug)
+        .await
+        .map_err(utils::e500)?
+    {
+        Some(issue) => issue,
+        None => return Ok(HttpResponse::NotFound().finish()),
+    };
+    let html_content = archived_html_content(issue.html_content, &base_url.0, &slug);
+    let title = encode_minimal(&issue.title);
+    let published_at = issue.published_at.format("%Y-%m-%d");
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>{title}</title>
+</head>
+    <body>
+        <h1>{title}</h1>
+        <p>Published on {published_at}</p>
+        <div>{html_content}</div>
+        <p><a href="/issues">&lt;-All issues</a></p>
+    </body>
+</html>"#,
+        )))
+}
+
+struct PublishedIssue {
+    title: String,
+    html_content: String,
+    published_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(name = "Get a published newsletter issue", skip(pool))]
+async fn get_published_issue(
+    pool: &PgPool,
+    slug: &str,
+) -> Result<Option<PublishedIssue>, anyhow::Error> {
+    sqlx::query_as!(
+        PublishedIssue,
+        r#"
+        SELECT
+            title,
+            html_content,
+            published_at AS "published_at!"
+        FROM newsletter_issues
+        WHERE slug = $1 AND NOT is_private
+        "#,
+        slug
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to perform a query to retrieve a published newsletter issue.")
+}
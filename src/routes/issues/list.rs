@@ -0,0 +1,113 @@
+use crate::utils;
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use htmlescape::encode_minimal;
+use sqlx::PgPool;
+use std::fmt::Write;
+
+const ISSUES_PER_PAGE: i64 = 10;
+
+#[derive(serde::Deserialize)]
+pub struct PageQuery {
+    page: Option<i64>,
+}
+
+/// The public archive: every published issue that is not private, newest first.
+pub async fn list_published_issues(
+    query: web::Query<PageQuery>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let page = query.page.unwrap_or(1).max(1);
+    let offset = (page - 1).saturating_mul(ISSUES_PER_PAGE);
+    // One extra row tells us whether there is an older page.
+    let mut issues = get_published_issues(&pool, ISSUES_PER_PAGE + 1, offset)
+        .await
+        .map_err(utils::e500)?;
+    let has_older_page = issues.len() as i64 > ISSUES_PER_PAGE;
+    issues.truncate(ISSUES_PER_PAGE as usize);
+    let mut issues_html = String::new();
+    for issue in &issues {
+        writeln!(
+            issues_html,
+            r#"<li><a href="/issues/{}">{}</a> - {}</li>"#,
+            encode_minimal(&issue.slug),
+            encode_minimal(&issue.title),
+            issue.published_at.format("%Y-%m-%d")
+        )
+        .unwrap();
+    }
+    if issues.is_empty() {
+        issues_html.push_str("<li>No issues here.</li>");
+    }
+    let mut pages_html = String::new();
+    if page > 1 {
+        write!(
+            pages_html,
+            r#"<a href="/issues?page={}">Newer issues</a> "#,
+            page - 1
+        )
+        .unwrap();
+    }
+    if has_older_page {
+        write!(
+            pages_html,
+            r#"<a href="/issues?page={}">Older issues</a>"#,
+            page + 1
+        )
+        .unwrap();
+    }
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Newsletter archive</title>
+</head>
+    <body>
+        <h1>Newsletter archive</h1>
+        <ul>
+            {issues_html}
+        </ul>
+        <p>{pages_html}</p>
+        <p><a href="/">Subscribe</a></p>
+    </body>
+</html>"#,
+        )))
+}
+
+struct PublishedIssueSummary {
+    title: String,
+    slug: String,
+    published_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(name = "Get published newsletter issues", skip(pool))]
+async fn get_published_issues(
+    pool: &PgPool,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<PublishedIssueSummary>, anyhow::Error> {
+    sqlx::query_as!(
+        PublishedIssueSummary,
+        r#"
+        SELECT
+            title,
+            slug AS "slug!",
+            published_at AS "published_at!"
+        FROM newsletter_issues
+        WHERE slug IS NOT NULL AND NOT is_private
+        ORDER BY published_at DESC
+        LIMIT $1
+        OFFSET $2
+        "#,
+        limit,
+        offset
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to perform a query to retrieve published newsletter issues.")
+}
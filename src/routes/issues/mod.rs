@@ -0,0 +1,8 @@
+mod archive;
+mod feed;
+mod get;
+mod list;
+
+pub use feed::{atom_feed, rss_feed};
+pub use get::published_issue;
+pub use list::list_published_issues;
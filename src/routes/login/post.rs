@@ -32,11 +32,21 @@ pub async fn login(
         Ok(user_id) => {
             tracing::Span::current().record("user_id", &tracing::field::display(&user_id));
             session.renew();
-            session
-                .insert_user_id(user_id)
-                .map_err(|e| login_redirect(LoginError::UnexpectedError(e.into())))?;
+            let totp_secret = authentication::get_totp_secret(user_id, &pool)
+                .await
+                .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?;
+            // With two-factor authentication on, the password alone does not
+            // log the user in: they still have to enter a code.
+            let (result, location) = match totp_secret {
+                Some(_) => (
+                    session.insert_pending_two_factor_user_id(user_id),
+                    "/login/two_factor",
+                ),
+                None => (session.insert_user_id(user_id), "/admin/dashboard"),
+            };
+            result.map_err(|e| login_redirect(LoginError::UnexpectedError(e.into())))?;
             Ok(HttpResponse::SeeOther()
-                .insert_header((LOCATION, "/admin/dashboard"))
+                .insert_header((LOCATION, location))
                 .finish())
         }
         Err(e) => {
@@ -0,0 +1,97 @@
+use crate::authentication::{self, SecondFactorOutcome};
+use crate::session_state::TypedSession;
+use crate::utils;
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use htmlescape::encode_minimal;
+use secrecy::Secret;
+use sqlx::PgPool;
+use std::fmt::Write;
+
+#[derive(serde::Deserialize)]
+pub struct TwoFactorFormData {
+    code: Secret<String>,
+}
+
+pub async fn two_factor_form(
+    session: TypedSession,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session
+        .get_pending_two_factor_user_id()
+        .map_err(utils::e500)?
+        .is_none()
+    {
+        return Ok(utils::see_other("/login"));
+    }
+    let mut error_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(error_html, "<p><i>{}</i></p>", encode_minimal(m.content())).unwrap();
+    }
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta http-equiv="content-type" content="text/html; charset=utf-8">
+<title>Two-factor authentication</title>
+</head>
+<body>
+{error_html}
+<p>Enter the code shown by your authenticator app, or one of your recovery codes.</p>
+<form action="/login/two_factor" method="post">
+<label>Code
+<input
+type="text"
+placeholder="Enter code"
+name="code"
+autocomplete="one-time-code"
+>
+</label>
+<button type="submit">Verify</button>
+</form>
+</body>
+</html>"#,
+        )))
+}
+
+#[tracing::instrument(
+    skip(form, pool, session),
+    fields(user_id=tracing::field::Empty)
+)]
+pub async fn verify_two_factor(
+    form: web::Form<TwoFactorFormData>,
+    pool: web::Data<PgPool>,
+    session: TypedSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = match session
+        .get_pending_two_factor_user_id()
+        .map_err(utils::e500)?
+    {
+        Some(user_id) => user_id,
+        None => return Ok(utils::see_other("/login")),
+    };
+    tracing::Span::current().record("user_id", &tracing::field::display(&user_id));
+
+    match authentication::verify_second_factor(user_id, &form.0.code, &pool)
+        .await
+        .map_err(utils::e500)?
+    {
+        SecondFactorOutcome::Valid => {
+            session.renew();
+            session.remove_pending_two_factor_user_id();
+            session.insert_user_id(user_id).map_err(utils::e500)?;
+            Ok(utils::see_other("/admin/dashboard"))
+        }
+        SecondFactorOutcome::Invalid => {
+            FlashMessage::error("The code is invalid.").send();
+            Ok(utils::see_other("/login/two_factor"))
+        }
+        SecondFactorOutcome::LockedOut => {
+            session.log_out();
+            FlashMessage::error("Too many invalid codes: please try again later.").send();
+            Ok(utils::see_other("/login"))
+        }
+    }
+}
@@ -1,13 +1,23 @@
 mod admin;
+mod email_events;
 mod health_check;
 mod home;
+mod issues;
 mod login;
+mod resend_confirmation;
+mod set_password;
 mod subscriptions;
 mod subscriptions_confirm;
+mod unsubscribe;
 
 pub use admin::*;
+pub use email_events::*;
 pub use health_check::*;
 pub use home::*;
+pub use issues::*;
 pub use login::*;
+pub use resend_confirmation::*;
+pub use set_password::*;
 pub use subscriptions::*;
 pub use subscriptions_confirm::*;
+pub use unsubscribe::*;
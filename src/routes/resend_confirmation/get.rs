@@ -0,0 +1,23 @@
+use actix_web::http::header::ContentType;
+use actix_web::HttpResponse;
+
+pub async fn resend_confirmation_form() -> HttpResponse {
+    HttpResponse::Ok().content_type(ContentType::html()).body(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Resend confirmation</title>
+</head>
+    <body>
+        <p>Did not get the email to confirm your subscription? We can send a new one.</p>
+        <form action="/subscriptions/resend_confirmation" method="post">
+            <label>Email
+                <input type="text" placeholder="Enter the address you subscribed with" name="email">
+            </label>
+            <button type="submit">Resend confirmation</button>
+        </form>
+    </body>
+</html>"#,
+    )
+}
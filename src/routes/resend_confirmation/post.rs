@@ -0,0 +1,186 @@
+use crate::configuration::SubscriptionsSettings;
+use crate::domain::SubscriberEmail;
+use crate::routes::{
+    enqueue_confirmation_email, generate_subscription_token, revoke_tokens, store_token,
+};
+use crate::startup::ApplicationBaseUrl;
+use crate::utils;
+use actix_web::http::header::ContentType;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, HttpResponse};
+use anyhow::Context;
+use sqlx::{PgPool, Postgres, Transaction};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    email: String,
+}
+
+/// Send a new confirmation email to a subscriber who is still pending.
+///
+/// Known, unknown and already confirmed addresses all get the same response:
+/// only the owner of the address finds out, by getting the email or not.
+#[tracing::instrument(
+    name = "Resend a confirmation email",
+    skip(request, form, pool, base_url, settings),
+    fields(subscriber_email = %form.email)
+)]
+pub async fn resend_confirmation(
+    request: HttpRequest,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    settings: web::Data<SubscriptionsSettings>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let email = SubscriberEmail::parse(form.0.email).map_err(utils::e400)?;
+    // Not `realip_remote_addr`: clients are free to send whatever
+    // `X-Forwarded-For` header they like.
+    let ip_address = request
+        .peer_addr()
+        .map(|address| address.ip().to_string())
+        .unwrap_or_else(|| "unknown".into());
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")
+        .map_err(utils::e500)?;
+    let is_allowed = record_attempt(&mut transaction, email.as_ref(), &ip_address, &settings)
+        .await
+        .context("Failed to record the attempt to resend a confirmation email.")
+        .map_err(utils::e500)?;
+    if !is_allowed {
+        transaction
+            .commit()
+            .await
+            .context("Failed to commit SQL transaction to resend a confirmation email.")
+            .map_err(utils::e500)?;
+        return Ok(page(
+            StatusCode::TOO_MANY_REQUESTS,
+            "<p>You asked for too many confirmation emails. Try again in an hour.</p>",
+        ));
+    }
+    let pending_subscriber_id = get_pending_subscriber_id(&mut transaction, email.as_ref())
+        .await
+        .context("Failed to look up the pending subscriber.")
+        .map_err(utils::e500)?;
+    if let Some(subscriber_id) = pending_subscriber_id {
+        let subscription_token = generate_subscription_token();
+        revoke_tokens(&mut transaction, subscriber_id)
+            .await
+            .context("Failed to revoke the previous subscription tokens.")
+            .map_err(utils::e500)?;
+        store_token(&mut transaction, subscriber_id, &subscription_token)
+            .await
+            .context("Failed to store the new subscription token.")
+            .map_err(utils::e500)?;
+        enqueue_confirmation_email(&mut transaction, &email, &base_url.0, &subscription_token)
+            .await
+            .context("Failed to enqueue a new confirmation email.")
+            .map_err(utils::e500)?;
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to resend a confirmation email.")
+        .map_err(utils::e500)?;
+    Ok(page(
+        StatusCode::OK,
+        "<p>If this address is waiting to be confirmed, \
+        a new confirmation email is on its way.</p>",
+    ))
+}
+
+fn page(status: StatusCode, body_html: &str) -> HttpResponse {
+    HttpResponse::build(status)
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Resend confirmation</title>
+</head>
+    <body>
+        {body_html}
+    </body>
+</html>"#
+        ))
+}
+
+/// Returns `false`, without recording anything, if the address or the client
+/// already asked for too many confirmation emails in the last hour.
+///
+/// Attempts for the same address, or from the same client, are serialised
+/// until the transaction ends: concurrent requests would otherwise all see
+/// the same count and all get through.
+#[tracing::instrument(skip(transaction, settings))]
+async fn record_attempt(
+    transaction: &mut Transaction<'_, Postgres>,
+    email: &str,
+    ip_address: &str,
+    settings: &SubscriptionsSettings,
+) -> Result<bool, sqlx::Error> {
+    // Always in the same order, so that two requests cannot deadlock.
+    for (lock_namespace, key) in [(1, email), (2, ip_address)] {
+        sqlx::query("SELECT pg_advisory_xact_lock($1, hashtext($2))")
+            .bind(lock_namespace)
+            .bind(key)
+            .execute(&mut *transaction)
+            .await?;
+    }
+    sqlx::query!(
+        r#"
+        DELETE FROM confirmation_resend_attempts
+        WHERE attempted_at < now() - interval '1 hour'
+        "#
+    )
+    .execute(&mut *transaction)
+    .await?;
+    let attempts = sqlx::query!(
+        r#"
+        SELECT
+            count(*) FILTER (WHERE email = $1) AS "by_address!",
+            count(*) FILTER (WHERE ip_address = $2) AS "by_ip_address!"
+        FROM confirmation_resend_attempts
+        "#,
+        email,
+        ip_address
+    )
+    .fetch_one(&mut *transaction)
+    .await?;
+    if attempts.by_address >= settings.max_resends_per_address_per_hour
+        || attempts.by_ip_address >= settings.max_resends_per_ip_per_hour
+    {
+        return Ok(false);
+    }
+    sqlx::query!(
+        r#"
+        INSERT INTO confirmation_resend_attempts (email, ip_address, attempted_at)
+        VALUES ($1, $2, now())
+        "#,
+        email,
+        ip_address
+    )
+    .execute(&mut *transaction)
+    .await?;
+    Ok(true)
+}
+
+#[tracing::instrument(skip(transaction))]
+async fn get_pending_subscriber_id(
+    transaction: &mut Transaction<'_, Postgres>,
+    email: &str,
+) -> Result<Option<uuid::Uuid>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT id
+        FROM subscriptions
+        WHERE email = $1 AND status = 'pending_confirmation'
+        FOR UPDATE
+        "#,
+        email
+    )
+    .fetch_optional(transaction)
+    .await?;
+    Ok(row.map(|r| r.id))
+}
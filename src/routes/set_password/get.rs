@@ -0,0 +1,89 @@
+use crate::authentication::get_set_password_token;
+use crate::utils;
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use htmlescape::{encode_attribute, encode_minimal};
+use sqlx::PgPool;
+use std::fmt::Write;
+
+#[derive(serde::Deserialize)]
+pub struct SetPasswordParameters {
+    token: String,
+}
+
+/// Where invited users, and users asked to reset their password, choose one.
+pub async fn set_password_form(
+    parameters: web::Query<SetPasswordParameters>,
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, actix_web::Error> {
+    let token = get_set_password_token(pool.get_ref(), &parameters.token)
+        .await
+        .context("Failed to retrieve the set-password token.")
+        .map_err(utils::e500)?;
+    let token = match token {
+        Some(token) => token,
+        None => return Ok(invalid_link_page()),
+    };
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", encode_minimal(m.content())).unwrap();
+    }
+    let username = encode_minimal(&token.username);
+    let token = encode_attribute(&parameters.token);
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Choose your password</title>
+</head>
+    <body>
+        {msg_html}
+        <p>Choose the password you will log in with as {username}.</p>
+        <form action="/users/set_password" method="post">
+            <input type="hidden" name="token" value="{token}">
+            <label>Password
+                <input
+                    type="password"
+                    placeholder="Enter your new password"
+                    name="password"
+                >
+            </label>
+            <br>
+            <label>
+                <input
+                    type="password"
+                    placeholder="Type the password again"
+                    name="password_check"
+                >
+            </label>
+            <br>
+            <button type="submit">Set password</button>
+        </form>
+    </body>
+</html>"#,
+        )))
+}
+
+pub fn invalid_link_page() -> HttpResponse {
+    HttpResponse::Unauthorized()
+        .content_type(ContentType::html())
+        .body(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Invalid link</title>
+</head>
+    <body>
+        <p>This link is not valid anymore: it has expired or it was used already.
+        Ask an admin to send you a new one.</p>
+    </body>
+</html>"#,
+        )
+}
@@ -0,0 +1,59 @@
+use super::get::invalid_link_page;
+use crate::authentication::{get_set_password_token, set_password_with_token};
+use crate::utils;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+use validator::HasLen;
+
+#[derive(serde::Deserialize)]
+pub struct SetPasswordFormData {
+    token: String,
+    password: Secret<String>,
+    password_check: Secret<String>,
+}
+
+#[tracing::instrument(name = "Set a password with an emailed link", skip(form, pool))]
+pub async fn set_password(
+    form: web::Form<SetPasswordFormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let form = form.into_inner();
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")
+        .map_err(utils::e500)?;
+    let token = get_set_password_token(&mut transaction, &form.token)
+        .await
+        .context("Failed to retrieve the set-password token.")
+        .map_err(utils::e500)?;
+    let token = match token {
+        Some(token) => token,
+        None => return Ok(invalid_link_page()),
+    };
+    // Tokens we hand out are alphanumeric: they can go in the URL as they are.
+    let form_location = format!("/users/set_password?token={}", form.token);
+    if form.password.expose_secret() != form.password_check.expose_secret() {
+        FlashMessage::error("You entered two different passwords - the field values must match.")
+            .send();
+        return Ok(utils::see_other(&form_location));
+    }
+    if !(13..=127).contains(&form.password.expose_secret().length()) {
+        FlashMessage::error("The password must contain at least 13 and shorten then 128 chars.")
+            .send();
+        return Ok(utils::see_other(&form_location));
+    }
+    set_password_with_token(&mut transaction, &form.token, token.user_id, form.password)
+        .await
+        .map_err(utils::e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to set a password.")
+        .map_err(utils::e500)?;
+    FlashMessage::info("Your password has been set: you can now log in.").send();
+    Ok(utils::see_other("/login"))
+}
@@ -1,6 +1,7 @@
 use crate::domain::{NewSubscriber, SubscriberEmail, SubscriberName};
-use crate::email_client::EmailClient;
+use crate::email_outbox::{enqueue_email, OutgoingEmail};
 use crate::startup::ApplicationBaseUrl;
+use crate::suppression::is_suppressed;
 use actix_web::http::StatusCode;
 use actix_web::ResponseError;
 use actix_web::{web, HttpResponse};
@@ -17,7 +18,7 @@ pub struct FormData {
     name: String,
 }
 
-fn generate_subscription_token() -> String {
+pub fn generate_subscription_token() -> String {
     let mut rng = thread_rng();
     std::iter::repeat_with(|| rng.sample(Alphanumeric))
         .map(char::from)
@@ -27,7 +28,7 @@ fn generate_subscription_token() -> String {
 
 #[tracing::instrument(
     name = "Adding a new subscriber",
-    skip(form, pool, email_client, base_url),
+    skip(form, pool, base_url),
     fields(
         subscriber_email = %form.email,
         subscriber_name= %form.name
@@ -36,41 +37,121 @@ fn generate_subscription_token() -> String {
 pub async fn subscribe(
     form: web::Form<FormData>,
     pool: web::Data<PgPool>,
-    email_client: web::Data<EmailClient>,
     base_url: web::Data<ApplicationBaseUrl>,
 ) -> Result<HttpResponse, SubscribeError> {
-    let new_subscriber = form.0.try_into().map_err(SubscribeError::ValidationError)?;
+    let new_subscriber: NewSubscriber =
+        form.0.try_into().map_err(SubscribeError::ValidationError)?;
     let mut transaction = pool
         .begin()
         .await
         .context("Failed to acquire a Postgres connection from the pool.")?;
-    let subscriber_id = insert_subscriber(&mut transaction, &new_subscriber)
+    // The outbox would drop the confirmation email: better say so than
+    // leave the user waiting for it.
+    if is_suppressed(&mut transaction, new_subscriber.email.as_ref())
         .await
-        .context("Failed to insert new subscriber in the database.")?;
-    let subscription_token = generate_subscription_token();
-    store_token(&mut transaction, subscriber_id, &subscription_token)
+        .context("Failed to check the suppression list.")?
+    {
+        return Err(SubscribeError::Suppressed);
+    }
+    let registration = register_subscriber(&mut transaction, &new_subscriber)
         .await
-        .context("Failed to store the confirmation token for a new subscriber.")?;
+        .context("Failed to register the subscriber in the database.")?;
+    // Whatever else we know about the address, the response is the same:
+    // only its owner finds out, via email.
+    match registration {
+        Registration::PendingConfirmation(subscriber_id) => {
+            let subscription_token = generate_subscription_token();
+            store_token(&mut transaction, subscriber_id, &subscription_token)
+                .await
+                .context("Failed to store the confirmation token for a new subscriber.")?;
+            enqueue_confirmation_email(
+                &mut transaction,
+                &new_subscriber.email,
+                &base_url.0,
+                &subscription_token,
+            )
+            .await
+            .context("Failed to enqueue a confirmation email.")?;
+        }
+        Registration::AlreadyConfirmed => {
+            enqueue_already_subscribed_email(&mut transaction, &new_subscriber.email, &base_url.0)
+                .await
+                .context("Failed to enqueue an already subscribed email.")?;
+        }
+    }
+    // The delivery worker picks the email up once this commits, so an outage
+    // of the email provider cannot fail the subscription.
     transaction
         .commit()
         .await
         .context("Failed to commit SQL transaction to store a new subscriber.")?;
-    send_confirmation_email(
-        &email_client,
-        new_subscriber,
-        &base_url.0,
-        &subscription_token,
-    )
-    .await
-    .context("Failed to send a confirmation email.")?;
 
     Ok(HttpResponse::Ok().finish())
 }
 
+/// What submitting the subscription form means for the address it carries.
+pub enum Registration {
+    /// A new subscriber, or a known one (re)starting the opt-in.
+    PendingConfirmation(Uuid),
+    AlreadyConfirmed,
+}
+
+/// Pending subscribers get their tokens rotated, so only the latest
+/// confirmation email works; unsubscribed (or suppressed) ones start a new
+/// opt-in under the same id.
+#[tracing::instrument(name = "Register a subscriber", skip(new_subscriber, transaction))]
+pub async fn register_subscriber(
+    transaction: &mut Transaction<'_, Postgres>,
+    new_subscriber: &NewSubscriber,
+) -> Result<Registration, sqlx::Error> {
+    if let Some(subscriber_id) = insert_subscriber(transaction, new_subscriber).await? {
+        return Ok(Registration::PendingConfirmation(subscriber_id));
+    }
+    let existing = sqlx::query!(
+        r#"SELECT id, status FROM subscriptions WHERE email = $1 FOR UPDATE"#,
+        new_subscriber.email.as_ref()
+    )
+    .fetch_one(&mut *transaction)
+    .await?;
+    if existing.status == "confirmed" {
+        return Ok(Registration::AlreadyConfirmed);
+    }
+    sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET name = $2, status = 'pending_confirmation', unsubscribed_at = NULL
+        WHERE id = $1
+        "#,
+        existing.id,
+        new_subscriber.name.as_ref()
+    )
+    .execute(&mut *transaction)
+    .await?;
+    revoke_tokens(transaction, existing.id).await?;
+    Ok(Registration::PendingConfirmation(existing.id))
+}
+
+/// Invalidate the links in all the confirmation emails sent so far.
+#[tracing::instrument(name = "Revoke subscription tokens", skip(transaction))]
+pub async fn revoke_tokens(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"DELETE FROM subscription_tokens WHERE subscriber_id = $1"#,
+        subscriber_id
+    )
+    .execute(transaction)
+    .await?;
+    Ok(())
+}
+
 #[derive(thiserror::Error)]
 pub enum SubscribeError {
     #[error("{0}")]
     ValidationError(String),
+    #[error("We cannot send emails to this address: it bounced or reported our emails as spam.")]
+    Suppressed,
     #[error(transparent)]
     UnexpectedError(#[from] anyhow::Error),
 }
@@ -85,6 +166,7 @@ impl ResponseError for SubscribeError {
     fn status_code(&self) -> StatusCode {
         match self {
             SubscribeError::ValidationError(_) => StatusCode::BAD_REQUEST,
+            SubscribeError::Suppressed => StatusCode::CONFLICT,
             SubscribeError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -137,7 +219,10 @@ pub async fn store_token(
     subscription_token: &str,
 ) -> Result<(), StoreTokenError> {
     sqlx::query!(
-        r#"INSERT INTO subscription_tokens (subscription_token, subscriber_id) VALUES ($1, $2)"#,
+        r#"
+        INSERT INTO subscription_tokens (subscription_token, subscriber_id, created_at)
+        VALUES ($1, $2, now())
+        "#,
         subscription_token,
         subscriber_id
     )
@@ -148,15 +233,15 @@ pub async fn store_token(
 }
 
 #[tracing::instrument(
-    name = "Send a conformation email to a new subscriber",
-    skip(email_client, new_subscriber, base_url, subscription_token)
+    name = "Enqueue a confirmation email to a new subscriber",
+    skip(transaction, base_url, subscription_token)
 )]
-pub async fn send_confirmation_email(
-    email_client: &EmailClient,
-    new_subscriber: NewSubscriber,
+pub async fn enqueue_confirmation_email(
+    transaction: &mut Transaction<'_, Postgres>,
+    recipient: &SubscriberEmail,
     base_url: &str,
     subscription_token: &str,
-) -> Result<(), reqwest::Error> {
+) -> Result<(), sqlx::Error> {
     let confirmation_link = format!(
         "{}/subscriptions/confirm?subscription_token={}",
         base_url, subscription_token
@@ -170,11 +255,51 @@ pub async fn send_confirmation_email(
 Click <a href=\"{}\">here</a> to confirm your subscription.",
         confirmation_link
     );
-    email_client
-        .send_email(&new_subscriber.email, "Welcome!", &html_body, &plain_body)
-        .await
+    enqueue_email(
+        transaction,
+        OutgoingEmail {
+            recipient,
+            subject: "Welcome!",
+            html_body: &html_body,
+            text_body: &plain_body,
+        },
+    )
+    .await
 }
 
+#[tracing::instrument(
+    name = "Enqueue an already subscribed email",
+    skip(transaction, base_url)
+)]
+pub async fn enqueue_already_subscribed_email(
+    transaction: &mut Transaction<'_, Postgres>,
+    recipient: &SubscriberEmail,
+    base_url: &str,
+) -> Result<(), sqlx::Error> {
+    let plain_body = format!(
+        "You are already subscribed to our newsletter: there is nothing else to do.\n\
+If you did not ask to subscribe again, you can ignore this email.\n{}",
+        base_url
+    );
+    let html_body = format!(
+        "You are already subscribed to our <a href=\"{}\">newsletter</a>: \
+there is nothing else to do.<br />\
+If you did not ask to subscribe again, you can ignore this email.",
+        base_url
+    );
+    enqueue_email(
+        transaction,
+        OutgoingEmail {
+            recipient,
+            subject: "You are already subscribed",
+            html_body: &html_body,
+            text_body: &plain_body,
+        },
+    )
+    .await
+}
+
+/// Returns `None` if the email belongs to a known subscriber already.
 #[tracing::instrument(
     name = "Saving new subscriber details in the database",
     skip(new_subscriber, transaction)
@@ -182,12 +307,13 @@ Click <a href=\"{}\">here</a> to confirm your subscription.",
 pub async fn insert_subscriber(
     transaction: &mut Transaction<'_, Postgres>,
     new_subscriber: &NewSubscriber,
-) -> Result<Uuid, sqlx::Error> {
+) -> Result<Option<Uuid>, sqlx::Error> {
     let subscriber_id = Uuid::new_v4();
-    sqlx::query!(
+    let n_inserted_rows = sqlx::query!(
         r#"
 INSERT INTO subscriptions (id, email, name, subscribed_at, status)
 VALUES ($1, $2, $3, $4, 'pending_confirmation')
+ON CONFLICT (email) DO NOTHING
 "#,
         subscriber_id,
         new_subscriber.email.as_ref(),
@@ -195,8 +321,12 @@ VALUES ($1, $2, $3, $4, 'pending_confirmation')
         Utc::now()
     )
     .execute(transaction)
-    .await?;
-    Ok(subscriber_id)
+    .await?
+    .rows_affected();
+    if n_inserted_rows == 0 {
+        return Ok(None);
+    }
+    Ok(Some(subscriber_id))
 }
 
 impl TryFrom<FormData> for NewSubscriber {
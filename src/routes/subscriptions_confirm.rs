@@ -1,4 +1,11 @@
+use crate::configuration::SubscriptionsSettings;
+use crate::utils;
+use actix_web::http::header::ContentType;
+use actix_web::http::StatusCode;
 use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use htmlescape::encode_attribute;
 use sqlx::{PgPool, Postgres, Transaction};
 use uuid::Uuid;
 
@@ -7,35 +14,115 @@ pub struct Parameters {
     subscription_token: String,
 }
 
-#[tracing::instrument(name = "Confirm a pending subscriber", skip(parameters, pool))]
-pub async fn confirm(parameters: web::Query<Parameters>, pool: web::Data<PgPool>) -> HttpResponse {
-    let mut transaction = match pool.begin().await {
-        Ok(transaction) => transaction,
-        Err(_) => return HttpResponse::InternalServerError().finish(),
+/// A confirmation token works once, and only for
+/// `confirmation_token_ttl_hours` after it was sent.
+#[tracing::instrument(
+    name = "Confirm a pending subscriber",
+    skip(parameters, pool, settings)
+)]
+pub async fn confirm(
+    parameters: web::Query<Parameters>,
+    pool: web::Data<PgPool>,
+    settings: web::Data<SubscriptionsSettings>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")
+        .map_err(utils::e500)?;
+    let token = get_token(&mut transaction, &parameters.subscription_token)
+        .await
+        .context("Failed to retrieve the subscription token.")
+        .map_err(utils::e500)?;
+    let token = match token {
+        Some(token) => token,
+        None => return Ok(invalid_token_page()),
     };
-    let id = match get_subscriber_id_from_token(&mut transaction, &parameters.subscription_token)
+    if token.subscriber_status == "confirmed" {
+        return Ok(already_confirmed_page());
+    }
+    if token.consumed_at.is_some() {
+        // Used already, by a subscriber who has since unsubscribed.
+        return Ok(invalid_token_page());
+    }
+    if token.created_at + settings.confirmation_token_ttl() < Utc::now() {
+        return Ok(expired_token_page(&token.subscriber_email));
+    }
+    if !confirm_subscriber(&mut transaction, token.subscriber_id)
         .await
+        .context("Failed to mark the subscriber as confirmed.")
+        .map_err(utils::e500)?
     {
-        Ok(id) => id,
-        Err(_) => return HttpResponse::InternalServerError().finish(),
-    };
-    match id {
-        None => HttpResponse::Unauthorized().finish(),
-        Some(subscriber_id) => {
-            if confirm_subscriber(&mut transaction, subscriber_id)
-                .await
-                .is_err()
-            {
-                return HttpResponse::InternalServerError().finish();
-            }
-            if transaction.commit().await.is_err() {
-                return HttpResponse::InternalServerError().finish();
-            }
-            HttpResponse::Ok().finish()
-        }
+        // The subscriber has unsubscribed (or been suppressed) since this
+        // email went out: the link must not count as used.
+        return Ok(invalid_token_page());
     }
+    consume_token(&mut transaction, &parameters.subscription_token)
+        .await
+        .context("Failed to mark the subscription token as used.")
+        .map_err(utils::e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to confirm a subscriber.")
+        .map_err(utils::e500)?;
+    Ok(page(
+        StatusCode::OK,
+        "Subscription confirmed",
+        "<p>Thanks for confirming your subscription! The next issue will land in your inbox.</p>",
+    ))
+}
+
+fn already_confirmed_page() -> HttpResponse {
+    page(
+        StatusCode::OK,
+        "Subscription confirmed",
+        "<p>Your subscription is already confirmed: there is nothing else to do.</p>",
+    )
+}
+
+fn invalid_token_page() -> HttpResponse {
+    page(
+        StatusCode::UNAUTHORIZED,
+        "Invalid confirmation link",
+        "<p>This confirmation link is not valid. \
+        Make sure you used the link in the latest email we sent you.</p>",
+    )
+}
+
+fn expired_token_page(email: &str) -> HttpResponse {
+    page(
+        StatusCode::GONE,
+        "Confirmation link expired",
+        &format!(
+            r#"<p>This confirmation link has expired.</p>
+        <form action="/subscriptions/resend_confirmation" method="post">
+            <input type="hidden" name="email" value="{}">
+            <button type="submit">Resend confirmation</button>
+        </form>"#,
+            encode_attribute(email)
+        ),
+    )
 }
 
+fn page(status: StatusCode, title: &str, body_html: &str) -> HttpResponse {
+    HttpResponse::build(status)
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>{title}</title>
+</head>
+    <body>
+        {body_html}
+    </body>
+</html>"#
+        ))
+}
+
+/// Returns `false` if the subscriber is no longer pending confirmation.
 #[tracing::instrument(
     name = "Mark subscriber as confirmed",
     skip(subscriber_id, transaction)
@@ -43,37 +130,66 @@ pub async fn confirm(parameters: web::Query<Parameters>, pool: web::Data<PgPool>
 pub async fn confirm_subscriber(
     transaction: &mut Transaction<'_, Postgres>,
     subscriber_id: Uuid,
-) -> Result<(), sqlx::Error> {
-    sqlx::query!(
-        r#"UPDATE subscriptions SET status = 'confirmed' WHERE id = $1"#,
+) -> Result<bool, sqlx::Error> {
+    let n_updated_rows = sqlx::query!(
+        r#"
+        UPDATE subscriptions SET status = 'confirmed'
+        WHERE id = $1 AND status = 'pending_confirmation'
+        "#,
         subscriber_id
     )
     .execute(transaction)
-    .await
-    .map_err(|e| {
-        tracing::error!("Failed to execute query: {:?}", e);
-        e
-    })?;
-    Ok(())
+    .await?
+    .rows_affected();
+    Ok(n_updated_rows == 1)
 }
 
-#[tracing::instrument(
-    name = "Get subscriber_id from token",
-    skip(subscription_token, transaction)
-)]
-pub async fn get_subscriber_id_from_token(
+pub struct SubscriptionToken {
+    pub subscriber_id: Uuid,
+    pub subscriber_email: String,
+    pub subscriber_status: String,
+    pub created_at: DateTime<Utc>,
+    pub consumed_at: Option<DateTime<Utc>>,
+}
+
+#[tracing::instrument(name = "Get subscription token", skip(subscription_token, transaction))]
+pub async fn get_token(
     transaction: &mut Transaction<'_, Postgres>,
     subscription_token: &str,
-) -> Result<Option<Uuid>, sqlx::Error> {
-    let result = sqlx::query!(
-        r#"SELECT subscriber_id FROM subscription_tokens WHERE subscription_token = $1"#,
+) -> Result<Option<SubscriptionToken>, sqlx::Error> {
+    sqlx::query_as!(
+        SubscriptionToken,
+        r#"
+        SELECT
+            t.subscriber_id,
+            s.email AS subscriber_email,
+            s.status AS subscriber_status,
+            t.created_at,
+            t.consumed_at
+        FROM subscription_tokens t
+        JOIN subscriptions s ON s.id = t.subscriber_id
+        WHERE t.subscription_token = $1
+        FOR UPDATE
+        "#,
         subscription_token,
     )
     .fetch_optional(transaction)
     .await
-    .map_err(|e| {
-        tracing::error!("Failed to execute query: {:?}", e);
-        e
-    })?;
-    Ok(result.map(|r| r.subscriber_id))
+}
+
+#[tracing::instrument(name = "Consume subscription token", skip_all)]
+async fn consume_token(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscription_token: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE subscription_tokens SET consumed_at = now()
+        WHERE subscription_token = $1
+        "#,
+        subscription_token
+    )
+    .execute(transaction)
+    .await?;
+    Ok(())
 }
@@ -0,0 +1,89 @@
+use crate::domain::UnsubscribeToken;
+use crate::startup::HmacSecret;
+use crate::utils;
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Build the link a subscriber can follow (or a mail client can POST to)
+/// to stop receiving our emails.
+pub fn unsubscribe_link(base_url: &str, subscriber_id: Uuid, hmac_secret: &HmacSecret) -> String {
+    let token = UnsubscribeToken::generate(subscriber_id, &hmac_secret.0);
+    format!(
+        "{}/subscriptions/unsubscribe?subscriber_id={}&token={}",
+        base_url, subscriber_id, token
+    )
+}
+
+#[derive(serde::Deserialize)]
+pub struct Parameters {
+    pub subscriber_id: Uuid,
+    pub token: String,
+}
+
+impl Parameters {
+    pub fn is_valid(&self, hmac_secret: &HmacSecret) -> bool {
+        UnsubscribeToken::parse(self.token.clone(), self.subscriber_id, &hmac_secret.0).is_ok()
+    }
+}
+
+#[tracing::instrument(
+    name = "Show the unsubscribe page",
+    skip(parameters, pool, hmac_secret),
+    fields(subscriber_id = %parameters.subscriber_id)
+)]
+pub async fn unsubscribe_form(
+    parameters: web::Query<Parameters>,
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if !parameters.is_valid(&hmac_secret) {
+        return Ok(HttpResponse::Unauthorized().finish());
+    }
+    let status = get_subscription_status(&pool, parameters.subscriber_id)
+        .await
+        .map_err(utils::e500)?;
+    let content = match status.as_deref() {
+        None | Some("unsubscribed") => {
+            "<p>You are not subscribed to our newsletter anymore.</p>".to_string()
+        }
+        Some(_) => format!(
+            r#"<p>Do you want to stop receiving our newsletter?</p>
+        <form action="/subscriptions/unsubscribe?subscriber_id={}&token={}" method="post">
+            <button type="submit">Unsubscribe</button>
+        </form>"#,
+            parameters.subscriber_id, parameters.token
+        ),
+    };
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Unsubscribe</title>
+</head>
+    <body>
+        {content}
+    </body>
+</html>"#,
+        )))
+}
+
+#[tracing::instrument(name = "Get subscription status", skip(pool))]
+async fn get_subscription_status(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+) -> Result<Option<String>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"SELECT status FROM subscriptions WHERE id = $1"#,
+        subscriber_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to perform a query to retrieve the subscription status.")?;
+    Ok(row.map(|r| r.status))
+}
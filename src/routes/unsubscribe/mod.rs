@@ -0,0 +1,4 @@
+mod get;
+mod post;
+pub use get::{unsubscribe_form, unsubscribe_link};
+pub use post::unsubscribe;
@@ -0,0 +1,92 @@
+use super::get::Parameters;
+use crate::startup::HmacSecret;
+use crate::utils;
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+/// Unsubscribe a reader.
+///
+/// The subscriber id and the token are read from the query string rather than
+/// from the body: mail clients performing a one-click unsubscribe (RFC 8058)
+/// POST to the exact URL we gave them.
+#[tracing::instrument(
+    name = "Unsubscribe a subscriber",
+    skip(parameters, pool, hmac_secret),
+    fields(subscriber_id = %parameters.subscriber_id)
+)]
+pub async fn unsubscribe(
+    parameters: web::Query<Parameters>,
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if !parameters.is_valid(&hmac_secret) {
+        return Ok(HttpResponse::Unauthorized().finish());
+    }
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")
+        .map_err(utils::e500)?;
+    mark_subscriber_as_unsubscribed(&mut transaction, parameters.subscriber_id)
+        .await
+        .context("Failed to mark the subscriber as unsubscribed.")
+        .map_err(utils::e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to unsubscribe a subscriber.")
+        .map_err(utils::e500)?;
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Unsubscribe</title>
+</head>
+    <body>
+        <p>You have been unsubscribed. You will not receive any more emails from us.</p>
+    </body>
+</html>"#,
+    ))
+}
+
+#[tracing::instrument(name = "Mark subscriber as unsubscribed", skip(transaction))]
+async fn mark_subscriber_as_unsubscribed(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    let subscriber = sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET status = 'unsubscribed', unsubscribed_at = now()
+        WHERE id = $1 AND status <> 'unsubscribed'
+        RETURNING email
+        "#,
+        subscriber_id
+    )
+    .fetch_optional(&mut *transaction)
+    .await?;
+    if let Some(subscriber) = subscriber {
+        // Issues that are already being delivered must not reach them either.
+        sqlx::query!(
+            r#"DELETE FROM issue_delivery_queue WHERE subscriber_email = $1"#,
+            subscriber.email
+        )
+        .execute(&mut *transaction)
+        .await?;
+        sqlx::query!(
+            r#"
+            UPDATE issue_delivery_log
+            SET status = 'skipped_unsubscribed', updated_at = now()
+            WHERE subscriber_email = $1 AND status = 'queued'
+            "#,
+            subscriber.email
+        )
+        .execute(&mut *transaction)
+        .await?;
+    }
+    Ok(())
+}
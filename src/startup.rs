@@ -1,7 +1,10 @@
 use crate::authentication::reject_anonymous_users;
-use crate::configuration::{DatabaseSettings, Settings};
+use crate::configuration::{
+    DatabaseSettings, EmailEventsSettings, Settings, SubscriptionsSettings,
+};
 use crate::email_client::EmailClient;
 use crate::routes;
+use crate::shutdown::Shutdown;
 use actix_session::{storage::RedisSessionStore, SessionMiddleware};
 use actix_web::dev::Server;
 use actix_web::web::Data;
@@ -26,18 +29,10 @@ impl Application {
     pub async fn build(configuration: Settings) -> Result<Self, anyhow::Error> {
         let connection_pool = get_connection_pool(&configuration.database);
 
-        let sender_email = configuration
+        let email_client = configuration
             .email_client
-            .sender()
-            .expect("invalid sender email address.");
-
-        let timeout = configuration.email_client.timeout();
-        let email_client = EmailClient::new(
-            configuration.email_client.base_url,
-            sender_email,
-            configuration.email_client.authorization_token,
-            timeout,
-        );
+            .client()
+            .map_err(anyhow::Error::msg)?;
 
         let address = format!(
             "{}:{}",
@@ -53,6 +48,10 @@ impl Application {
             configuration.application.base_url,
             HmacSecret(configuration.application.hmac_secret),
             configuration.redis_uri,
+            RouteSettings {
+                email_events: configuration.email_events,
+                subscriptions: configuration.subscriptions,
+            },
         )
         .await?;
 
@@ -63,19 +62,38 @@ impl Application {
         self.port
     }
 
-    pub async fn run_until_stopped(self) -> Result<(), std::io::Error> {
-        self.server.await
+    /// Serve requests until `shutdown` is triggered, then stop accepting
+    /// connections and let in-flight requests complete.
+    pub async fn run_until_stopped(self, shutdown: Shutdown) -> Result<(), std::io::Error> {
+        let handle = self.server.handle();
+        let server = self.server;
+        tokio::pin!(server);
+        tokio::select! {
+            outcome = &mut server => return outcome,
+            _ = shutdown.triggered() => {}
+        }
+        tracing::info!("Shutting down the API: waiting for in-flight requests to complete.");
+        handle.stop(true).await;
+        server.await
     }
 }
 
 pub fn get_connection_pool(configuration: &DatabaseSettings) -> PgPool {
-    PgPoolOptions::new()
-        .acquire_timeout(std::time::Duration::from_secs(2))
-        .connect_lazy_with(configuration.with_db())
+    connection_pool_options().connect_lazy_with(configuration.with_db())
+}
+
+pub fn connection_pool_options() -> PgPoolOptions {
+    PgPoolOptions::new().acquire_timeout(std::time::Duration::from_secs(2))
 }
 
 pub struct ApplicationBaseUrl(pub String);
 
+/// Settings read by the request handlers, each available as `web::Data`.
+pub struct RouteSettings {
+    pub email_events: EmailEventsSettings,
+    pub subscriptions: SubscriptionsSettings,
+}
+
 pub async fn run(
     listener: TcpListener,
     db_pool: PgPool,
@@ -83,10 +101,14 @@ pub async fn run(
     base_url: String,
     hmac_secret: HmacSecret,
     redis_uri: Secret<String>,
+    route_settings: RouteSettings,
 ) -> Result<Server, anyhow::Error> {
     let db_pool = web::Data::new(db_pool);
     let email_client = web::Data::new(email_client);
     let base_url = Data::new(ApplicationBaseUrl(base_url));
+    let hmac_secret = Data::new(hmac_secret);
+    let email_events = Data::new(route_settings.email_events);
+    let subscriptions = Data::new(route_settings.subscriptions);
     let secret_key = Key::from(hmac_secret.0.expose_secret().as_bytes());
     let message_store = CookieMessageStore::builder(secret_key.clone()).build();
     let message_framework = FlashMessagesFramework::builder(message_store).build();
@@ -103,9 +125,43 @@ pub async fn run(
             .route("/health_check", web::get().to(routes::health_check))
             .route("/subscriptions", web::post().to(routes::subscribe))
             .route("/subscriptions/confirm", web::get().to(routes::confirm))
+            .route(
+                "/subscriptions/resend_confirmation",
+                web::get().to(routes::resend_confirmation_form),
+            )
+            .route(
+                "/subscriptions/resend_confirmation",
+                web::post().to(routes::resend_confirmation),
+            )
+            .route(
+                "/subscriptions/unsubscribe",
+                web::get().to(routes::unsubscribe_form),
+            )
+            .route(
+                "/subscriptions/unsubscribe",
+                web::post().to(routes::unsubscribe),
+            )
+            .route(
+                "/webhooks/email_events",
+                web::post().to(routes::receive_email_event),
+            )
+            .route("/feed.rss", web::get().to(routes::rss_feed))
+            .route("/feed.atom", web::get().to(routes::atom_feed))
+            .route("/issues", web::get().to(routes::list_published_issues))
+            .route("/issues/{slug}", web::get().to(routes::published_issue))
             .route("/", web::get().to(routes::home))
             .route("/login", web::get().to(routes::login_form))
             .route("/login", web::post().to(routes::login))
+            .route("/login/two_factor", web::get().to(routes::two_factor_form))
+            .route(
+                "/login/two_factor",
+                web::post().to(routes::verify_two_factor),
+            )
+            .route(
+                "/users/set_password",
+                web::get().to(routes::set_password_form),
+            )
+            .route("/users/set_password", web::post().to(routes::set_password))
             .service(
                 web::scope("/admin")
                     .wrap(from_fn(reject_anonymous_users))
@@ -113,13 +169,98 @@ pub async fn run(
                     .route("/password", web::get().to(routes::change_password_form))
                     .route("/password", web::post().to(routes::change_password))
                     .route("/logout", web::post().to(routes::log_out))
-                    .route("/newsletters", web::get().to(routes::send_newsletters_form))
-                    .route("/newsletters", web::post().to(routes::publish_newsletter)),
+                    .route("/two_factor", web::get().to(routes::two_factor_settings))
+                    .route("/two_factor", web::post().to(routes::enable_two_factor))
+                    .route(
+                        "/two_factor/disable",
+                        web::post().to(routes::disable_two_factor),
+                    )
+                    .route("/suppressions", web::get().to(routes::suppression_list))
+                    .route("/suppressions", web::post().to(routes::suppress_email))
+                    .route(
+                        "/suppressions/remove",
+                        web::post().to(routes::unsuppress_email),
+                    )
+                    .route("/users", web::get().to(routes::list_users))
+                    .route("/users", web::post().to(routes::invite_user))
+                    .route(
+                        "/users/{user_id}/deactivate",
+                        web::post().to(routes::deactivate_user),
+                    )
+                    .route(
+                        "/users/{user_id}/activate",
+                        web::post().to(routes::activate_user),
+                    )
+                    .route(
+                        "/users/{user_id}/role",
+                        web::post().to(routes::change_user_role),
+                    )
+                    .route(
+                        "/users/{user_id}/delete",
+                        web::post().to(routes::delete_user),
+                    )
+                    .route(
+                        "/users/{user_id}/reset_password",
+                        web::post().to(routes::reset_user_password),
+                    )
+                    .route(
+                        "/newsletters",
+                        web::get().to(routes::list_newsletter_issues),
+                    )
+                    .route(
+                        "/newsletters",
+                        web::post().to(routes::create_newsletter_issue),
+                    )
+                    .route(
+                        "/newsletters/new",
+                        web::get().to(routes::new_newsletter_issue_form),
+                    )
+                    .route(
+                        "/newsletters/{newsletter_issue_id}/edit",
+                        web::get().to(routes::edit_newsletter_issue_form),
+                    )
+                    .route(
+                        "/newsletters/{newsletter_issue_id}/edit",
+                        web::post().to(routes::edit_newsletter_issue),
+                    )
+                    .route(
+                        "/newsletters/{newsletter_issue_id}/preview",
+                        web::get().to(routes::preview_newsletter_issue),
+                    )
+                    .route(
+                        "/newsletters/{newsletter_issue_id}/deliveries",
+                        web::get().to(routes::newsletter_issue_deliveries),
+                    )
+                    .route(
+                        "/newsletters/{newsletter_issue_id}/publish",
+                        web::post().to(routes::publish_newsletter),
+                    )
+                    .route(
+                        "/newsletters/{newsletter_issue_id}/schedule",
+                        web::post().to(routes::schedule_newsletter),
+                    )
+                    .route(
+                        "/newsletters/{newsletter_issue_id}/unschedule",
+                        web::post().to(routes::unschedule_newsletter),
+                    )
+                    .route(
+                        "/newsletters/{newsletter_issue_id}/send_test",
+                        web::post().to(routes::send_test_newsletter),
+                    )
+                    .route(
+                        "/newsletters/{newsletter_issue_id}/visibility",
+                        web::post().to(routes::set_newsletter_visibility),
+                    ),
             )
             .app_data(db_pool.clone())
             .app_data(email_client.clone())
             .app_data(base_url.clone())
+            .app_data(hmac_secret.clone())
+            .app_data(email_events.clone())
+            .app_data(subscriptions.clone())
     })
+    // Signals are handled by the caller, which stops the worker as well.
+    .disable_signals()
     .listen(listener)?
     .run();
     Ok(server)
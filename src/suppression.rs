@@ -0,0 +1,142 @@
+use chrono::{DateTime, Utc};
+use sqlx::{PgExecutor, PgPool, Postgres, Transaction};
+
+/// Who put an address on the suppression list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuppressionSource {
+    /// A bounce or a spam complaint reported by our email provider.
+    EmailProvider,
+    /// An admin, by hand.
+    Admin,
+}
+
+impl SuppressionSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SuppressionSource::EmailProvider => "email_provider",
+            SuppressionSource::Admin => "admin",
+        }
+    }
+}
+
+impl std::fmt::Display for SuppressionSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+pub struct SuppressedEmail {
+    pub email: String,
+    pub reason: String,
+    pub source: String,
+    pub suppressed_at: DateTime<Utc>,
+}
+
+/// Stop emailing an address for good, e.g. after a hard bounce or a spam
+/// complaint. The address goes on the suppression list, its subscription
+/// (if any) is flagged and deliveries still waiting in the queue are dropped.
+///
+/// Returns `false` if the address was already on the suppression list.
+#[tracing::instrument(name = "Suppress an email address", skip(transaction))]
+pub async fn suppress_subscriber(
+    transaction: &mut Transaction<'_, Postgres>,
+    email: &str,
+    source: SuppressionSource,
+    reason: &str,
+) -> Result<bool, sqlx::Error> {
+    let n_inserted_rows = sqlx::query!(
+        r#"
+        INSERT INTO suppressed_emails (email, reason, source, suppressed_at)
+        VALUES (lower($1), $2, $3, now())
+        ON CONFLICT (email) DO NOTHING
+        "#,
+        email,
+        reason,
+        source.as_str()
+    )
+    .execute(&mut *transaction)
+    .await?
+    .rows_affected();
+    sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET status = 'suppressed', suppressed_at = now()
+        WHERE lower(email) = lower($1) AND status <> 'suppressed'
+        "#,
+        email
+    )
+    .execute(&mut *transaction)
+    .await?;
+    sqlx::query!(
+        r#"DELETE FROM issue_delivery_queue WHERE lower(subscriber_email) = lower($1)"#,
+        email
+    )
+    .execute(&mut *transaction)
+    .await?;
+    sqlx::query!(
+        r#"
+        UPDATE issue_delivery_log
+        SET status = 'skipped_suppressed', updated_at = now()
+        WHERE lower(subscriber_email) = lower($1) AND status = 'queued'
+        "#,
+        email
+    )
+    .execute(&mut *transaction)
+    .await?;
+    Ok(n_inserted_rows > 0)
+}
+
+/// Take an address off the suppression list.
+///
+/// Its subscription stays suppressed: the owner has to opt in again before
+/// they get any newsletter.
+/// Returns `false` if the address was not on the list.
+#[tracing::instrument(name = "Remove an email address from the suppression list", skip(pool))]
+pub async fn remove_suppressed_email(pool: &PgPool, email: &str) -> Result<bool, sqlx::Error> {
+    let n_deleted_rows = sqlx::query!(
+        r#"DELETE FROM suppressed_emails WHERE email = lower($1)"#,
+        email
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(n_deleted_rows > 0)
+}
+
+/// Every code path sending an email must check this first.
+#[tracing::instrument(name = "Check the suppression list", skip(executor))]
+pub async fn is_suppressed(
+    executor: impl PgExecutor<'_>,
+    email: &str,
+) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"SELECT email FROM suppressed_emails WHERE email = lower($1)"#,
+        email
+    )
+    .fetch_optional(executor)
+    .await?;
+    Ok(row.is_some())
+}
+
+/// Entries whose address contains `query`, most recent first.
+#[tracing::instrument(name = "Search the suppression list", skip(pool))]
+pub async fn search_suppressed_emails(
+    pool: &PgPool,
+    query: &str,
+    limit: i64,
+) -> Result<Vec<SuppressedEmail>, sqlx::Error> {
+    sqlx::query_as!(
+        SuppressedEmail,
+        r#"
+        SELECT email, reason, source, suppressed_at
+        FROM suppressed_emails
+        WHERE strpos(email, lower($1)) > 0
+        ORDER BY suppressed_at DESC
+        LIMIT $2
+        "#,
+        query,
+        limit
+    )
+    .fetch_all(pool)
+    .await
+}
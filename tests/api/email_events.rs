@@ -0,0 +1,235 @@
+use crate::helpers::{spawn_app, TestApp};
+
+const EMAIL: &str = "ursula_le_guin@gmail.com";
+
+fn bounce(id: i64, bounce_type: &str) -> serde_json::Value {
+    serde_json::json!({
+        "RecordType": "Bounce",
+        "ID": id,
+        "Type": bounce_type,
+        "Email": EMAIL,
+        "MessageID": "883953f4-6105-42a2-a16a-77a8eac79483",
+        "Description": "The server was unable to deliver your message."
+    })
+}
+
+async fn subscriber_status(app: &TestApp) -> String {
+    sqlx::query!("SELECT status FROM subscriptions WHERE email = $1", EMAIL)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription.")
+        .status
+}
+
+#[tokio::test]
+async fn email_events_without_the_webhook_token_are_rejected_with_a_401() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client
+        .post(&format!("{}/webhooks/email_events", &app.address))
+        .basic_auth("postmark", Some("not-the-token"))
+        .json(&bounce(1, "HardBounce"))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(401, response.status().as_u16());
+    assert_eq!(
+        r#"Basic realm="email_events""#,
+        response.headers()["WWW-Authenticate"]
+    );
+}
+
+#[tokio::test]
+async fn malformed_email_events_are_rejected_with_a_400() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .post_email_event(&serde_json::json!({ "RecordType": "Bounce" }))
+        .await;
+
+    // Assert
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn a_hard_bounce_suppresses_the_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber(EMAIL).await;
+
+    // Act
+    let response = app.post_email_event(&bounce(1, "HardBounce")).await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(subscriber_status(&app).await, "suppressed");
+    let event = sqlx::query!("SELECT kind, subscriber_id FROM email_events")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(event.kind, "hard_bounce");
+    assert!(event.subscriber_id.is_some());
+}
+
+#[tokio::test]
+async fn a_spam_complaint_suppresses_the_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber(EMAIL).await;
+    let complaint = serde_json::json!({
+        "RecordType": "SpamComplaint",
+        "ID": 42,
+        "Type": "SpamComplaint",
+        "Email": EMAIL,
+        "MessageID": "883953f4-6105-42a2-a16a-77a8eac79483"
+    });
+
+    // Act
+    let response = app.post_email_event(&complaint).await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(subscriber_status(&app).await, "suppressed");
+}
+
+#[tokio::test]
+async fn soft_bounces_suppress_the_subscriber_once_they_reach_the_threshold() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber(EMAIL).await;
+
+    // Act - Part 1 - Below the threshold
+    for id in 1..3 {
+        app.post_email_event(&bounce(id, "SoftBounce"))
+            .await
+            .error_for_status()
+            .unwrap();
+    }
+    // Assert - Part 1
+    assert_eq!(subscriber_status(&app).await, "confirmed");
+
+    // Act - Part 2 - Reaching the threshold
+    app.post_email_event(&bounce(3, "SoftBounce"))
+        .await
+        .error_for_status()
+        .unwrap();
+    // Assert - Part 2
+    assert_eq!(subscriber_status(&app).await, "suppressed");
+}
+
+#[tokio::test]
+async fn soft_bounces_are_counted_whatever_the_case_of_the_reported_address() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber(EMAIL).await;
+
+    // Act
+    for id in 1..4 {
+        let mut event = bounce(id, "SoftBounce");
+        event["Email"] = EMAIL.to_uppercase().into();
+        app.post_email_event(&event)
+            .await
+            .error_for_status()
+            .unwrap();
+    }
+
+    // Assert
+    assert_eq!(subscriber_status(&app).await, "suppressed");
+    let n_unlinked_events =
+        sqlx::query!(r#"SELECT COUNT(*) as "n!" FROM email_events WHERE subscriber_id IS NULL"#)
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap()
+            .n;
+    assert_eq!(n_unlinked_events, 0);
+}
+
+#[tokio::test]
+async fn a_delivery_resets_the_soft_bounce_count() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber(EMAIL).await;
+    for id in 1..3 {
+        app.post_email_event(&bounce(id, "SoftBounce"))
+            .await
+            .error_for_status()
+            .unwrap();
+    }
+
+    // Act
+    let delivery = serde_json::json!({
+        "RecordType": "Delivery",
+        "Recipient": EMAIL,
+        "MessageID": "883953f4-6105-42a2-a16a-77a8eac79483",
+        "DeliveredAt": "2022-08-06T09:00:00Z",
+        "Details": "Test delivery webhook details"
+    });
+    app.post_email_event(&delivery)
+        .await
+        .error_for_status()
+        .unwrap();
+    app.post_email_event(&bounce(3, "SoftBounce"))
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    assert_eq!(subscriber_status(&app).await, "confirmed");
+}
+
+#[tokio::test]
+async fn redelivered_email_events_are_only_processed_once() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_confirmed_subscriber(EMAIL).await;
+
+    // Act
+    for _ in 0..3 {
+        app.post_email_event(&bounce(7, "SoftBounce"))
+            .await
+            .error_for_status()
+            .unwrap();
+    }
+
+    // Assert
+    let subscriber = sqlx::query!(
+        "SELECT status, soft_bounce_count FROM subscriptions WHERE email = $1",
+        EMAIL
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(subscriber.status, "confirmed");
+    assert_eq!(subscriber.soft_bounce_count, 1);
+    let n_events = sqlx::query!("SELECT count(*) AS \"n!\" FROM email_events")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(n_events, 1);
+}
+
+#[tokio::test]
+async fn email_events_for_unknown_addresses_are_recorded() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.post_email_event(&bounce(1, "HardBounce")).await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let event = sqlx::query!("SELECT email, subscriber_id FROM email_events")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(event.email, EMAIL);
+    assert!(event.subscriber_id.is_none());
+}
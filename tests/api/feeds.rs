@@ -0,0 +1,135 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+
+async fn get_feed(app: &TestApp, path: &str) -> reqwest::Response {
+    app.api_client
+        .get(&format!("{}{}", &app.address, path))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+#[tokio::test]
+async fn the_rss_feed_lists_published_issues() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = app.publish_issue("Our first issue").await;
+    let private_issue_id = app.publish_issue("Members only").await;
+    app.post_newsletter_visibility(private_issue_id, true).await;
+
+    // Act
+    let response = get_feed(&app, "/feed.rss").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers()["Content-Type"],
+        "application/rss+xml; charset=utf-8"
+    );
+    let feed = response.text().await.unwrap();
+    assert!(feed.contains("<title>Our first issue</title>"));
+    assert!(feed.contains(&format!(
+        "<link>{}/issues/our-first-issue</link>",
+        app.base_url
+    )));
+    assert!(feed.contains(&format!(
+        r#"<guid isPermaLink="false">urn:uuid:{}</guid>"#,
+        newsletter_issue_id
+    )));
+    assert!(feed.contains("<pubDate>"));
+    assert!(feed.contains("&lt;p&gt;Hi reader, this is the HTML body&lt;/p&gt;"));
+    assert!(!feed.contains("Members only"));
+}
+
+#[tokio::test]
+async fn the_atom_feed_lists_published_issues() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = app.publish_issue("Our first issue").await;
+    let response = app
+        .post_newsletters(&serde_json::json!({
+            "title": "Work in progress",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": uuid::Uuid::new_v4().to_string()
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    // Act
+    let response = get_feed(&app, "/feed.atom").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers()["Content-Type"],
+        "application/atom+xml; charset=utf-8"
+    );
+    let feed = response.text().await.unwrap();
+    assert!(feed.contains("<title>Our first issue</title>"));
+    assert!(feed.contains(&format!("<id>urn:uuid:{}</id>", newsletter_issue_id)));
+    assert!(feed.contains("<published>"));
+    assert!(feed.contains(r#"<content type="html">"#));
+    assert!(!feed.contains("Work in progress"));
+}
+
+#[tokio::test]
+async fn unchanged_feeds_are_not_downloaded_again_with_an_etag() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    app.publish_issue("Our first issue").await;
+    let response = get_feed(&app, "/feed.rss").await;
+    let etag = response.headers()[ETAG].clone();
+
+    // Act - Part 1 - Nothing changed
+    let response = app
+        .api_client
+        .get(&format!("{}/feed.rss", &app.address))
+        .header(IF_NONE_MATCH, etag.clone())
+        .send()
+        .await
+        .unwrap();
+
+    // Assert - Part 1
+    assert_eq!(response.status().as_u16(), 304);
+    assert_eq!(response.text().await.unwrap(), "");
+
+    // Act - Part 2 - A new issue is out
+    app.publish_issue("Our second issue").await;
+    let response = app
+        .api_client
+        .get(&format!("{}/feed.rss", &app.address))
+        .header(IF_NONE_MATCH, etag)
+        .send()
+        .await
+        .unwrap();
+
+    // Assert - Part 2
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.text().await.unwrap().contains("Our second issue"));
+}
+
+#[tokio::test]
+async fn unchanged_feeds_are_not_downloaded_again_with_if_modified_since() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    app.publish_issue("Our first issue").await;
+    let response = get_feed(&app, "/feed.atom").await;
+    let last_modified = response.headers()[LAST_MODIFIED].clone();
+
+    // Act
+    let response = app
+        .api_client
+        .get(&format!("{}/feed.atom", &app.address))
+        .header(IF_MODIFIED_SINCE, last_modified)
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 304);
+}
@@ -1,12 +1,26 @@
 use argon2::{password_hash::SaltString, Algorithm, Argon2, Params, PasswordHasher, Version};
+use fake::faker::name::en::Name;
+use fake::Fake;
 use once_cell::sync::Lazy;
+use secrecy::{ExposeSecret, Secret};
 use sqlx::{Connection, Executor, PgConnection, PgPool};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
 use uuid::Uuid;
-use wiremock::MockServer;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
 use zeroToprod_finalll::configuration::{get_configuration,DatabaseSettings};
+use zeroToprod_finalll::configuration::{IssueDeliveryWorkerSettings, Settings};
+use zeroToprod_finalll::domain::UnsubscribeToken;
+use zeroToprod_finalll::email_client::EmailClient;
+use zeroToprod_finalll::email_outbox::try_send_outbox_email;
+use zeroToprod_finalll::issue_delivery_worker::{
+    enqueue_due_scheduled_issues, run_worker_until_stopped, try_execute_task, ExecutionOutcome,
+};
 //use zero2prod::configuration::{get_configuration, DatabaseSettings};
+use zeroToprod_finalll::shutdown::Shutdown;
 use zeroToprod_finalll::startup::get_connection_pool;
-use zeroToprod_finalll::startup::Application;
+use zeroToprod_finalll::startup::{Application, ApplicationBaseUrl, HmacSecret};
 use zeroToprod_finalll::telemetry::{get_subscriber, init_subscriber};
 
 // Ensure that the `tracing` stack is only initialised once using `once_cell`
@@ -32,6 +46,7 @@ pub struct TestUser {
     pub user_id: Uuid,
     pub username: String,
     pub password: String,
+    pub role: String,
 }
 
 pub struct TestApp {
@@ -41,16 +56,52 @@ pub struct TestApp {
     pub port: u16,
     pub test_user: TestUser,
     pub api_client: reqwest::Client,
+    pub hmac_secret: Secret<String>,
+    pub email_client: EmailClient,
+    pub base_url: String,
+    pub worker_settings: IssueDeliveryWorkerSettings,
+    pub webhook_token: Secret<String>,
+    pub configuration: Settings,
 }
 
 impl TestApp {
+    /// Confirmation emails go through the outbox: they are sent before returning,
+    /// as the background worker would.
     pub async fn post_subscriptions(&self, body: String) -> reqwest::Response {
-        self.api_client
+        let response = self
+            .api_client
             .post(&format!("{}/subscriptions", &self.address))
             .header("Content-Type", "application/x-www-form-urlencoded")
             .body(body)
             .send()
             .await
+            .expect("Failed to execute request.");
+        self.dispatch_outbox_emails().await;
+        response
+    }
+
+    pub async fn post_resend_confirmation(&self, email: &str) -> reqwest::Response {
+        let response = self
+            .api_client
+            .post(&format!(
+                "{}/subscriptions/resend_confirmation",
+                &self.address
+            ))
+            .form(&[("email", email)])
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        self.dispatch_outbox_emails().await;
+        response
+    }
+
+    pub async fn post_email_event(&self, body: &serde_json::Value) -> reqwest::Response {
+        self.api_client
+            .post(&format!("{}/webhooks/email_events", &self.address))
+            .basic_auth("postmark", Some(self.webhook_token.expose_secret()))
+            .json(body)
+            .send()
+            .await
             .expect("Failed to execute request.")
     }
 
@@ -74,6 +125,135 @@ impl TestApp {
         ConfirmationLinks { html, plain_text }
     }
 
+    /// Subscribe `email` and return the links of the confirmation email it gets.
+    pub async fn create_unconfirmed_subscriber(&self, email: &str) -> ConfirmationLinks {
+        let name: String = Name().fake();
+        let body = serde_urlencoded::to_string(&serde_json::json!({
+            "name": name,
+            "email": email
+        }))
+        .unwrap();
+
+        let _mock_guard = Mock::given(path("/"))
+            .and(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .named("Create unconfirmed subscriber")
+            .expect(1)
+            .mount_as_scoped(&self.email_server)
+            .await;
+        self.post_subscriptions(body)
+            .await
+            .error_for_status()
+            .unwrap();
+
+        let email_request = self
+            .email_server
+            .received_requests()
+            .await
+            .unwrap()
+            .pop()
+            .unwrap();
+        self.get_confirmation_links(&email_request)
+    }
+
+    /// Subscribe `email`, confirm the subscription and return the subscriber id.
+    pub async fn create_confirmed_subscriber(&self, email: &str) -> Uuid {
+        let confirmation_links = self.create_unconfirmed_subscriber(email).await;
+        reqwest::get(confirmation_links.html)
+            .await
+            .unwrap()
+            .error_for_status()
+            .unwrap();
+
+        sqlx::query!("SELECT id FROM subscriptions WHERE email = $1", email)
+            .fetch_one(&self.db_pool)
+            .await
+            .expect("Failed to fetch saved subscription.")
+            .id
+    }
+
+    pub async fn dispatch_outbox_emails(&self) {
+        let rate_limiter = self
+            .worker_settings
+            .rate_limiter(self.db_pool.clone())
+            .unwrap();
+        loop {
+            if let ExecutionOutcome::EmptyQueue = try_send_outbox_email(
+                &self.db_pool,
+                &self.email_client,
+                &self.worker_settings,
+                &rate_limiter,
+            )
+            .await
+            .unwrap()
+            {
+                break;
+            }
+        }
+    }
+
+    pub async fn dispatch_all_pending_emails(&self) {
+        self.dispatch_outbox_emails().await;
+        enqueue_due_scheduled_issues(&self.db_pool).await.unwrap();
+        let rate_limiter = self
+            .worker_settings
+            .rate_limiter(self.db_pool.clone())
+            .unwrap();
+        loop {
+            if let ExecutionOutcome::EmptyQueue = try_execute_task(
+                &self.db_pool,
+                &self.email_client,
+                &ApplicationBaseUrl(self.base_url.clone()),
+                &HmacSecret(self.hmac_secret.clone()),
+                &self.worker_settings,
+                &rate_limiter,
+            )
+            .await
+            .unwrap()
+            {
+                break;
+            }
+        }
+    }
+
+    /// Run the delivery worker in the background, as in production, until
+    /// the returned `Shutdown` is triggered.
+    pub fn spawn_worker(
+        &self,
+        worker_settings: IssueDeliveryWorkerSettings,
+    ) -> (Shutdown, JoinHandle<Result<(), anyhow::Error>>) {
+        let mut configuration = self.configuration.clone();
+        configuration.issue_delivery_worker = worker_settings;
+        let shutdown = Shutdown::new();
+        let worker = tokio::spawn(run_worker_until_stopped(configuration, shutdown.clone()));
+        (shutdown, worker)
+    }
+
+    /// Wait for the email server to have received `n` requests in total,
+    /// giving up after `timeout`.
+    pub async fn wait_for_email_requests(
+        &self,
+        n: usize,
+        timeout: Duration,
+    ) -> Vec<wiremock::Request> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let requests = self.email_server.received_requests().await.unwrap();
+            if requests.len() >= n || Instant::now() >= deadline {
+                return requests;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+
+    pub fn get_unsubscribe_link(&self, subscriber_id: Uuid) -> String {
+        let token = UnsubscribeToken::generate(subscriber_id, &self.hmac_secret);
+        format!(
+            "{}/subscriptions/unsubscribe?subscriber_id={}&token={}",
+            &self.address, subscriber_id, token
+        )
+    }
+
     pub async fn post_newsletters(&self, body: &serde_json::Value) -> reqwest::Response {
         self.api_client
             .post(&format!("{}/admin/newsletters", &self.address))
@@ -83,6 +263,120 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    /// Create a draft and publish it straight away.
+    pub async fn publish_newsletter(&self, body: &serde_json::Value) -> reqwest::Response {
+        let response = self.post_newsletters(body).await;
+        assert_is_redirect_to(&response, "/admin/newsletters");
+        let newsletter_issue_id = self.latest_newsletter_issue_id().await;
+        self.post_publish_newsletter(newsletter_issue_id).await
+    }
+
+    pub async fn create_draft(&self) -> Uuid {
+        let response = self
+            .post_newsletters(&serde_json::json!({
+                "title": "Newsletter title",
+                "text_content": "Newsletter body as plain text",
+                "html_content": "<p>Newsletter body as HTML</p>",
+                "idempotency_key": Uuid::new_v4().to_string()
+            }))
+            .await;
+        assert_is_redirect_to(&response, "/admin/newsletters");
+        self.latest_newsletter_issue_id().await
+    }
+
+    pub async fn publish_issue(&self, title: &str) -> Uuid {
+        let response = self
+            .publish_newsletter(&serde_json::json!({
+                "title": title,
+                "text_content": "Newsletter body as plain text",
+                "html_content": "<p>Hi {{ subscriber.name }}, this is the HTML body</p>",
+                "idempotency_key": Uuid::new_v4().to_string()
+            }))
+            .await;
+        assert_is_redirect_to(&response, "/admin/newsletters");
+        self.latest_newsletter_issue_id().await
+    }
+
+    pub async fn latest_newsletter_issue_id(&self) -> Uuid {
+        sqlx::query!(
+            "SELECT newsletter_issue_id FROM newsletter_issues ORDER BY created_at DESC LIMIT 1"
+        )
+        .fetch_one(&self.db_pool)
+        .await
+        .expect("Failed to fetch the latest newsletter issue.")
+        .newsletter_issue_id
+    }
+
+    pub async fn post_publish_newsletter(&self, newsletter_issue_id: Uuid) -> reqwest::Response {
+        self.api_client
+            .post(&format!(
+                "{}/admin/newsletters/{}/publish",
+                &self.address, newsletter_issue_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_schedule_newsletter(
+        &self,
+        newsletter_issue_id: Uuid,
+        body: &serde_json::Value,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(&format!(
+                "{}/admin/newsletters/{}/schedule",
+                &self.address, newsletter_issue_id
+            ))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_unschedule_newsletter(&self, newsletter_issue_id: Uuid) -> reqwest::Response {
+        self.api_client
+            .post(&format!(
+                "{}/admin/newsletters/{}/unschedule",
+                &self.address, newsletter_issue_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_send_test_newsletter(
+        &self,
+        newsletter_issue_id: Uuid,
+        body: &serde_json::Value,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(&format!(
+                "{}/admin/newsletters/{}/send_test",
+                &self.address, newsletter_issue_id
+            ))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_edit_newsletter(
+        &self,
+        newsletter_issue_id: Uuid,
+        body: &serde_json::Value,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(&format!(
+                "{}/admin/newsletters/{}/edit",
+                &self.address, newsletter_issue_id
+            ))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn post_login<Body>(&self, body: &Body) -> reqwest::Response
     where
         Body: serde::Serialize,
@@ -130,6 +424,180 @@ impl TestApp {
         self.get_admin_newsletters().await.text().await.unwrap()
     }
 
+    pub async fn get_newsletter_deliveries_html(&self, newsletter_issue_id: Uuid) -> String {
+        self.api_client
+            .get(&format!(
+                "{}/admin/newsletters/{}/deliveries",
+                &self.address, newsletter_issue_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_new_newsletter_html(&self) -> String {
+        self.api_client
+            .get(&format!("{}/admin/newsletters/new", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_edit_newsletter_html(&self, newsletter_issue_id: Uuid) -> String {
+        self.api_client
+            .get(&format!(
+                "{}/admin/newsletters/{}/edit",
+                &self.address, newsletter_issue_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_suppressions_html(&self, search: &str) -> String {
+        self.api_client
+            .get(&format!("{}/admin/suppressions", &self.address))
+            .query(&[("search", search)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_issues_html(&self, page: u32) -> String {
+        self.api_client
+            .get(&format!("{}/issues", &self.address))
+            .query(&[("page", page)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_issue(&self, slug: &str) -> reqwest::Response {
+        self.api_client
+            .get(&format!("{}/issues/{}", &self.address, slug))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_newsletter_visibility(
+        &self,
+        newsletter_issue_id: Uuid,
+        is_private: bool,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(&format!(
+                "{}/admin/newsletters/{}/visibility",
+                &self.address, newsletter_issue_id
+            ))
+            .form(&[("is_private", is_private)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_suppression<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(&format!("{}/admin/suppressions", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_remove_suppression(&self, email: &str) -> reqwest::Response {
+        self.api_client
+            .post(&format!("{}/admin/suppressions/remove", &self.address))
+            .form(&[("email", email)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_users(&self) -> reqwest::Response {
+        self.api_client
+            .get(&format!("{}/admin/users", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_users_html(&self) -> String {
+        self.get_admin_users().await.text().await.unwrap()
+    }
+
+    /// The invitation goes through the outbox: it is sent before returning.
+    pub async fn post_invite_user(
+        &self,
+        username: &str,
+        email: &str,
+        role: &str,
+    ) -> reqwest::Response {
+        let response = self
+            .api_client
+            .post(&format!("{}/admin/users", &self.address))
+            .form(&[("username", username), ("email", email), ("role", role)])
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        self.dispatch_outbox_emails().await;
+        response
+    }
+
+    /// `action` is one of `activate`, `deactivate`, `delete` or `reset_password`.
+    pub async fn post_user_action(&self, user_id: Uuid, action: &str) -> reqwest::Response {
+        let response = self
+            .api_client
+            .post(&format!(
+                "{}/admin/users/{}/{}",
+                &self.address, user_id, action
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        self.dispatch_outbox_emails().await;
+        response
+    }
+
+    pub async fn post_user_role(&self, user_id: Uuid, role: &str) -> reqwest::Response {
+        self.api_client
+            .post(&format!("{}/admin/users/{}/role", &self.address, user_id))
+            .form(&[("role", role)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_set_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(&format!("{}/users/set_password", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn get_change_password(&self) -> reqwest::Response {
         self.api_client
             .get(&format!("{}/admin/password", &self.address))
@@ -161,6 +629,52 @@ impl TestApp {
             .await
             .expect("Failed to execute request.")
     }
+
+    pub async fn get_two_factor_settings_html(&self) -> String {
+        self.api_client
+            .get(&format!("{}/admin/two_factor", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_enable_two_factor(&self, code: &str) -> reqwest::Response {
+        self.api_client
+            .post(&format!("{}/admin/two_factor", &self.address))
+            .form(&[("code", code)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_disable_two_factor(&self, code: &str) -> reqwest::Response {
+        self.api_client
+            .post(&format!("{}/admin/two_factor/disable", &self.address))
+            .form(&[("code", code)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_login_two_factor(&self) -> reqwest::Response {
+        self.api_client
+            .get(&format!("{}/login/two_factor", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_login_two_factor(&self, code: &str) -> reqwest::Response {
+        self.api_client
+            .post(&format!("{}/login/two_factor", &self.address))
+            .form(&[("code", code)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
 }
 
 pub fn assert_is_redirect_to(response: &reqwest::Response, location: &str) {
@@ -174,10 +688,18 @@ impl TestUser {
             user_id: Uuid::new_v4(),
             username: Uuid::new_v4().to_string(),
             password: Uuid::new_v4().to_string(),
+            role: "owner".into(),
         }
     }
 
-    async fn store(&self, pool: &PgPool) {
+    pub fn generate_with_role(role: &str) -> Self {
+        Self {
+            role: role.into(),
+            ..Self::generate()
+        }
+    }
+
+    pub async fn store(&self, pool: &PgPool) {
         let salt = SaltString::generate(&mut rand::thread_rng());
         let password_hash = Argon2::new(
             Algorithm::Argon2id,
@@ -188,11 +710,12 @@ impl TestUser {
         .unwrap()
         .to_string();
         sqlx::query!(
-            "INSERT INTO users (user_id, username, password_hash)\
-            VALUES ($1, $2, $3)",
+            "INSERT INTO users (user_id, username, password_hash, role)\
+            VALUES ($1, $2, $3, $4)",
             self.user_id,
             self.username,
-            password_hash
+            password_hash,
+            self.role
         )
         .execute(pool)
         .await
@@ -217,7 +740,9 @@ pub async fn spawn_app() -> TestApp {
         let mut c = get_configuration().expect("Failed to read configuration.");
         c.database.database_name = Uuid::new_v4().to_string();
         c.application.port = 0;
-        c.email_client.base_url = email_server.uri();
+        c.email_client.base_url = Some(email_server.uri());
+        // Retry failed deliveries straight away
+        c.issue_delivery_worker.initial_backoff_milliseconds = 0;
         c
     };
 
@@ -228,13 +753,7 @@ pub async fn spawn_app() -> TestApp {
         .expect("Failed to build application.");
 
     let app_port = application.port();
-    let _ = tokio::spawn(application.run_until_stopped());
-
-    let client = reqwest::Client::builder()
-        .redirect(reqwest::redirect::Policy::none())
-        .cookie_store(true)
-        .build()
-        .unwrap();
+    let _ = tokio::spawn(application.run_until_stopped(Shutdown::new()));
 
     let test_app = TestApp {
         address: format!("http://127.0.0.1:{}", app_port),
@@ -242,12 +761,31 @@ pub async fn spawn_app() -> TestApp {
         db_pool: get_connection_pool(&configuration.database),
         email_server,
         test_user: TestUser::generate(),
-        api_client: client,
+        api_client: api_client(),
+        hmac_secret: configuration.application.hmac_secret.clone(),
+        email_client: configuration
+            .email_client
+            .clone()
+            .client()
+            .expect("Failed to build the email client."),
+        base_url: configuration.application.base_url.clone(),
+        worker_settings: configuration.issue_delivery_worker.clone(),
+        webhook_token: configuration.email_events.webhook_token.clone(),
+        configuration,
     };
     test_app.test_user.store(&test_app.db_pool).await;
     test_app
 }
 
+/// A client with its own cookies: a separate browser, as far as sessions go.
+pub fn api_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .cookie_store(true)
+        .build()
+        .unwrap()
+}
+
 async fn configure_database(config: &DatabaseSettings) -> PgPool {
     let mut connection = PgConnection::connect_with(&config.without_db())
         .await
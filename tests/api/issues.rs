@@ -0,0 +1,130 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+
+#[tokio::test]
+async fn published_issues_are_listed_in_the_archive() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    app.publish_issue("Our First Issue!").await;
+
+    // Act
+    let html_page = app.get_issues_html(1).await;
+
+    // Assert
+    assert!(html_page.contains(r#"<a href="/issues/our-first-issue">Our First Issue!</a>"#));
+}
+
+#[tokio::test]
+async fn published_issues_have_a_public_page() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    app.publish_issue("Our First Issue!").await;
+
+    // Act
+    let response = app.get_issue("our-first-issue").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("<h1>Our First Issue!</h1>"));
+    assert!(html_page.contains("<p>Hi reader, this is the HTML body</p>"));
+}
+
+#[tokio::test]
+async fn drafts_are_not_in_the_archive() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let response = app
+        .post_newsletters(&serde_json::json!({
+            "title": "Work in progress",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": uuid::Uuid::new_v4().to_string()
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    // Act
+    let html_page = app.get_issues_html(1).await;
+    let response = app.get_issue("work-in-progress").await;
+
+    // Assert
+    assert!(!html_page.contains("Work in progress"));
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn private_issues_are_hidden_from_the_archive() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = app.publish_issue("Members only").await;
+
+    // Act - Part 1 - Make it private
+    let response = app
+        .post_newsletter_visibility(newsletter_issue_id, true)
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    // Assert - Part 1
+    assert!(!app.get_issues_html(1).await.contains("Members only"));
+    let response = app.get_issue("members-only").await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    // Act - Part 2 - Make it public again
+    let response = app
+        .post_newsletter_visibility(newsletter_issue_id, false)
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    // Assert - Part 2
+    assert!(app.get_issues_html(1).await.contains("Members only"));
+    let response = app.get_issue("members-only").await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn issues_with_the_same_title_get_distinct_slugs() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    app.publish_issue("Weekly digest").await;
+    app.publish_issue("Weekly digest").await;
+
+    // Assert
+    let slugs: Vec<String> =
+        sqlx::query!(r#"SELECT slug AS "slug!" FROM newsletter_issues ORDER BY slug"#)
+            .fetch_all(&app.db_pool)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|r| r.slug)
+            .collect();
+    assert_eq!(slugs, vec!["weekly-digest", "weekly-digest-2"]);
+}
+
+#[tokio::test]
+async fn the_archive_is_paginated() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    for i in 0..11 {
+        app.publish_issue(&format!("Issue {}", i)).await;
+    }
+
+    // Act
+    let first_page = app.get_issues_html(1).await;
+    let second_page = app.get_issues_html(2).await;
+
+    // Assert
+    assert!(first_page.contains("Issue 10"));
+    assert!(!first_page.contains(r#""/issues/issue-0""#));
+    assert!(first_page.contains(r#"<a href="/issues?page=2">Older issues</a>"#));
+    assert!(second_page.contains(r#""/issues/issue-0""#));
+    assert!(second_page.contains(r#"<a href="/issues?page=1">Newer issues</a>"#));
+    assert!(!second_page.contains("Older issues"));
+}
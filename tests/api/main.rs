@@ -1,8 +1,18 @@
 mod admin_dashboard;
 mod change_password;
+mod email_events;
+mod feeds;
 mod health_check;
 mod helpers;
+mod issues;
 mod login;
 mod newsletter;
+mod permissions;
+mod rate_limiter;
+mod resend_confirmation;
 mod subscriptions;
 mod subscriptions_confirm;
+mod suppressions;
+mod two_factor;
+mod unsubscribe;
+mod users;
@@ -1,10 +1,11 @@
-use crate::helpers::{self, ConfirmationLinks, TestApp};
-use fake::faker::{internet::en::SafeEmail, name::en::Name};
+use crate::helpers;
+use fake::faker::internet::en::SafeEmail;
 use fake::Fake;
 use std::time::Duration;
 use uuid::Uuid;
 use wiremock::matchers::{any, method, path};
 use wiremock::{Mock, MockBuilder, ResponseTemplate};
+use zeroToprod_finalll::domain::UnsubscribeToken;
 
 #[tokio::test]
 async fn newsletter_are_not_delivered_to_unconfirmed_subscribers() {
@@ -16,7 +17,7 @@ async fn newsletter_are_not_delivered_to_unconfirmed_subscribers() {
     });
     app.post_login(&login_body).await;
 
-    create_unconfirmed_subscriber(&app).await;
+    app.create_unconfirmed_subscriber(&random_email()).await;
 
     Mock::given(any())
         .respond_with(ResponseTemplate::new(200))
@@ -30,8 +31,9 @@ async fn newsletter_are_not_delivered_to_unconfirmed_subscribers() {
         "html_content": "<p>Newsletter body as HTML</p>",
         "idempotency_key": uuid::Uuid::new_v4().to_string()
     });
-    let response = app.post_newsletters(&newsletter_request_body).await;
+    let response = app.publish_newsletter(&newsletter_request_body).await;
     helpers::assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
 }
 
 #[tokio::test]
@@ -44,7 +46,7 @@ async fn newsletters_are_delivered_to_confirmed_subscribers() {
     });
     app.post_login(&login_body).await;
 
-    create_confirmed_subscriber(&app).await;
+    app.create_confirmed_subscriber(&random_email()).await;
 
     Mock::given(path("/"))
         .and(method("POST"))
@@ -59,11 +61,12 @@ async fn newsletters_are_delivered_to_confirmed_subscribers() {
         "html_content": "<p>Newsletter body as HTML</p>",
         "idempotency_key": uuid::Uuid::new_v4().to_string()
     });
-    let response = app.post_newsletters(&newsletter_request_body).await;
+    let response = app.publish_newsletter(&newsletter_request_body).await;
     helpers::assert_is_redirect_to(&response, "/admin/newsletters");
 
     let html_page = app.get_admin_newsletters_html().await;
-    assert!(html_page.contains(&format!("{}", "Newsletter issue started successfully.")));
+    assert!(html_page.contains("The newsletter issue has been published"));
+    app.dispatch_all_pending_emails().await;
 }
 
 #[tokio::test]
@@ -164,16 +167,9 @@ async fn invalid_password_is_rejected() {
 #[tokio::test]
 async fn newsletter_creation_is_idempotent() {
     let app = helpers::spawn_app().await;
-    create_confirmed_subscriber(&app).await;
+    app.create_confirmed_subscriber(&random_email()).await;
     app.test_user.login(&app).await;
 
-    Mock::given(path("/"))
-        .and(method("POST"))
-        .respond_with(ResponseTemplate::new(200))
-        .expect(1)
-        .mount(&app.email_server)
-        .await;
-
     let newsletter_request_body = serde_json::json!({
         "title": "Newsletter title",
         "text_content": "Newsletter body as plain text",
@@ -184,26 +180,26 @@ async fn newsletter_creation_is_idempotent() {
     helpers::assert_is_redirect_to(&response, "/admin/newsletters");
 
     let html_page = app.get_admin_newsletters_html().await;
-    assert!(html_page.contains("<p><i>Newsletter issue started successfully.</i></p>"));
+    assert!(html_page.contains("<p><i>The newsletter issue draft has been saved.</i></p>"));
 
     //retry
     let response = app.post_newsletters(&newsletter_request_body).await;
     helpers::assert_is_redirect_to(&response, "/admin/newsletters");
+
+    let n_issues = sqlx::query!(r#"SELECT COUNT(*) as "n!" FROM newsletter_issues"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(n_issues, 1);
 }
 
 #[tokio::test]
 async fn concurrent_form_submission_is_handled_gracefully() {
     let app = helpers::spawn_app().await;
-    create_confirmed_subscriber(&app).await;
+    app.create_confirmed_subscriber(&random_email()).await;
     app.test_user.login(&app).await;
 
-    Mock::given(path("/"))
-        .and(method("POST"))
-        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(2)))
-        .expect(1)
-        .mount(&app.email_server)
-        .await;
-
     let newsletter_request_body = serde_json::json!({
         "title": "Newsletter title",
         "text_content": "Newsletter body as plain text",
@@ -219,6 +215,199 @@ async fn concurrent_form_submission_is_handled_gracefully() {
         response1.text().await.unwrap(),
         response2.text().await.unwrap()
     );
+    let n_issues = sqlx::query!(r#"SELECT COUNT(*) as "n!" FROM newsletter_issues"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(n_issues, 1);
+}
+
+#[tokio::test]
+async fn drafts_are_not_delivered_until_they_are_published() {
+    let app = helpers::spawn_app().await;
+    app.create_confirmed_subscriber(&random_email()).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    let response = app.post_newsletters(&newsletter_request_body).await;
+    helpers::assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+
+    let issue = sqlx::query!("SELECT status, published_at FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(issue.status, "draft");
+    assert!(issue.published_at.is_none());
+}
+
+#[tokio::test]
+async fn drafts_can_be_edited() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    app.post_newsletters(&newsletter_request_body).await;
+    let newsletter_issue_id = app.latest_newsletter_issue_id().await;
+
+    let response = app
+        .post_edit_newsletter(
+            newsletter_issue_id,
+            &serde_json::json!({
+                "title": "A better title",
+                "text_content": "Newsletter body as plain text",
+                "html_content": "<p>Newsletter body as HTML</p>",
+            }),
+        )
+        .await;
+    helpers::assert_is_redirect_to(
+        &response,
+        &format!("/admin/newsletters/{}/edit", newsletter_issue_id),
+    );
+
+    let issue = sqlx::query!("SELECT title FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(issue.title, "A better title");
+}
+
+#[tokio::test]
+async fn published_issues_can_no_longer_be_edited() {
+    let app = helpers::spawn_app().await;
+    app.create_confirmed_subscriber(&random_email()).await;
+    app.test_user.login(&app).await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    app.publish_newsletter(&newsletter_request_body).await;
+    let newsletter_issue_id = app.latest_newsletter_issue_id().await;
+
+    let response = app
+        .post_edit_newsletter(
+            newsletter_issue_id,
+            &serde_json::json!({
+                "title": "A better title",
+                "text_content": "Newsletter body as plain text",
+                "html_content": "<p>Newsletter body as HTML</p>",
+            }),
+        )
+        .await;
+    helpers::assert_is_redirect_to(&response, "/admin/newsletters");
+
+    let issue = sqlx::query!("SELECT title, status FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(issue.title, "Newsletter title");
+    assert_eq!(issue.status, "sending");
+}
+
+#[tokio::test]
+async fn publishing_twice_enqueues_a_single_delivery() {
+    let app = helpers::spawn_app().await;
+    app.create_confirmed_subscriber(&random_email()).await;
+    app.test_user.login(&app).await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    app.publish_newsletter(&newsletter_request_body).await;
+    let newsletter_issue_id = app.latest_newsletter_issue_id().await;
+    let response = app.post_publish_newsletter(newsletter_issue_id).await;
+    helpers::assert_is_redirect_to(&response, "/admin/newsletters");
+
+    let n_tasks = sqlx::query!(r#"SELECT COUNT(*) as "n!" FROM issue_delivery_queue"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(n_tasks, 1);
+}
+
+#[tokio::test]
+async fn issues_are_marked_as_sent_once_delivered() {
+    let app = helpers::spawn_app().await;
+    app.create_confirmed_subscriber(&random_email()).await;
+    app.test_user.login(&app).await;
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    app.publish_newsletter(&newsletter_request_body).await;
+    app.dispatch_all_pending_emails().await;
+
+    let issue = sqlx::query!("SELECT status FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(issue.status, "sent");
+}
+
+#[tokio::test]
+async fn issues_can_be_queried_by_publication_date() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+    let before_publishing = chrono::Utc::now();
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    app.publish_newsletter(&newsletter_request_body).await;
+
+    let n_issues = sqlx::query!(
+        r#"
+        SELECT count(*) AS "n!"
+        FROM newsletter_issues
+        WHERE published_at BETWEEN $1 AND now()
+        "#,
+        before_publishing
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .n;
+    assert_eq!(n_issues, 1);
+}
+
+fn random_email() -> String {
+    SafeEmail().fake()
 }
 
 fn when_sending_an_email() -> MockBuilder {
@@ -234,8 +423,8 @@ async fn transient_errors_do_not_cause_duplicate_deliveries_on_retries() {
     "html_content": "<p>Newsletter body as HTML</p>",
     "idempotency_key": uuid::Uuid::new_v4().to_string()
     });
-    create_confirmed_subscriber(&app).await;
-    create_confirmed_subscriber(&app).await;
+    app.create_confirmed_subscriber(&random_email()).await;
+    app.create_confirmed_subscriber(&random_email()).await;
     app.test_user.login(&app).await;
 
     when_sending_an_email()
@@ -250,56 +439,803 @@ async fn transient_errors_do_not_cause_duplicate_deliveries_on_retries() {
         .expect(1)
         .mount(&app.email_server)
         .await;
-
-    let response = app.post_newsletters(&newsletter_request_body).await;
-    assert_eq!(response.status().as_u16(), 500);
-
     when_sending_an_email()
         .respond_with(ResponseTemplate::new(200))
         .expect(1)
         .named("Delivery retry")
         .mount(&app.email_server)
         .await;
-    let response = app.post_newsletters(&newsletter_request_body).await;
+
+    let response = app.publish_newsletter(&newsletter_request_body).await;
     assert_eq!(response.status().as_u16(), 303);
+    app.dispatch_all_pending_emails().await;
 }
 
-async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
-    let name: String = Name().fake();
-    let email: String = SafeEmail().fake();
-    let body = serde_urlencoded::to_string(&serde_json::json!({
-        "name": name,
-        "email": email
-    }))
-    .unwrap();
+#[tokio::test]
+async fn failed_deliveries_are_retried() {
+    let app = helpers::spawn_app().await;
+    app.create_confirmed_subscriber(&random_email()).await;
+    app.test_user.login(&app).await;
 
-    let _mock_guard = Mock::given(path("/"))
-        .and(method("POST"))
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(500))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    when_sending_an_email()
         .respond_with(ResponseTemplate::new(200))
-        .named("Create unconfirmed subscriber")
         .expect(1)
-        .mount_as_scoped(&app.email_server)
+        .named("Delivery retry")
+        .mount(&app.email_server)
         .await;
-    app.post_subscriptions(body.into())
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    let response = app.publish_newsletter(&newsletter_request_body).await;
+    helpers::assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+
+    let n_failed = sqlx::query!(r#"SELECT COUNT(*) as "n!" FROM failed_deliveries"#)
+        .fetch_one(&app.db_pool)
         .await
-        .error_for_status()
-        .unwrap();
+        .unwrap()
+        .n;
+    assert_eq!(n_failed, 0);
+}
+
+#[tokio::test]
+async fn deliveries_refused_for_our_credentials_are_retried() {
+    let app = helpers::spawn_app().await;
+    app.create_confirmed_subscriber(&random_email()).await;
+    app.test_user.login(&app).await;
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(401))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .named("Delivery retry")
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    let response = app.publish_newsletter(&newsletter_request_body).await;
+    helpers::assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
 
-    let email_request = &app
-        .email_server
-        .received_requests()
+    let n_failed = sqlx::query!(r#"SELECT COUNT(*) as "n!" FROM failed_deliveries"#)
+        .fetch_one(&app.db_pool)
         .await
         .unwrap()
-        .pop()
-        .unwrap();
-    app.get_confirmation_links(&email_request)
+        .n;
+    assert_eq!(n_failed, 0);
+    let n_suppressed = sqlx::query!(r#"SELECT COUNT(*) as "n!" FROM suppressed_emails"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(n_suppressed, 0);
 }
 
-async fn create_confirmed_subscriber(app: &TestApp) {
-    let confirmation_link = create_unconfirmed_subscriber(app).await;
-    reqwest::get(confirmation_link.html)
+#[tokio::test]
+async fn the_worker_wakes_up_as_soon_as_a_retry_is_due() {
+    let app = helpers::spawn_app().await;
+    app.create_confirmed_subscriber(&random_email()).await;
+    app.test_user.login(&app).await;
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(500))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .named("Delivery retry")
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    app.publish_newsletter(&newsletter_request_body).await;
+
+    // Nothing but the retry coming due can wake the worker up in time.
+    let mut worker_settings = app.worker_settings.clone();
+    worker_settings.initial_backoff_milliseconds = 1000;
+    worker_settings.poll_interval_milliseconds = 60_000;
+    worker_settings.scheduled_issues_poll_interval_milliseconds = 60_000;
+    let (shutdown, worker) = app.spawn_worker(worker_settings);
+
+    // The confirmation email, the failed delivery and its retry.
+    let requests = app
+        .wait_for_email_requests(3, Duration::from_secs(10))
+        .await;
+    assert_eq!(requests.len(), 3);
+    shutdown.trigger();
+    worker.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn deliveries_are_moved_to_failed_deliveries_when_retries_are_exhausted() {
+    let app = helpers::spawn_app().await;
+    app.create_confirmed_subscriber(&random_email()).await;
+    app.test_user.login(&app).await;
+
+    let max_retries = app.worker_settings.max_retries;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(500))
+        .expect(max_retries as u64 + 1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    let response = app.publish_newsletter(&newsletter_request_body).await;
+    helpers::assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+
+    let n_queued = sqlx::query!(r#"SELECT COUNT(*) as "n!" FROM issue_delivery_queue"#)
+        .fetch_one(&app.db_pool)
         .await
         .unwrap()
-        .error_for_status()
-        .unwrap();
+        .n;
+    assert_eq!(n_queued, 0);
+    let failed = sqlx::query!("SELECT n_retries, last_error FROM failed_deliveries")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch failed delivery.");
+    assert_eq!(failed.n_retries, max_retries);
+    assert!(!failed.last_error.is_empty());
+}
+
+#[tokio::test]
+async fn recipients_rejected_by_the_provider_are_suppressed_without_retries() {
+    let app = helpers::spawn_app().await;
+    app.create_confirmed_subscriber(&random_email()).await;
+    app.test_user.login(&app).await;
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(422).set_body_json(serde_json::json!({
+            "ErrorCode": 406,
+            "Message": "You tried to send to a recipient that has been marked as inactive."
+        })))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    let response = app.publish_newsletter(&newsletter_request_body).await;
+    helpers::assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+
+    let failed = sqlx::query!("SELECT n_retries FROM failed_deliveries")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch failed delivery.");
+    assert_eq!(failed.n_retries, 0);
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.status, "suppressed");
+}
+
+#[tokio::test]
+async fn scheduled_issues_are_not_delivered_before_their_send_time() {
+    let app = helpers::spawn_app().await;
+    app.create_confirmed_subscriber(&random_email()).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_issue_id = app.create_draft().await;
+    let response = app
+        .post_schedule_newsletter(
+            newsletter_issue_id,
+            &serde_json::json!({
+                "send_at": "2999-01-01T09:00",
+                "timezone": "Europe/Rome",
+            }),
+        )
+        .await;
+    helpers::assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+
+    let html_page = app.get_admin_newsletters_html().await;
+    assert!(html_page.contains("The newsletter issue will go out on 2999-01-01 08:00 UTC."));
+    let issue = sqlx::query!("SELECT status FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(issue.status, "scheduled");
+}
+
+#[tokio::test]
+async fn scheduled_issues_are_delivered_once_due() {
+    let app = helpers::spawn_app().await;
+    app.create_confirmed_subscriber(&random_email()).await;
+    app.test_user.login(&app).await;
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_issue_id = app.create_draft().await;
+    app.post_schedule_newsletter(
+        newsletter_issue_id,
+        &serde_json::json!({
+            "send_at": "2999-01-01T09:00",
+            "timezone": "UTC",
+        }),
+    )
+    .await;
+    // Pretend time has passed.
+    sqlx::query!("UPDATE newsletter_issues SET scheduled_for = now() - interval '1 minute'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    app.dispatch_all_pending_emails().await;
+
+    let issue = sqlx::query!("SELECT status FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(issue.status, "sent");
+}
+
+#[tokio::test]
+async fn issues_cannot_be_scheduled_in_the_past() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let newsletter_issue_id = app.create_draft().await;
+    let response = app
+        .post_schedule_newsletter(
+            newsletter_issue_id,
+            &serde_json::json!({
+                "send_at": "2000-01-01T09:00",
+                "timezone": "UTC",
+            }),
+        )
+        .await;
+    helpers::assert_is_redirect_to(
+        &response,
+        &format!("/admin/newsletters/{}/edit", newsletter_issue_id),
+    );
+
+    let issue = sqlx::query!("SELECT status FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(issue.status, "draft");
+}
+
+#[tokio::test]
+async fn issues_cannot_be_scheduled_in_an_unknown_timezone() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let newsletter_issue_id = app.create_draft().await;
+    let response = app
+        .post_schedule_newsletter(
+            newsletter_issue_id,
+            &serde_json::json!({
+                "send_at": "2999-01-01T09:00",
+                "timezone": "Middle/Earth",
+            }),
+        )
+        .await;
+    helpers::assert_is_redirect_to(
+        &response,
+        &format!("/admin/newsletters/{}/edit", newsletter_issue_id),
+    );
+
+    let issue = sqlx::query!("SELECT status FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(issue.status, "draft");
+}
+
+#[tokio::test]
+async fn unknown_timezones_are_escaped_in_the_error_message() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let newsletter_issue_id = app.create_draft().await;
+    app.post_schedule_newsletter(
+        newsletter_issue_id,
+        &serde_json::json!({
+            "send_at": "2999-01-01T09:00",
+            "timezone": "<script>alert(1)</script>",
+        }),
+    )
+    .await;
+
+    let html_page = app.get_edit_newsletter_html(newsletter_issue_id).await;
+    assert!(!html_page.contains("<script>"));
+    assert!(html_page.contains("&lt;script&gt;alert(1)&lt;/script&gt; is not a valid timezone."));
+}
+
+#[tokio::test]
+async fn scheduled_issues_can_be_rescheduled_and_cancelled() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let newsletter_issue_id = app.create_draft().await;
+    for send_at in ["2999-01-01T09:00", "2999-02-01T09:00"] {
+        app.post_schedule_newsletter(
+            newsletter_issue_id,
+            &serde_json::json!({
+                "send_at": send_at,
+                "timezone": "UTC",
+            }),
+        )
+        .await;
+    }
+    let issue =
+        sqlx::query!(r#"SELECT status, scheduled_for as "scheduled_for!" FROM newsletter_issues"#)
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(issue.status, "scheduled");
+    assert_eq!(
+        issue.scheduled_for.format("%Y-%m-%d").to_string(),
+        "2999-02-01"
+    );
+
+    let response = app.post_unschedule_newsletter(newsletter_issue_id).await;
+    helpers::assert_is_redirect_to(&response, "/admin/newsletters");
+
+    let issue = sqlx::query!("SELECT status, scheduled_for FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(issue.status, "draft");
+    assert!(issue.scheduled_for.is_none());
+}
+
+#[tokio::test]
+async fn test_copies_are_sent_without_publishing_the_issue() {
+    let app = helpers::spawn_app().await;
+    app.create_confirmed_subscriber(&random_email()).await;
+    app.test_user.login(&app).await;
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_issue_id = app.create_draft().await;
+    let response = app
+        .post_send_test_newsletter(
+            newsletter_issue_id,
+            &serde_json::json!({
+                "recipients": "editor@example.com, reviewer@example.com",
+            }),
+        )
+        .await;
+    helpers::assert_is_redirect_to(
+        &response,
+        &format!("/admin/newsletters/{}/edit", newsletter_issue_id),
+    );
+
+    // The first request is the subscriber's confirmation email.
+    let email_request = &app.email_server.received_requests().await.unwrap()[1];
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(body["Subject"], "Newsletter title");
+    assert!(body["HtmlBody"].as_str().unwrap().contains("Unsubscribe"));
+    assert!(body["TextBody"].as_str().unwrap().contains("unsubscribe"));
+
+    let issue = sqlx::query!("SELECT status FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(issue.status, "draft");
+    let n_tasks = sqlx::query!(r#"SELECT COUNT(*) as "n!" FROM issue_delivery_queue"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(n_tasks, 0);
+}
+
+#[tokio::test]
+async fn test_copies_are_not_sent_if_an_address_is_invalid() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_issue_id = app.create_draft().await;
+    let response = app
+        .post_send_test_newsletter(
+            newsletter_issue_id,
+            &serde_json::json!({
+                "recipients": "editor@example.com, <b>not-an-email</b>",
+            }),
+        )
+        .await;
+    helpers::assert_is_redirect_to(
+        &response,
+        &format!("/admin/newsletters/{}/edit", newsletter_issue_id),
+    );
+
+    // The invalid address is echoed back, escaped.
+    let html_page = app.get_edit_newsletter_html(newsletter_issue_id).await;
+    assert!(html_page.contains("&lt;b&gt;not-an-email&lt;/b&gt; is not a valid email."));
+}
+
+#[tokio::test]
+async fn newsletter_content_is_personalised_for_each_subscriber() {
+    let app = helpers::spawn_app().await;
+    app.create_confirmed_subscriber(&random_email()).await;
+    app.test_user.login(&app).await;
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Hi {{ subscriber.name }}, read it at {{ issue.archive_url }}",
+        "html_content": "<p>Hi {{subscriber.name}}</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    app.publish_newsletter(&newsletter_request_body).await;
+    app.dispatch_all_pending_emails().await;
+
+    let subscriber = sqlx::query!("SELECT name FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    // The first request is the subscriber's confirmation email.
+    let email_request = &app.email_server.received_requests().await.unwrap()[1];
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    let text_body = body["TextBody"].as_str().unwrap();
+    assert!(text_body.starts_with(&format!(
+        "Hi {}, read it at {}/issues/newsletter-title",
+        subscriber.name, app.base_url
+    )));
+    let html_body = body["HtmlBody"].as_str().unwrap();
+    assert!(html_body.starts_with(&format!(
+        "<p>Hi {}</p>",
+        htmlescape::encode_attribute(&subscriber.name)
+    )));
+}
+
+#[tokio::test]
+async fn each_subscriber_gets_their_own_one_click_unsubscribe_link() {
+    let app = helpers::spawn_app().await;
+    let emails = [random_email(), random_email()];
+    for email in &emails {
+        app.create_confirmed_subscriber(email).await;
+    }
+    app.test_user.login(&app).await;
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    app.publish_newsletter(&newsletter_request_body).await;
+    app.dispatch_all_pending_emails().await;
+
+    // The first two requests are the confirmation emails.
+    let email_requests = &app.email_server.received_requests().await.unwrap()[2..];
+    assert_eq!(email_requests.len(), 2);
+    for email_request in email_requests {
+        let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+        let subscriber_id = sqlx::query!(
+            "SELECT id FROM subscriptions WHERE email = $1",
+            body["To"].as_str().unwrap()
+        )
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .id;
+        let unsubscribe_link = format!(
+            "{}/subscriptions/unsubscribe?subscriber_id={}&token={}",
+            app.base_url,
+            subscriber_id,
+            UnsubscribeToken::generate(subscriber_id, &app.hmac_secret)
+        );
+        let headers = body["Headers"].as_array().unwrap();
+        assert!(headers.contains(&serde_json::json!({
+            "Name": "List-Unsubscribe",
+            "Value": format!("<{}>", unsubscribe_link)
+        })));
+        assert!(headers.contains(&serde_json::json!({
+            "Name": "List-Unsubscribe-Post",
+            "Value": "List-Unsubscribe=One-Click"
+        })));
+    }
+}
+
+#[tokio::test]
+async fn issues_with_unknown_template_variables_are_rejected() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let response = app
+        .post_newsletters(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Hi {{ subscriber.nickname }}",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": uuid::Uuid::new_v4().to_string()
+        }))
+        .await;
+    helpers::assert_is_redirect_to(&response, "/admin/newsletters/new");
+
+    let html_page = app.get_new_newsletter_html().await;
+    assert!(html_page.contains("subscriber.nickname"));
+    let n_issues = sqlx::query!(r#"SELECT COUNT(*) as "n!" FROM newsletter_issues"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(n_issues, 0);
+}
+
+#[tokio::test]
+async fn rejected_template_variables_are_escaped_in_the_error_message() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let response = app
+        .post_newsletters(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Hi {{ <script>alert(1)</script> }}",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": uuid::Uuid::new_v4().to_string()
+        }))
+        .await;
+    helpers::assert_is_redirect_to(&response, "/admin/newsletters/new");
+
+    let html_page = app.get_new_newsletter_html().await;
+    assert!(!html_page.contains("<script>"));
+    assert!(html_page.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+}
+
+#[tokio::test]
+async fn deliveries_are_logged_with_the_provider_message_id() {
+    let app = helpers::spawn_app().await;
+    app.create_confirmed_subscriber(&random_email()).await;
+    app.test_user.login(&app).await;
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "MessageID": "b7bc2f4a-e38e-4336-af7d-e6c392c2f817",
+            "ErrorCode": 0,
+            "Message": "OK"
+        })))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    app.publish_newsletter(&newsletter_request_body).await;
+
+    let delivery = sqlx::query!("SELECT status FROM issue_delivery_log")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(delivery.status, "queued");
+
+    app.dispatch_all_pending_emails().await;
+
+    let delivery = sqlx::query!("SELECT status, provider_message_id FROM issue_delivery_log")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(delivery.status, "sent");
+    assert_eq!(
+        delivery.provider_message_id.as_deref(),
+        Some("b7bc2f4a-e38e-4336-af7d-e6c392c2f817")
+    );
+}
+
+#[tokio::test]
+async fn the_deliveries_page_reports_totals_and_failure_reasons() {
+    let app = helpers::spawn_app().await;
+    app.create_confirmed_subscriber(&random_email()).await;
+    app.create_confirmed_subscriber(&random_email()).await;
+    app.test_user.login(&app).await;
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .up_to_n_times(1)
+        .mount(&app.email_server)
+        .await;
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(422).set_body_json(serde_json::json!({
+            "ErrorCode": 300,
+            "Message": "Invalid email request"
+        })))
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    app.publish_newsletter(&newsletter_request_body).await;
+    app.dispatch_all_pending_emails().await;
+
+    let newsletter_issue_id = app.latest_newsletter_issue_id().await;
+    let html_page = app
+        .get_newsletter_deliveries_html(newsletter_issue_id)
+        .await;
+    assert!(html_page.contains("Progress: 100% of 2 recipients processed."));
+    assert!(html_page.contains("<tr><td>sent</td><td>1</td></tr>"));
+    assert!(html_page.contains("<tr><td>failed</td><td>1</td></tr>"));
+    assert!(html_page.contains("Invalid email request"));
+}
+
+#[tokio::test]
+async fn publishing_an_issue_notifies_listening_workers() {
+    let app = helpers::spawn_app().await;
+    app.create_confirmed_subscriber(&random_email()).await;
+    app.test_user.login(&app).await;
+
+    let mut listener = sqlx::postgres::PgListener::connect_with(&app.db_pool)
+        .await
+        .unwrap();
+    listener.listen("issue_delivery_queue").await.unwrap();
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    app.publish_newsletter(&newsletter_request_body).await;
+
+    let notification = tokio::time::timeout(std::time::Duration::from_secs(5), listener.recv())
+        .await
+        .expect("No notification received.")
+        .unwrap();
+    let newsletter_issue_id = app.latest_newsletter_issue_id().await;
+    assert_eq!(notification.payload(), newsletter_issue_id.to_string());
+}
+
+#[tokio::test]
+async fn concurrent_workers_deliver_each_email_exactly_once() {
+    let app = helpers::spawn_app().await;
+    for _ in 0..5 {
+        app.create_confirmed_subscriber(&random_email()).await;
+    }
+    app.test_user.login(&app).await;
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200))
+        .expect(5)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    app.publish_newsletter(&newsletter_request_body).await;
+
+    // Two worker processes, each running several delivery tasks, all sharing
+    // a quota of two emails per second.
+    let mut worker_settings = app.worker_settings.clone();
+    worker_settings.concurrency = 3;
+    worker_settings.max_emails_per_second = 2;
+    let start = std::time::Instant::now();
+    let workers = [
+        app.spawn_worker(worker_settings.clone()),
+        app.spawn_worker(worker_settings),
+    ];
+
+    // The confirmation emails and one copy of the issue per subscriber.
+    let requests = app
+        .wait_for_email_requests(10, Duration::from_secs(10))
+        .await;
+    assert_eq!(requests.len(), 10);
+    // Two emails go out straight away, the other three wait for the bucket
+    // to be refilled.
+    assert!(start.elapsed() >= Duration::from_millis(1400));
+    for (shutdown, worker) in workers {
+        shutdown.trigger();
+        worker.await.unwrap().unwrap();
+    }
+}
+
+#[tokio::test]
+async fn workers_with_more_tasks_than_the_default_pool_size_deliver_every_email() {
+    let app = helpers::spawn_app().await;
+    for _ in 0..20 {
+        app.create_confirmed_subscriber(&random_email()).await;
+    }
+    app.test_user.login(&app).await;
+
+    when_sending_an_email()
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(200)))
+        .expect(20)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": uuid::Uuid::new_v4().to_string()
+    });
+    app.publish_newsletter(&newsletter_request_body).await;
+
+    // Every task holds a connection while its delivery is in flight.
+    let mut worker_settings = app.worker_settings.clone();
+    worker_settings.concurrency = 16;
+    worker_settings.max_emails_per_second = 1000;
+    let (shutdown, worker) = app.spawn_worker(worker_settings);
+
+    // The confirmation emails and one copy of the issue per subscriber. Tasks
+    // waiting on each other for connections would only give up after the
+    // pool's 2s acquire timeout.
+    let requests = app
+        .wait_for_email_requests(40, Duration::from_millis(1500))
+        .await;
+    assert_eq!(requests.len(), 40);
+    shutdown.trigger();
+    worker.await.unwrap().unwrap();
 }
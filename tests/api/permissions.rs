@@ -0,0 +1,119 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp, TestUser};
+use uuid::Uuid;
+
+async fn login_with_role(app: &TestApp, role: &str) -> TestUser {
+    let user = TestUser::generate_with_role(role);
+    user.store(&app.db_pool).await;
+    user.login(app).await;
+    user
+}
+
+async fn assert_is_forbidden(response: reqwest::Response) {
+    assert_eq!(response.status().as_u16(), 403);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("does not allow you to do this"));
+}
+
+#[tokio::test]
+async fn viewers_can_look_at_issues_but_not_write_them() {
+    // Arrange
+    let app = spawn_app().await;
+    login_with_role(&app, "viewer").await;
+
+    // Act
+    let list_response = app.get_admin_newsletters().await;
+    let create_response = app
+        .post_newsletters(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": Uuid::new_v4().to_string()
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(list_response.status().as_u16(), 200);
+    assert_is_forbidden(create_response).await;
+}
+
+#[tokio::test]
+async fn only_publishers_and_owners_can_publish_an_issue() {
+    // Arrange
+    let app = spawn_app().await;
+    login_with_role(&app, "editor").await;
+    let newsletter_issue_id = app.create_draft().await;
+
+    // Act - Part 1 - Editors write issues, but cannot send them
+    let response = app.post_publish_newsletter(newsletter_issue_id).await;
+    assert_is_forbidden(response).await;
+    let status = sqlx::query!(
+        "SELECT status FROM newsletter_issues WHERE newsletter_issue_id = $1",
+        newsletter_issue_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .status;
+    assert_eq!(status, "draft");
+
+    // Act - Part 2 - Publishers can
+    login_with_role(&app, "publisher").await;
+    let response = app.post_publish_newsletter(newsletter_issue_id).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+}
+
+#[tokio::test]
+async fn only_owners_can_manage_users() {
+    // Arrange
+    let app = spawn_app().await;
+    let colleague = TestUser::generate_with_role("viewer");
+    colleague.store(&app.db_pool).await;
+    login_with_role(&app, "publisher").await;
+
+    // Act
+    let list_response = app.get_admin_users().await;
+    let delete_response = app.post_user_action(colleague.user_id, "delete").await;
+    let dashboard_html = app.get_admin_dashboard_html().await;
+
+    // Assert
+    assert_is_forbidden(list_response).await;
+    assert_is_forbidden(delete_response).await;
+    assert!(!dashboard_html.contains(r#"href="/admin/users""#));
+}
+
+#[tokio::test]
+async fn owners_can_change_the_role_of_other_users() {
+    // Arrange
+    let app = spawn_app().await;
+    let colleague = login_with_role(&app, "viewer").await;
+    assert_is_forbidden(app.get_admin_users().await).await;
+
+    // Act
+    app.test_user.login(&app).await;
+    let response = app.post_user_role(colleague.user_id, "owner").await;
+    assert_is_redirect_to(&response, "/admin/users");
+    assert!(app.get_admin_users_html().await.contains(&format!(
+        "<p><i>The role of {} is now owner.</i></p>",
+        colleague.username
+    )));
+
+    // Assert
+    colleague.login(&app).await;
+    let response = app.get_admin_users().await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn users_cannot_change_their_own_role() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app.post_user_role(app.test_user.user_id, "viewer").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/users");
+    let html_page = app.get_admin_users_html().await;
+    assert!(html_page.contains("<p><i>You cannot change your own role.</i></p>"));
+}
@@ -0,0 +1,60 @@
+use crate::helpers::spawn_app;
+use std::time::{Duration, Instant};
+use zeroToprod_finalll::rate_limiter::TokenBucket;
+
+#[tokio::test]
+async fn a_zero_rate_is_rejected() {
+    let app = spawn_app().await;
+    assert!(TokenBucket::new(app.db_pool.clone(), 0, 1).is_err());
+}
+
+#[tokio::test]
+async fn a_full_bucket_does_not_make_callers_wait() {
+    let app = spawn_app().await;
+    let bucket = TokenBucket::new(app.db_pool.clone(), 1, 5).unwrap();
+    let start = Instant::now();
+    for _ in 0..5 {
+        bucket.acquire().await.unwrap();
+    }
+    // Waiting for a single token would take a second.
+    assert!(start.elapsed() < Duration::from_millis(500));
+}
+
+#[tokio::test]
+async fn callers_are_throttled_to_the_refill_rate_once_the_bucket_is_empty() {
+    let app = spawn_app().await;
+    let bucket = TokenBucket::new(app.db_pool.clone(), 50, 1).unwrap();
+    let start = Instant::now();
+    for _ in 0..6 {
+        bucket.acquire().await.unwrap();
+    }
+    // One token was there from the start, the other five take 20ms each.
+    assert!(start.elapsed() >= Duration::from_millis(100));
+}
+
+#[tokio::test]
+async fn buckets_backed_by_the_same_database_share_their_tokens() {
+    let app = spawn_app().await;
+    // As if two worker processes were running side by side.
+    let first = TokenBucket::new(app.db_pool.clone(), 10, 1).unwrap();
+    let second = TokenBucket::new(app.db_pool.clone(), 10, 1).unwrap();
+    let start = Instant::now();
+    for _ in 0..3 {
+        first.acquire().await.unwrap();
+        second.acquire().await.unwrap();
+    }
+    // One token was there from the start, the other five take 100ms each.
+    assert!(start.elapsed() >= Duration::from_millis(500));
+}
+
+#[tokio::test]
+async fn released_tokens_can_be_acquired_again_without_waiting() {
+    let app = spawn_app().await;
+    let bucket = TokenBucket::new(app.db_pool.clone(), 1, 1).unwrap();
+    bucket.acquire().await.unwrap();
+    bucket.release().await.unwrap();
+    let start = Instant::now();
+    bucket.acquire().await.unwrap();
+    // Waiting for a refill would take a second.
+    assert!(start.elapsed() < Duration::from_millis(500));
+}
@@ -0,0 +1,171 @@
+use crate::helpers::spawn_app;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+const EMAIL: &str = "ursula_le_guin@gmail.com";
+const UNIFORM_RESPONSE: &str = "If this address is waiting to be confirmed";
+
+#[tokio::test]
+async fn pending_subscribers_get_a_new_confirmation_email() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_unconfirmed_subscriber(EMAIL).await;
+    let first_email = &app.email_server.received_requests().await.unwrap()[0];
+    let first_links = app.get_confirmation_links(first_email);
+    Mock::given(path("/"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_resend_confirmation(EMAIL).await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    assert!(response.text().await.unwrap().contains(UNIFORM_RESPONSE));
+    let new_email = &app.email_server.received_requests().await.unwrap()[1];
+    let new_links = app.get_confirmation_links(new_email);
+    // Only the latest link works
+    let response = reqwest::get(first_links.html).await.unwrap();
+    assert_eq!(401, response.status().as_u16());
+    reqwest::get(new_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+#[tokio::test]
+async fn unknown_and_confirmed_addresses_get_the_same_response_and_no_email() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_unconfirmed_subscriber(EMAIL).await;
+    sqlx::query!("UPDATE subscriptions SET status = 'confirmed'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    Mock::given(path("/"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    for email in [EMAIL, "octavia_butler@gmail.com"] {
+        // Act
+        let response = app.post_resend_confirmation(email).await;
+
+        // Assert
+        assert_eq!(200, response.status().as_u16());
+        assert!(response.text().await.unwrap().contains(UNIFORM_RESPONSE));
+    }
+}
+
+#[tokio::test]
+async fn resending_is_rate_limited_per_address() {
+    // Arrange
+    let app = spawn_app().await;
+    app.create_unconfirmed_subscriber(EMAIL).await;
+    Mock::given(path("/"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(3)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    for _ in 0..3 {
+        let response = app.post_resend_confirmation(EMAIL).await;
+        assert_eq!(200, response.status().as_u16());
+    }
+    let response = app.post_resend_confirmation(EMAIL).await;
+
+    // Assert
+    assert_eq!(429, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn resending_is_rate_limited_per_ip_address() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    for i in 0..20 {
+        let response = app
+            .post_resend_confirmation(&format!("reader{}@gmail.com", i))
+            .await;
+        assert_eq!(200, response.status().as_u16());
+    }
+    let response = app.post_resend_confirmation(EMAIL).await;
+
+    // Assert
+    assert_eq!(429, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn spoofing_x_forwarded_for_does_not_get_around_the_limit_per_ip_address() {
+    // Arrange
+    let app = spawn_app().await;
+    let resend_confirmation = |i: u32| {
+        app.api_client
+            .post(&format!(
+                "{}/subscriptions/resend_confirmation",
+                &app.address
+            ))
+            .header("X-Forwarded-For", format!("203.0.113.{}", i))
+            .form(&[("email", format!("reader{}@gmail.com", i))])
+            .send()
+    };
+
+    // Act
+    for i in 0..20 {
+        let response = resend_confirmation(i).await.unwrap();
+        assert_eq!(200, response.status().as_u16());
+    }
+    let response = resend_confirmation(20).await.unwrap();
+
+    // Assert
+    assert_eq!(429, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn concurrent_requests_cannot_get_around_the_limit_per_address() {
+    // Arrange
+    let app = spawn_app().await;
+    let mut requests = tokio::task::JoinSet::new();
+
+    // Act
+    for _ in 0..10 {
+        let request = app
+            .api_client
+            .post(&format!(
+                "{}/subscriptions/resend_confirmation",
+                &app.address
+            ))
+            .form(&[("email", EMAIL)]);
+        requests.spawn(async move { request.send().await.unwrap().status().as_u16() });
+    }
+    let mut n_accepted = 0;
+    while let Some(status) = requests.join_next().await {
+        if status.unwrap() == 200 {
+            n_accepted += 1;
+        }
+    }
+
+    // Assert
+    assert_eq!(n_accepted, 3);
+}
+
+#[tokio::test]
+async fn invalid_addresses_are_rejected_with_a_400() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.post_resend_confirmation("not-an-email").await;
+
+    // Assert
+    assert_eq!(400, response.status().as_u16());
+}
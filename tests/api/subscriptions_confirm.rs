@@ -43,3 +43,116 @@ async fn clicking_on_the_confirmation_link_confirms_a_subscriber() {
     assert_eq!(saved.name, "le guin");
     assert_eq!(saved.status, "confirmed");
 }
+
+#[tokio::test]
+async fn confirmations_with_an_unknown_token_are_rejected_with_a_401() {
+    let app = spawn_app().await;
+
+    let response = reqwest::get(&format!(
+        "{}/subscriptions/confirm?subscription_token=not-a-real-token",
+        app.address
+    ))
+    .await
+    .unwrap();
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("This confirmation link is not valid."));
+}
+
+#[tokio::test]
+async fn clicking_on_the_confirmation_link_shows_a_confirmation_page() {
+    let app = spawn_app().await;
+    let confirmation_links = app
+        .create_unconfirmed_subscriber("ursula_le_guin@gmail.com")
+        .await;
+
+    let response = reqwest::get(confirmation_links.html).await.unwrap();
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("Thanks for confirming your subscription!"));
+}
+
+#[tokio::test]
+async fn confirmation_tokens_can_only_be_used_once() {
+    let app = spawn_app().await;
+    let confirmation_links = app
+        .create_unconfirmed_subscriber("ursula_le_guin@gmail.com")
+        .await;
+    reqwest::get(confirmation_links.html.clone())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    let response = reqwest::get(confirmation_links.html).await.unwrap();
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("Your subscription is already confirmed"));
+    let token = sqlx::query!("SELECT consumed_at FROM subscription_tokens")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(token.consumed_at.is_some());
+}
+
+#[tokio::test]
+async fn unused_tokens_of_unsubscribed_subscribers_are_rejected_with_a_401() {
+    let app = spawn_app().await;
+    let confirmation_links = app
+        .create_unconfirmed_subscriber("ursula_le_guin@gmail.com")
+        .await;
+    sqlx::query!("UPDATE subscriptions SET status = 'unsubscribed', unsubscribed_at = now()")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let response = reqwest::get(confirmation_links.html).await.unwrap();
+
+    assert_eq!(response.status().as_u16(), 401);
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "unsubscribed");
+    let token = sqlx::query!("SELECT consumed_at FROM subscription_tokens")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(token.consumed_at.is_none());
+}
+
+#[tokio::test]
+async fn expired_confirmation_tokens_are_rejected_with_a_410() {
+    let app = spawn_app().await;
+    let confirmation_links = app
+        .create_unconfirmed_subscriber("ursula_le_guin@gmail.com")
+        .await;
+    sqlx::query!("UPDATE subscription_tokens SET created_at = now() - interval '1 week'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let response = reqwest::get(confirmation_links.html).await.unwrap();
+
+    assert_eq!(response.status().as_u16(), 410);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("This confirmation link has expired."));
+    assert!(html_page.contains(r#"action="/subscriptions/resend_confirmation""#));
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.status, "pending_confirmation");
+}
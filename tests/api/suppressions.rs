@@ -0,0 +1,209 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use uuid::Uuid;
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+const EMAIL: &str = "ursula_le_guin@gmail.com";
+
+async fn suppress(app: &TestApp, email: &str) {
+    let response = app
+        .post_suppression(&serde_json::json!({
+            "email": email,
+            "reason": "Asked us to stop emailing them"
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/suppressions");
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_the_suppression_list() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client
+        .get(&format!("{}/admin/suppressions", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn admins_can_add_search_and_remove_suppressed_addresses() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act - Part 1 - Add two addresses
+    suppress(&app, EMAIL).await;
+    let html_page = app.get_suppressions_html("").await;
+    assert!(html_page.contains("ursula_le_guin@gmail.com has been added to the suppression list."));
+    suppress(&app, "octavia_butler@gmail.com").await;
+    let html_page = app.get_suppressions_html("").await;
+    assert!(html_page.contains("octavia_butler@gmail.com has been added to the suppression list."));
+
+    // Act - Part 2 - Search
+    let html_page = app.get_suppressions_html("le_guin").await;
+    assert!(html_page.contains(EMAIL));
+    assert!(html_page.contains("Asked us to stop emailing them"));
+    assert!(!html_page.contains("octavia_butler@gmail.com"));
+
+    // Act - Part 3 - Remove
+    let response = app.post_remove_suppression(EMAIL).await;
+    assert_is_redirect_to(&response, "/admin/suppressions");
+    let html_page = app.get_suppressions_html("").await;
+    assert!(
+        html_page.contains("ursula_le_guin@gmail.com has been removed from the suppression list.")
+    );
+    assert!(html_page.contains("octavia_butler@gmail.com"));
+    let n_entries = sqlx::query!(r#"SELECT count(*) AS "n!" FROM suppressed_emails"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(n_entries, 1);
+}
+
+#[tokio::test]
+async fn invalid_addresses_cannot_be_suppressed() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    suppress(&app, "not-an-email").await;
+
+    // Assert
+    let html_page = app.get_suppressions_html("").await;
+    assert!(html_page.contains("No suppressed addresses."));
+}
+
+#[tokio::test]
+async fn removing_an_invalid_address_does_not_echo_it_back_as_html() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .post_remove_suppression("<script>alert(1)</script>")
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/suppressions");
+    let html_page = app.get_suppressions_html("").await;
+    assert!(html_page.contains("&lt;script&gt;alert(1)&lt;/script&gt; is not a valid email."));
+    assert!(!html_page.contains("<script>"));
+}
+
+#[tokio::test]
+async fn suppressed_addresses_do_not_get_a_confirmation_email() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    // The suppression list ignores case.
+    suppress(&app, "Ursula_Le_Guin@gmail.com").await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+
+    // Assert
+    assert_eq!(409, response.status().as_u16());
+    let n_subscribers = sqlx::query!(r#"SELECT COUNT(*) as "n!" FROM subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(n_subscribers, 0);
+}
+
+#[tokio::test]
+async fn suppressed_addresses_do_not_get_newsletters_under_a_new_subscription() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    suppress(&app, EMAIL).await;
+    // A subscription created behind the suppression list's back, e.g. by an import.
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriptions (id, email, name, subscribed_at, status)
+        VALUES ($1, $2, 'le guin', now(), 'confirmed')
+        "#,
+        Uuid::new_v4(),
+        EMAIL
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    Mock::given(path("/"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.publish_newsletter(&serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    }))
+    .await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let delivery = sqlx::query!("SELECT status FROM issue_delivery_log")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(delivery.status, "skipped_suppressed");
+}
+
+#[tokio::test]
+async fn test_copies_are_not_sent_to_suppressed_addresses() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    suppress(&app, EMAIL).await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    let response = app
+        .post_newsletters(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": Uuid::new_v4().to_string()
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let newsletter_issue_id = app.latest_newsletter_issue_id().await;
+
+    // Act
+    let response = app
+        .post_send_test_newsletter(
+            newsletter_issue_id,
+            &serde_json::json!({ "recipients": EMAIL }),
+        )
+        .await;
+
+    // Assert
+    assert_is_redirect_to(
+        &response,
+        &format!("/admin/newsletters/{}/edit", newsletter_issue_id),
+    );
+}
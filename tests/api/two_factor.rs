@@ -0,0 +1,253 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use chrono::{Duration, Utc};
+use zeroToprod_finalll::domain::TotpSecret;
+
+/// Enable two-factor authentication for the test user, returning their
+/// secret and recovery codes.
+async fn enroll(app: &TestApp) -> (TotpSecret, Vec<String>) {
+    app.test_user.login(app).await;
+    let html_page = app.get_two_factor_settings_html().await;
+    let secret = html_page
+        .split("secret=")
+        .nth(1)
+        .unwrap()
+        .split('&')
+        .next()
+        .unwrap();
+    let secret = TotpSecret::parse(secret).unwrap();
+    let response = app
+        .post_enable_two_factor(&secret.code_at(Utc::now()))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    let recovery_codes = html_page
+        .split("<li><code>")
+        .skip(1)
+        .map(|s| s.split("</code>").next().unwrap().to_string())
+        .collect();
+    app.post_logout().await;
+    (secret, recovery_codes)
+}
+
+/// A code the replay protection has not seen yet: enrolling used up the
+/// current one.
+fn next_code(secret: &TotpSecret) -> String {
+    secret.code_at(Utc::now() + Duration::seconds(30))
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_manage_two_factor_authentication() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.post_enable_two_factor("123456").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn enrolling_requires_a_valid_code_and_shows_recovery_codes() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let html_page = app.get_two_factor_settings_html().await;
+    assert!(html_page.contains("Two-factor authentication is disabled."));
+    assert!(html_page.contains(&format!(
+        "otpauth://totp/Newsletter:{}?secret=",
+        app.test_user.username
+    )));
+
+    // Act - Part 1 - A wrong code
+    let response = app.post_enable_two_factor("not-a-code").await;
+    assert_is_redirect_to(&response, "/admin/two_factor");
+    let html_page = app.get_two_factor_settings_html().await;
+    assert!(
+        html_page.contains("<p><i>The code is invalid: check the clock of your device.</i></p>")
+    );
+
+    // Act - Part 2 - Enroll for real
+    app.post_logout().await;
+    let (_, recovery_codes) = enroll(&app).await;
+
+    // Assert
+    assert_eq!(recovery_codes.len(), 10);
+    app.test_user.login(&app).await;
+    app.post_login_two_factor(&recovery_codes[0]).await;
+    let html_page = app.get_two_factor_settings_html().await;
+    assert!(html_page.contains("Two-factor authentication is enabled."));
+    assert!(html_page.contains("You have 9 unused recovery codes left."));
+}
+
+#[tokio::test]
+async fn the_password_alone_does_not_log_in_a_user_with_two_factor_authentication() {
+    // Arrange
+    let app = spawn_app().await;
+    let (secret, _) = enroll(&app).await;
+
+    // Act - Part 1 - Password
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/login/two_factor");
+    assert_is_redirect_to(&app.get_admin_dashboard().await, "/login");
+
+    // Act - Part 2 - Code
+    let response = app.post_login_two_factor(&next_code(&secret)).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    let html_page = app.get_admin_dashboard_html().await;
+    assert!(html_page.contains(&format!("Welcome {}", app.test_user.username)));
+}
+
+#[tokio::test]
+async fn the_two_factor_form_requires_a_verified_password() {
+    // Arrange
+    let app = spawn_app().await;
+    let (secret, _) = enroll(&app).await;
+
+    // Act
+    let form_response = app.get_login_two_factor().await;
+    let response = app.post_login_two_factor(&next_code(&secret)).await;
+
+    // Assert
+    assert_is_redirect_to(&form_response, "/login");
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn codes_cannot_be_reused() {
+    // Arrange
+    let app = spawn_app().await;
+    let (secret, _) = enroll(&app).await;
+    let code = next_code(&secret);
+    app.test_user.login(&app).await;
+    app.post_login_two_factor(&code).await;
+    app.post_logout().await;
+
+    // Act
+    app.test_user.login(&app).await;
+    let response = app.post_login_two_factor(&code).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login/two_factor");
+    let html_page = app.get_login_two_factor().await.text().await.unwrap();
+    assert!(html_page.contains("<p><i>The code is invalid.</i></p>"));
+    assert_is_redirect_to(&app.get_admin_dashboard().await, "/login");
+}
+
+#[tokio::test]
+async fn recovery_codes_work_only_once() {
+    // Arrange
+    let app = spawn_app().await;
+    let (_, recovery_codes) = enroll(&app).await;
+
+    // Act - Part 1 - Users may type recovery codes without the dash
+    app.test_user.login(&app).await;
+    let response = app
+        .post_login_two_factor(&recovery_codes[0].replace('-', "").to_uppercase())
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    app.post_logout().await;
+
+    // Act - Part 2
+    app.test_user.login(&app).await;
+    let response = app.post_login_two_factor(&recovery_codes[0]).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login/two_factor");
+}
+
+#[tokio::test]
+async fn too_many_invalid_codes_lock_two_factor_authentication() {
+    // Arrange
+    let app = spawn_app().await;
+    let (secret, _) = enroll(&app).await;
+    app.test_user.login(&app).await;
+
+    // Act
+    for _ in 0..4 {
+        let response = app.post_login_two_factor("not-a-code").await;
+        assert_is_redirect_to(&response, "/login/two_factor");
+    }
+    let response = app.post_login_two_factor("not-a-code").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    assert!(app
+        .get_login_html()
+        .await
+        .contains("<p><i>Too many invalid codes: please try again later.</i></p>"));
+    // Even a valid code is refused until the lockout expires.
+    app.test_user.login(&app).await;
+    let response = app.post_login_two_factor(&next_code(&secret)).await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn logging_in_again_does_not_reset_invalid_code_attempts() {
+    // Arrange
+    let app = spawn_app().await;
+    enroll(&app).await;
+    app.test_user.login(&app).await;
+    for _ in 0..4 {
+        let response = app.post_login_two_factor("not-a-code").await;
+        assert_is_redirect_to(&response, "/login/two_factor");
+    }
+
+    // Act
+    app.test_user.login(&app).await;
+    let response = app.post_login_two_factor("not-a-code").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    let attempts = sqlx::query!(
+        "SELECT two_factor_failed_attempts FROM users WHERE user_id = $1",
+        app.test_user.user_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .two_factor_failed_attempts;
+    assert_eq!(attempts, 5);
+}
+
+#[tokio::test]
+async fn disabling_two_factor_authentication_requires_a_valid_code() {
+    // Arrange
+    let app = spawn_app().await;
+    let (_, recovery_codes) = enroll(&app).await;
+    app.test_user.login(&app).await;
+    app.post_login_two_factor(&recovery_codes[0]).await;
+
+    // Act - Part 1 - A wrong code
+    let response = app.post_disable_two_factor("not-a-code").await;
+    assert_is_redirect_to(&response, "/admin/two_factor");
+    assert!(app
+        .get_two_factor_settings_html()
+        .await
+        .contains("<p><i>The code is invalid.</i></p>"));
+
+    // Act - Part 2 - A valid one
+    let response = app.post_disable_two_factor(&recovery_codes[1]).await;
+    assert_is_redirect_to(&response, "/admin/two_factor");
+    assert!(app
+        .get_two_factor_settings_html()
+        .await
+        .contains("<p><i>Two-factor authentication has been disabled.</i></p>"));
+
+    // Assert
+    app.post_logout().await;
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
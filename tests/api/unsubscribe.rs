@@ -0,0 +1,101 @@
+use crate::helpers::spawn_app;
+use uuid::Uuid;
+
+#[tokio::test]
+async fn unsubscribe_links_with_an_invalid_token_are_rejected_with_a_401() {
+    let app = spawn_app().await;
+    let subscriber_id = app
+        .create_confirmed_subscriber("ursula_le_guin@gmail.com")
+        .await;
+    let link = format!(
+        "{}/subscriptions/unsubscribe?subscriber_id={}&token=not-a-valid-token",
+        app.address, subscriber_id
+    );
+
+    let response = reqwest::get(&link).await.unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = reqwest::Client::new().post(&link).send().await.unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.status, "confirmed");
+}
+
+#[tokio::test]
+async fn the_unsubscribe_link_shows_a_confirmation_form() {
+    let app = spawn_app().await;
+    let subscriber_id = app
+        .create_confirmed_subscriber("ursula_le_guin@gmail.com")
+        .await;
+
+    let response = reqwest::get(app.get_unsubscribe_link(subscriber_id))
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains(r#"<form action="/subscriptions/unsubscribe?"#));
+
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.status, "confirmed");
+}
+
+#[tokio::test]
+async fn posting_to_the_unsubscribe_link_unsubscribes_a_subscriber() {
+    let app = spawn_app().await;
+    let subscriber_id = app
+        .create_confirmed_subscriber("ursula_le_guin@gmail.com")
+        .await;
+
+    let response = reqwest::Client::new()
+        .post(app.get_unsubscribe_link(subscriber_id))
+        .form(&[("List-Unsubscribe", "One-Click")])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+
+    let saved = sqlx::query!("SELECT status, unsubscribed_at FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.status, "unsubscribed");
+    assert!(saved.unsubscribed_at.is_some());
+}
+
+#[tokio::test]
+async fn newsletters_are_not_enqueued_for_unsubscribed_subscribers() {
+    let app = spawn_app().await;
+    let subscriber_id = app
+        .create_confirmed_subscriber("ursula_le_guin@gmail.com")
+        .await;
+    reqwest::Client::new()
+        .post(app.get_unsubscribe_link(subscriber_id))
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    app.test_user.login(&app).await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    app.publish_newsletter(&newsletter_request_body).await;
+
+    let n_tasks = sqlx::query!(r#"SELECT COUNT(*) as "n!" FROM issue_delivery_queue"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n;
+    assert_eq!(n_tasks, 0);
+}
@@ -0,0 +1,400 @@
+use crate::helpers::{api_client, assert_is_redirect_to, spawn_app, TestApp, TestUser};
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+const EMAIL: &str = "ursula_le_guin@gmail.com";
+
+/// The set-password link in the latest email we sent.
+async fn last_set_password_link(app: &TestApp) -> reqwest::Url {
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    app.get_confirmation_links(&email_request).html
+}
+
+fn token(set_password_link: &reqwest::Url) -> String {
+    set_password_link
+        .query_pairs()
+        .find(|(key, _)| key == "token")
+        .unwrap()
+        .1
+        .into_owned()
+}
+
+async fn invited_user_id(app: &TestApp, username: &str) -> Uuid {
+    sqlx::query!("SELECT user_id FROM users WHERE username = $1", username)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .user_id
+}
+
+/// Log in with a client of its own, so that the test user's session is left alone.
+async fn login_as(app: &TestApp, username: &str, password: &str) -> reqwest::Client {
+    let client = api_client();
+    let response = client
+        .post(&format!("{}/login", &app.address))
+        .form(&[("username", username), ("password", password)])
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    client
+}
+
+async fn get_dashboard(app: &TestApp, client: &reqwest::Client) -> reqwest::Response {
+    client
+        .get(&format!("{}/admin/dashboard", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_manage_users() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let list_response = app
+        .api_client
+        .get(&format!("{}/admin/users", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    let invite_response = app.post_invite_user("ursula", EMAIL, "editor").await;
+
+    // Assert
+    assert_is_redirect_to(&list_response, "/login");
+    assert_is_redirect_to(&invite_response, "/login");
+}
+
+#[tokio::test]
+async fn invited_users_choose_their_password_and_can_log_in() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    Mock::given(path("/"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let password = Uuid::new_v4().to_string();
+
+    // Act - Part 1 - Invite a colleague
+    let response = app.post_invite_user("ursula", EMAIL, "editor").await;
+    assert_is_redirect_to(&response, "/admin/users");
+    let html_page = app.get_admin_users_html().await;
+    assert!(html_page.contains(&format!(
+        "<p><i>ursula has been invited: they will receive an email at {}.</i></p>",
+        EMAIL
+    )));
+    assert!(html_page.contains("Waiting for a password"));
+
+    // Act - Part 2 - They cannot log in before choosing a password
+    let response = api_client()
+        .post(&format!("{}/login", &app.address))
+        .form(&[("username", "ursula"), ("password", "")])
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_is_redirect_to(&response, "/login");
+
+    // Act - Part 3 - Follow the link
+    let set_password_link = last_set_password_link(&app).await;
+    let html_page = reqwest::get(set_password_link.clone())
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains("Choose the password you will log in with as ursula."));
+    let response = app
+        .post_set_password(&serde_json::json!({
+            "token": token(&set_password_link),
+            "password": &password,
+            "password_check": &password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/login");
+
+    // Assert
+    let client = login_as(&app, "ursula", &password).await;
+    let html_page = get_dashboard(&app, &client).await.text().await.unwrap();
+    assert!(html_page.contains("Welcome ursula!"));
+    assert!(html_page.contains("Your role: editor"));
+}
+
+#[tokio::test]
+async fn set_password_links_work_only_once() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    Mock::given(path("/"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    app.post_invite_user("ursula", EMAIL, "editor").await;
+    let set_password_link = last_set_password_link(&app).await;
+    let password = Uuid::new_v4().to_string();
+    let body = serde_json::json!({
+        "token": token(&set_password_link),
+        "password": &password,
+        "password_check": &password,
+    });
+    app.post_set_password(&body).await;
+
+    // Act
+    let form_response = reqwest::get(set_password_link).await.unwrap();
+    let response = app.post_set_password(&body).await;
+
+    // Assert
+    assert_eq!(form_response.status().as_u16(), 401);
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn passwords_must_match_and_have_a_valid_length() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    Mock::given(path("/"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    app.post_invite_user("ursula", EMAIL, "editor").await;
+    let set_password_link = last_set_password_link(&app).await;
+    let token = token(&set_password_link);
+    let test_cases = vec![
+        (
+            Uuid::new_v4().to_string(),
+            Uuid::new_v4().to_string(),
+            "You entered two different passwords - the field values must match.",
+        ),
+        (
+            "too-short".to_string(),
+            "too-short".to_string(),
+            "The password must contain at least 13 and shorten then 128 chars.",
+        ),
+    ];
+
+    for (password, password_check, error_message) in test_cases {
+        // Act
+        let response = app
+            .post_set_password(&serde_json::json!({
+                "token": &token,
+                "password": password,
+                "password_check": password_check,
+            }))
+            .await;
+
+        // Assert
+        assert_is_redirect_to(&response, &format!("/users/set_password?token={}", token));
+        let html_page = app
+            .api_client
+            .get(set_password_link.clone())
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(html_page.contains(&format!("<p><i>{}</i></p>", error_message)));
+    }
+}
+
+#[tokio::test]
+async fn invitations_require_a_unique_username_and_a_valid_email() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    Mock::given(path("/"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    let test_cases = vec![
+        (
+            app.test_user.username.as_str(),
+            EMAIL,
+            "A user with this username or email already exists.",
+        ),
+        (
+            "",
+            EMAIL,
+            "The username must contain between 1 and 64 characters.",
+        ),
+        (
+            "ursula",
+            "definitely-not-an-email",
+            "definitely-not-an-email is not a valid email.",
+        ),
+        (
+            "ursula",
+            "<script>alert(1)</script>",
+            "&lt;script&gt;alert(1)&lt;/script&gt; is not a valid email.",
+        ),
+    ];
+
+    for (username, email, error_message) in test_cases {
+        // Act
+        let response = app.post_invite_user(username, email, "editor").await;
+
+        // Assert
+        assert_is_redirect_to(&response, "/admin/users");
+        let html_page = app.get_admin_users_html().await;
+        assert!(
+            html_page.contains(&format!("<p><i>{}</i></p>", error_message)),
+            "No error for username {:?} and email {:?}.",
+            username,
+            email
+        );
+    }
+}
+
+#[tokio::test]
+async fn deactivated_users_are_logged_out_until_activated_again() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let colleague = TestUser::generate();
+    colleague.store(&app.db_pool).await;
+    let client = login_as(&app, &colleague.username, &colleague.password).await;
+
+    // Act - Part 1 - Deactivate
+    let response = app.post_user_action(colleague.user_id, "deactivate").await;
+    assert_is_redirect_to(&response, "/admin/users");
+
+    // Assert - Part 1
+    assert_is_redirect_to(&get_dashboard(&app, &client).await, "/login");
+    let response = api_client()
+        .post(&format!("{}/login", &app.address))
+        .form(&[
+            ("username", &colleague.username),
+            ("password", &colleague.password),
+        ])
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_is_redirect_to(&response, "/login");
+
+    // Act - Part 2 - Activate
+    let response = app.post_user_action(colleague.user_id, "activate").await;
+    assert_is_redirect_to(&response, "/admin/users");
+
+    // Assert - Part 2
+    login_as(&app, &colleague.username, &colleague.password).await;
+}
+
+#[tokio::test]
+async fn deleted_users_are_logged_out() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let colleague = TestUser::generate();
+    colleague.store(&app.db_pool).await;
+    let client = login_as(&app, &colleague.username, &colleague.password).await;
+
+    // Act
+    let response = app.post_user_action(colleague.user_id, "delete").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/users");
+    assert_is_redirect_to(&get_dashboard(&app, &client).await, "/login");
+    let html_page = app.get_admin_users_html().await;
+    assert!(html_page.contains(&format!(
+        "<p><i>{} has been deleted.</i></p>",
+        colleague.username
+    )));
+    let n_users = sqlx::query!(
+        "SELECT count(*) AS \"count!\" FROM users WHERE user_id = $1",
+        colleague.user_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .count;
+    assert_eq!(n_users, 0);
+}
+
+#[tokio::test]
+async fn a_password_reset_logs_the_user_out_and_emails_them_a_new_link() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    Mock::given(path("/"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+    app.post_invite_user("ursula", EMAIL, "editor").await;
+    let old_password = Uuid::new_v4().to_string();
+    app.post_set_password(&serde_json::json!({
+        "token": token(&last_set_password_link(&app).await),
+        "password": &old_password,
+        "password_check": &old_password,
+    }))
+    .await;
+    let client = login_as(&app, "ursula", &old_password).await;
+    let user_id = invited_user_id(&app, "ursula").await;
+
+    // Act
+    let response = app.post_user_action(user_id, "reset_password").await;
+    assert_is_redirect_to(&response, "/admin/users");
+
+    // Assert
+    assert_is_redirect_to(&get_dashboard(&app, &client).await, "/login");
+    let response = api_client()
+        .post(&format!("{}/login", &app.address))
+        .form(&[("username", "ursula"), ("password", old_password.as_str())])
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_is_redirect_to(&response, "/login");
+    let new_password = Uuid::new_v4().to_string();
+    let response = app
+        .post_set_password(&serde_json::json!({
+            "token": token(&last_set_password_link(&app).await),
+            "password": &new_password,
+            "password_check": &new_password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/login");
+    login_as(&app, "ursula", &new_password).await;
+}
+
+#[tokio::test]
+async fn users_cannot_lock_themselves_out() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    for (action, error_message) in [
+        ("deactivate", "You cannot deactivate yourself."),
+        ("delete", "You cannot delete yourself."),
+        (
+            "reset_password",
+            "Change your own password from the password page instead.",
+        ),
+    ] {
+        // Act
+        let response = app.post_user_action(app.test_user.user_id, action).await;
+
+        // Assert
+        assert_is_redirect_to(&response, "/admin/users");
+        let html_page = app.get_admin_users_html().await;
+        assert!(html_page.contains(&format!("<p><i>{}</i></p>", error_message)));
+    }
+    let response = app.get_admin_dashboard().await;
+    assert_eq!(response.status().as_u16(), 200);
+}